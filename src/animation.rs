@@ -0,0 +1,348 @@
+//! Animated wallpapers.
+//!
+//! An animated pipeline is rendered once for every frame, with a
+//! context whose time runs from `0.0` at the first frame up towards
+//! `1.0` after the last.  Everything that moves is back where it
+//! started by then, so the animation loops without a jump.  Frames
+//! are written out one at a time as they're rendered, either into a
+//! single animated GIF or APNG, or as a numbered image each.
+
+use crate::config::Config;
+use crate::context::Context;
+use crate::error::{Error, Result};
+use crate::position::Position;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Animation {
+    /// How long one loop of the animation lasts, in seconds.
+    pub length: f64,
+    /// GIFs time their frames in hundredths of a second, so their
+    /// frame rate is rounded to the closest one they can manage.
+    #[serde(default = "default_fps")]
+    pub fps: u32,
+}
+
+fn default_fps() -> u32 {
+    30
+}
+
+impl Animation {
+    /// The number of frames in one loop of the animation.
+    pub fn frames(&self) -> Result<u32> {
+        if self.fps == 0 {
+            return Err(Error::parameter("animation", "fps", "must be above 0"));
+        }
+        let frames = (self.length * self.fps as f64).round();
+        if frames < 1.0 || !frames.is_finite() {
+            return Err(Error::parameter(
+                "animation",
+                "length",
+                format!("must last at least one frame, not {} seconds", self.length),
+            ));
+        }
+        Ok(frames as u32)
+    }
+
+    /// The context for every frame of the animation, in order.
+    pub fn contexts(&self, context: &Context) -> Result<impl Iterator<Item = Context>> {
+        let frames = self.frames()?;
+        let context = *context;
+        Ok((0..frames).map(move |frame| context.at_frame(frame, frames)))
+    }
+}
+
+/// How the frames of an animation are written.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Encoding {
+    /// An animated GIF, which loops forever.
+    Gif,
+    /// An animated PNG, which loops forever, and has the pipeline
+    /// recorded in it like a still PNG does.
+    Apng,
+    /// A separate image for every frame, in the given format, with
+    /// the number of the frame added to the end of the file name.
+    Frames(image::ImageFormat),
+}
+
+/// Writes the frames of an animation as they're rendered.
+pub struct Writer {
+    path: PathBuf,
+    encoding: Encoding,
+    frames: u32,
+    fps: u32,
+    config: Config,
+    target: Option<String>,
+    frame: u32,
+    // Opened on the first frame, once the size of the image is known.
+    encoder: Option<Encoder>,
+}
+
+enum Encoder {
+    Gif(gif::Encoder<BufWriter<File>>),
+    /// The writer, and the sequence number of the next fcTL or fdAT
+    /// chunk.
+    Apng(png::Writer<BufWriter<File>>, u32),
+}
+
+impl Writer {
+    /// Starts writing the animation of the given pipeline to `path`.
+    /// If the animation is one monitor's slice of a layout, `target`
+    /// is the name of the monitor.
+    pub fn new(
+        path: &Path,
+        encoding: Encoding,
+        animation: &Animation,
+        config: &Config,
+        target: Option<&str>,
+    ) -> Result<Writer> {
+        Ok(Writer {
+            path: path.to_path_buf(),
+            encoding,
+            frames: animation.frames()?,
+            fps: animation.fps,
+            config: config.clone(),
+            target: target.map(str::to_string),
+            frame: 0,
+            encoder: None,
+        })
+    }
+
+    /// Writes the next frame of the animation.
+    pub fn write_frame(&mut self, image: &image::RgbImage) -> Result<()> {
+        let size = Position::from(image.dimensions());
+        match self.encoding {
+            Encoding::Frames(format) => {
+                let path = frame_path(&self.path, self.frame);
+                match format {
+                    image::ImageFormat::Png => crate::metadata::save_png(
+                        image,
+                        &self.config,
+                        self.target.as_deref(),
+                        &path,
+                    )?,
+                    _ => image.save_with_format(&path, format)?,
+                }
+            }
+            Encoding::Gif => {
+                let delay = (100.0 / self.fps as f64).round().max(1.0) as u16;
+                let (width, height) = gif_size(size)?;
+                // A speed of 10 is what `gif` recommends as a balance
+                // between how long quantizing takes and how good the
+                // colors come out.
+                let mut frame = gif::Frame::from_rgb_speed(width, height, image.as_raw(), 10);
+                frame.delay = delay;
+                match self.encoder {
+                    Some(Encoder::Gif(ref mut encoder)) => encoder.write_frame(&frame)?,
+                    _ => {
+                        let file = BufWriter::new(File::create(&self.path)?);
+                        let mut encoder = gif::Encoder::new(file, width, height, &[])?;
+                        encoder.set_repeat(gif::Repeat::Infinite)?;
+                        encoder.write_frame(&frame)?;
+                        self.encoder = Some(Encoder::Gif(encoder));
+                    }
+                }
+            }
+            Encoding::Apng => match self.encoder {
+                Some(Encoder::Apng(ref mut writer, ref mut sequence)) => {
+                    write_frame_control(writer, sequence, size, self.fps)?;
+                    let data = [&sequence.to_be_bytes()[..], &frame_data(image)?].concat();
+                    writer.write_chunk(*b"fdAT", &data)?;
+                    *sequence += 1;
+                }
+                _ => {
+                    let file = BufWriter::new(File::create(&self.path)?);
+                    let mut writer =
+                        crate::metadata::writer(file, size, &self.config, self.target.as_deref())?;
+                    // Zero plays is forever.
+                    let control = [self.frames.to_be_bytes(), 0u32.to_be_bytes()].concat();
+                    writer.write_chunk(*b"acTL", &control)?;
+                    let mut sequence = 0;
+                    write_frame_control(&mut writer, &mut sequence, size, self.fps)?;
+                    writer.write_image_data(image.as_raw())?;
+                    self.encoder = Some(Encoder::Apng(writer, sequence));
+                }
+            },
+        }
+        self.frame += 1;
+        Ok(())
+    }
+
+    /// Finishes the file off once every frame has been written.
+    pub fn finish(self) -> Result<()> {
+        match self.encoder {
+            // The trailer is written when the encoder is dropped.
+            Some(Encoder::Apng(writer, _)) => drop(writer),
+            Some(Encoder::Gif(encoder)) => drop(encoder),
+            None => {}
+        }
+        Ok(())
+    }
+}
+
+/// Writes the fcTL chunk that comes before every frame of an APNG,
+/// which shows the whole image for `1 / fps` seconds and then leaves
+/// it for the next frame to replace.
+fn write_frame_control(
+    writer: &mut png::Writer<BufWriter<File>>,
+    sequence: &mut u32,
+    size: Position,
+    fps: u32,
+) -> Result<()> {
+    let fps = u16::try_from(fps).unwrap_or(u16::MAX);
+    let data = [
+        &sequence.to_be_bytes()[..],
+        &size.x().to_be_bytes(),
+        &size.y().to_be_bytes(),
+        // The offset of the frame.
+        &[0; 8],
+        &1u16.to_be_bytes(),
+        &fps.to_be_bytes(),
+        // Neither disposes of the frame nor blends it.
+        &[0, 0],
+    ]
+    .concat();
+    *sequence += 1;
+    Ok(writer.write_chunk(*b"fcTL", &data)?)
+}
+
+/// The image data of a frame after the first, for an fdAT chunk.  It's
+/// compressed the same way `png` compresses the first frame, with the
+/// Sub filter on every row.
+fn frame_data(image: &image::RgbImage) -> Result<Vec<u8>> {
+    let mut zlib = deflate::write::ZlibEncoder::new(Vec::new(), deflate::Compression::Fast);
+    let row = image.width() as usize * 3;
+    for line in image.as_raw().chunks(row.max(1)) {
+        let filtered = (0..line.len())
+            .map(|i| line[i].wrapping_sub(if i < 3 { 0 } else { line[i - 3] }))
+            .collect::<Vec<_>>();
+        zlib.write_all(&[1])?;
+        zlib.write_all(&filtered)?;
+    }
+    Ok(zlib.finish()?)
+}
+
+fn gif_size(size: Position) -> Result<(u16, u16)> {
+    match (u16::try_from(size.x()), u16::try_from(size.y())) {
+        (Ok(width), Ok(height)) => Ok((width, height)),
+        _ => Err(Error::parameter(
+            "animation",
+            "gif",
+            format!(
+                "{} is too big; gifs are at most 65535 pixels on a side",
+                size
+            ),
+        )),
+    }
+}
+
+/// The path to write a single frame to, which is the path with the
+/// number of the frame added to the end of the file name.
+fn frame_path(path: &Path, frame: u32) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_else(|| "bkgn".as_ref());
+    let mut file_name = stem.to_os_string();
+    file_name.push(format!("-{:04}", frame));
+    if let Some(extension) = path.extension() {
+        file_name.push(".");
+        file_name.push(extension);
+    }
+    path.with_file_name(file_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::Pipeline;
+
+    #[test]
+    fn test_loops_back_to_the_start() {
+        let mut config = Config::from_toml(
+            r##"
+            [animation]
+            length = 1.0
+            fps = 4
+
+            [generator]
+            type = "noise"
+            image_size = [16, 16]
+            background_color = "#000000"
+            block_color = "#ffffff"
+            image_scale = 2.0
+            motion = 0.5
+
+            [[filters]]
+            type = "scan"
+            lines = 4
+            vary = 16
+            "##,
+        )
+        .unwrap();
+        let animation = config.animation.take().unwrap();
+        let pipeline = Pipeline::from_config(&config).unwrap();
+        let context = Context::new(3);
+
+        let frames = animation
+            .contexts(&context)
+            .unwrap()
+            .map(|frame| pipeline.render(&frame).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(frames.len(), 4);
+        assert_ne!(frames[0], frames[1]);
+        // A whole loop on is the first frame again.
+        let after = pipeline.render(&context.at_frame(4, 4)).unwrap();
+        assert_eq!(frames[0], after);
+    }
+
+    #[test]
+    fn test_apng() {
+        let config = Config::from_toml(
+            r##"
+            [generator]
+            type = "random"
+            block_size = [2, 2]
+            image_size = [6, 4]
+            block_color = "#ffffff"
+            background_color = "#000000"
+            "##,
+        )
+        .unwrap();
+        let animation = Animation {
+            length: 1.0,
+            fps: 3,
+        };
+        let frames = (0..3)
+            .map(|i| {
+                image::RgbImage::from_fn(6, 4, |x, y| {
+                    image::Rgb([x as u8 * 40, y as u8 * 60, i * 100])
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let path = std::env::temp_dir().join(format!("bkgn-animation-{}.png", std::process::id()));
+        let mut writer = Writer::new(&path, Encoding::Apng, &animation, &config, None).unwrap();
+        for frame in &frames {
+            writer.write_frame(frame).unwrap();
+        }
+        writer.finish().unwrap();
+        let file = File::open(&path).unwrap();
+        let recorded = crate::metadata::read(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let (info, mut reader) = png::Decoder::new(file).read_info().unwrap();
+        let control = reader.info().animation_control.unwrap();
+        assert_eq!((control.num_frames, control.num_plays), (3, 0));
+        for frame in &frames {
+            let mut buffer = vec![0; info.buffer_size()];
+            reader.next_frame(&mut buffer).unwrap();
+            assert_eq!(&buffer, frame.as_raw());
+            let control = reader.info().frame_control.unwrap();
+            assert_eq!((control.delay_num, control.delay_den), (1, 3));
+        }
+        assert_eq!(recorded.unwrap().config.generator.name(), "random");
+    }
+}
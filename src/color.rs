@@ -0,0 +1,166 @@
+//! Conversions between sRGB and the color spaces that are better at
+//! telling how different two colors look, for matching colors
+//! against a palette, along with measures of brightness and
+//! contrast.
+
+use serde::{Deserialize, Serialize};
+
+/// A space to measure the distance between colors in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Space {
+    /// Straight sRGB.  Quick, but off for how colors look; dark
+    /// colors come out much closer together than they seem.
+    Rgb,
+    /// CIE L*a*b*, under a D65 white point.
+    Cielab,
+    /// Björn Ottosson's OKLab, which keeps hues more even than
+    /// CIELAB does.
+    Oklab,
+}
+
+impl Space {
+    /// The coordinates of an sRGB color, with channels from `0.0` to
+    /// `255.0`, in this space.  Channels outside of that range are
+    /// clamped to it first.
+    pub fn coordinates(self, rgb: [f32; 3]) -> [f32; 3] {
+        let rgb = [
+            rgb[0].clamp(0.0, 255.0),
+            rgb[1].clamp(0.0, 255.0),
+            rgb[2].clamp(0.0, 255.0),
+        ];
+        match self {
+            Space::Rgb => rgb,
+            Space::Cielab => cielab_from_linear(linear(rgb)),
+            Space::Oklab => oklab_from_linear(linear(rgb)),
+        }
+    }
+}
+
+/// The squared distance between two points.  This is enough to find
+/// the closest of a set of colors.
+pub fn distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+    (a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)
+}
+
+/// How bright a color looks, from `0.0` for black to `1.0` for
+/// white, by the usual weighting of the channels.
+pub fn brightness(pixel: &image::Rgb<u8>) -> f32 {
+    let r = pixel.0[0] as f32 / 255.0;
+    let g = pixel.0[1] as f32 / 255.0;
+    let b = pixel.0[2] as f32 / 255.0;
+    (r * 0.30 + g * 0.59 + b * 0.11).clamp(0.0, 1.0)
+}
+
+/// The relative luminance of a color, as WCAG defines it.
+pub fn luminance(color: image::Rgb<u8>) -> f32 {
+    let [r, g, b] = linear(to_array(color));
+    0.2126 * r + 0.7152 * g + 0.0722 * b
+}
+
+/// The WCAG contrast ratio between two colors, from `1.0` for the
+/// same color up to `21.0` for black on white.  Text is meant to
+/// have a ratio of at least `4.5` against its background.
+pub fn contrast(a: image::Rgb<u8>, b: image::Rgb<u8>) -> f32 {
+    let (a, b) = (luminance(a), luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+/// A color as a hex string, like `#214f72`.
+pub fn to_hex(color: image::Rgb<u8>) -> String {
+    let [r, g, b] = color.0;
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+pub fn to_array(color: image::Rgb<u8>) -> [f32; 3] {
+    [color.0[0] as f32, color.0[1] as f32, color.0[2] as f32]
+}
+
+/// Linear light from sRGB, with channels from `0.0` to `255.0`.
+pub fn linear(rgb: [f32; 3]) -> [f32; 3] {
+    fn decode(v: f32) -> f32 {
+        let v = v / 255.0;
+        if v <= 0.04045 {
+            v / 12.92
+        } else {
+            ((v + 0.055) / 1.055).powf(2.4)
+        }
+    }
+    [decode(rgb[0]), decode(rgb[1]), decode(rgb[2])]
+}
+
+/// sRGB from linear light, rounded and clamped to a displayable
+/// color.
+pub fn from_linear(rgb: [f32; 3]) -> image::Rgb<u8> {
+    fn encode(v: f32) -> u8 {
+        let v = v.clamp(0.0, 1.0);
+        let v = if v <= 0.003_130_8 {
+            v * 12.92
+        } else {
+            1.055 * v.powf(1.0 / 2.4) - 0.055
+        };
+        (v * 255.0).round() as u8
+    }
+    image::Rgb([encode(rgb[0]), encode(rgb[1]), encode(rgb[2])])
+}
+
+pub fn oklab_from_linear([r, g, b]: [f32; 3]) -> [f32; 3] {
+    let l = (0.412_221_46 * r + 0.536_332_55 * g + 0.051_445_995 * b).cbrt();
+    let m = (0.211_903_5 * r + 0.680_699_5 * g + 0.107_396_96 * b).cbrt();
+    let s = (0.088_302_46 * r + 0.281_718_85 * g + 0.629_978_7 * b).cbrt();
+    [
+        0.210_454_26 * l + 0.793_617_8 * m - 0.004_072_047 * s,
+        1.977_998_5 * l - 2.428_592_2 * m + 0.450_593_7 * s,
+        0.025_904_037 * l + 0.782_771_77 * m - 0.808_675_77 * s,
+    ]
+}
+
+pub fn linear_from_oklab([l, a, b]: [f32; 3]) -> [f32; 3] {
+    let l_ = (l + 0.396_337_78 * a + 0.215_803_76 * b).powi(3);
+    let m_ = (l - 0.105_561_346 * a - 0.063_854_17 * b).powi(3);
+    let s_ = (l - 0.089_484_18 * a - 1.291_485_5 * b).powi(3);
+    [
+        4.076_741_7 * l_ - 3.307_711_6 * m_ + 0.230_969_94 * s_,
+        -1.268_438 * l_ + 2.609_757_4 * m_ - 0.341_319_38 * s_,
+        -0.004_196_086_3 * l_ - 0.703_418_6 * m_ + 1.707_614_7 * s_,
+    ]
+}
+
+pub fn cielab_from_linear([r, g, b]: [f32; 3]) -> [f32; 3] {
+    // The D65 white point.
+    const WHITE: [f32; 3] = [0.950_47, 1.0, 1.088_83];
+    fn f(t: f32) -> f32 {
+        const DELTA: f32 = 6.0 / 29.0;
+        if t > DELTA.powi(3) {
+            t.cbrt()
+        } else {
+            t / (3.0 * DELTA * DELTA) + 4.0 / 29.0
+        }
+    }
+
+    let x = (0.412_456_4 * r + 0.357_576_1 * g + 0.180_437_5 * b) / WHITE[0];
+    let y = (0.212_672_9 * r + 0.715_152_2 * g + 0.072_175 * b) / WHITE[1];
+    let z = (0.019_333_9 * r + 0.119_192 * g + 0.950_304_1 * b) / WHITE[2];
+    let (x, y, z) = (f(x), f(y), f(z));
+    [116.0 * y - 16.0, 500.0 * (x - y), 200.0 * (y - z)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_oklab_round_trip() {
+        let color = crate::hex(0x7addaa);
+        let lab = oklab_from_linear(linear(to_array(color)));
+        assert_eq!(from_linear(linear_from_oklab(lab)), color);
+
+        let white = Space::Oklab.coordinates([255.0; 3]);
+        assert!((white[0] - 1.0).abs() < 1e-3);
+        let white = Space::Cielab.coordinates([255.0; 3]);
+        assert!((white[0] - 100.0).abs() < 1e-2);
+
+        let contrast = contrast(crate::hex(0x000000), crate::hex(0xffffff));
+        assert!((contrast - 21.0).abs() < 1e-3);
+    }
+}
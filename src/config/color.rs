@@ -0,0 +1,435 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// A color in a pipeline description.  This is written as a string,
+/// in any of the forms CSS has for sRGB colors:
+///
+/// - hex, as `"#214f72"`, `"#fff"`, or with alpha as `"#214f7280"`
+///   or `"#fff8"`; the leading `#` can be left off;
+/// - `"rgb(33 79 114)"`, with channels from 0 to 255 or as
+///   percentages;
+/// - `"hsl(206 55% 29%)"`;
+/// - `"oklch(40% 0.08 245)"`, with the lightness from 0 to 1 or as a
+///   percentage; colors outside of sRGB are clipped to it;
+/// - a CSS color name, like `"steelblue"`, or `"transparent"`.
+///
+/// The functions take their alpha after a slash, like
+/// `"rgb(33 79 114 / 50%)"`, and also accept the older comma
+/// separated forms, like `"rgba(33, 79, 114, 0.5)"`.  A color can also
+/// be given as an integer, like `0x214f72` in TOML.
+///
+/// Images are opaque, so a color with alpha is mixed with whatever
+/// it's drawn over.  For a generator, that's its `background_color`;
+/// for anything else, it's black.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Color(pub image::Rgba<u8>);
+
+impl Color {
+    /// The color, drawn over the given opaque color.
+    pub fn over(self, below: image::Rgb<u8>) -> image::Rgb<u8> {
+        let [r, g, b, a] = self.0 .0;
+        let mix = |over: u8, under: u8| {
+            let (over, under, a) = (over as u32, under as u32, a as u32);
+            ((over * a + under * (255 - a) + 127) / 255) as u8
+        };
+        image::Rgb([mix(r, below[0]), mix(g, below[1]), mix(b, below[2])])
+    }
+}
+
+impl From<image::Rgb<u8>> for Color {
+    fn from(color: image::Rgb<u8>) -> Self {
+        let [r, g, b] = color.0;
+        Color(image::Rgba([r, g, b, 255]))
+    }
+}
+
+impl From<Color> for image::Rgba<u8> {
+    fn from(color: Color) -> Self {
+        color.0
+    }
+}
+
+/// The color drawn over black.
+impl From<Color> for image::Rgb<u8> {
+    fn from(color: Color) -> Self {
+        color.over(crate::hex(0x000000))
+    }
+}
+
+impl FromStr for Color {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Color, String> {
+        let value = value.trim().to_ascii_lowercase();
+        if let Some((name, arguments)) = value.split_once('(') {
+            let arguments = arguments
+                .strip_suffix(')')
+                .ok_or_else(|| format!("{:?} is missing a closing `)`", value))?;
+            return function(name.trim(), arguments)
+                .map_err(|e| format!("invalid {}(): {}", name.trim(), e));
+        }
+        if let Some((_, hex)) = NAMES.iter().find(|(name, _)| *name == value) {
+            return Ok(Color::from(crate::hex(*hex)));
+        }
+        if value == "transparent" {
+            return Ok(Color(image::Rgba([0, 0, 0, 0])));
+        }
+
+        let digits = value.strip_prefix('#').unwrap_or(&value);
+        if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("unknown color {:?}", value));
+        }
+        let digit = |i: usize| u8::from_str_radix(&digits[i..i + 1], 16).unwrap();
+        let pair = |i: usize| u8::from_str_radix(&digits[i..i + 2], 16).unwrap();
+        let channels = match digits.len() {
+            3 | 4 => (0..digits.len()).map(|i| digit(i) * 0x11).collect(),
+            6 | 8 => (0..digits.len()).step_by(2).map(pair).collect(),
+            _ => vec![],
+        };
+        match channels[..] {
+            [r, g, b] => Ok(Color(image::Rgba([r, g, b, 255]))),
+            [r, g, b, a] => Ok(Color(image::Rgba([r, g, b, a]))),
+            _ => Err(format!("{:?} should have 3, 4, 6 or 8 hex digits", value)),
+        }
+    }
+}
+
+/// Parses the arguments of `rgb()`, `hsl()` or `oklch()`.
+fn function(name: &str, arguments: &str) -> Result<Color, String> {
+    let (channels, alpha) = match arguments.split_once('/') {
+        Some((channels, alpha)) => (channels, Some(alpha.trim())),
+        None => (arguments, None),
+    };
+    let mut values = channels
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|v| !v.is_empty())
+        .collect::<Vec<_>>();
+    // The older comma separated forms put alpha fourth.
+    let alpha = match (alpha, values.len()) {
+        (Some(alpha), 3) => alpha,
+        (None, 4) => values.pop().expect("there are four values"),
+        (None, 3) => "1",
+        _ => {
+            return Err(format!(
+                "expected 3 values and an alpha, got {:?}",
+                arguments
+            ))
+        }
+    };
+    let alpha = (fraction(alpha, 1.0)?.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+    let rgb = match name {
+        "rgb" | "rgba" => {
+            let channel = |v| Ok::<_, String>(fraction(v, 255.0)? / 255.0);
+            [
+                channel(values[0])?,
+                channel(values[1])?,
+                channel(values[2])?,
+            ]
+        }
+        "hsl" | "hsla" => hsl(
+            hue(values[0])?,
+            fraction(values[1], 100.0)? / 100.0,
+            fraction(values[2], 100.0)? / 100.0,
+        ),
+        "oklch" => {
+            let (l, c, h) = (
+                fraction(values[0], 1.0)?,
+                fraction(values[1], 0.4)?,
+                hue(values[2])?.to_radians(),
+            );
+            let linear = crate::color::linear_from_oklab([l, c * h.cos(), c * h.sin()]);
+            crate::color::to_array(crate::color::from_linear(linear)).map(|v| v / 255.0)
+        }
+        _ => return Err(format!("unknown color function {:?}", name)),
+    };
+    let channel = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+    Ok(Color(image::Rgba([
+        channel(rgb[0]),
+        channel(rgb[1]),
+        channel(rgb[2]),
+        alpha,
+    ])))
+}
+
+/// A number, or a percentage of `whole`.  `none` is zero, like CSS
+/// has it.
+fn fraction(value: &str, whole: f32) -> Result<f32, String> {
+    let number = |v: &str| {
+        v.parse::<f32>()
+            .ok()
+            .filter(|v| v.is_finite())
+            .ok_or_else(|| format!("expected a number, got {:?}", value))
+    };
+    match value.strip_suffix('%') {
+        _ if value == "none" => Ok(0.0),
+        Some(percent) => Ok(number(percent)? / 100.0 * whole),
+        None => number(value),
+    }
+}
+
+/// An angle, in degrees.  Bare numbers are in degrees too.
+fn hue(value: &str) -> Result<f32, String> {
+    let units = [
+        ("deg", 1.0),
+        ("grad", 0.9),
+        ("rad", 180.0 / std::f32::consts::PI),
+        ("turn", 360.0),
+    ];
+    for (unit, degrees) in units.iter() {
+        if let Some(number) = value.strip_suffix(unit) {
+            return Ok(fraction(number, 1.0)? * degrees);
+        }
+    }
+    fraction(value, 1.0)
+}
+
+/// sRGB from hue, saturation and lightness, with channels from `0.0`
+/// to `1.0`.
+fn hsl(h: f32, s: f32, l: f32) -> [f32; 3] {
+    let (s, l) = (s.clamp(0.0, 1.0), l.clamp(0.0, 1.0));
+    let a = s * l.min(1.0 - l);
+    let f = |n: f32| {
+        let k = (n + h / 30.0).rem_euclid(12.0);
+        l - a * (k - 3.0).min(9.0 - k).clamp(-1.0, 1.0)
+    };
+    [f(0.0), f(8.0), f(4.0)]
+}
+
+impl Serialize for Color {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let [r, g, b, a] = self.0 .0;
+        let mut hex = crate::color::to_hex(crate::rgb(r, g, b));
+        if a != 255 {
+            hex.push_str(&format!("{:02x}", a));
+        }
+        serializer.serialize_str(&hex)
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = Color;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str("a color, like \"#214f72\", \"hsl(206 55% 29%)\" or \"steelblue\"")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<Color, E> {
+                value.parse().map_err(E::custom)
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<Color, E> {
+                if value > 0xffffff {
+                    return Err(E::invalid_value(
+                        serde::de::Unexpected::Unsigned(value),
+                        &self,
+                    ));
+                }
+                Ok(Color::from(crate::hex(value as u32)))
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, value: i64) -> Result<Color, E> {
+                if value < 0 {
+                    return Err(E::invalid_value(
+                        serde::de::Unexpected::Signed(value),
+                        &self,
+                    ));
+                }
+                self.visit_u64(value as u64)
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+/// The named colors of CSS.
+const NAMES: &[(&str, u32)] = &[
+    ("aliceblue", 0xf0f8ff),
+    ("antiquewhite", 0xfaebd7),
+    ("aqua", 0x00ffff),
+    ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff),
+    ("beige", 0xf5f5dc),
+    ("bisque", 0xffe4c4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd),
+    ("blue", 0x0000ff),
+    ("blueviolet", 0x8a2be2),
+    ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887),
+    ("cadetblue", 0x5f9ea0),
+    ("chartreuse", 0x7fff00),
+    ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50),
+    ("cornflowerblue", 0x6495ed),
+    ("cornsilk", 0xfff8dc),
+    ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff),
+    ("darkblue", 0x00008b),
+    ("darkcyan", 0x008b8b),
+    ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xa9a9a9),
+    ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b),
+    ("darkolivegreen", 0x556b2f),
+    ("darkorange", 0xff8c00),
+    ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000),
+    ("darksalmon", 0xe9967a),
+    ("darkseagreen", 0x8fbc8f),
+    ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f),
+    ("darkslategrey", 0x2f4f4f),
+    ("darkturquoise", 0x00ced1),
+    ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493),
+    ("deepskyblue", 0x00bfff),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff),
+    ("firebrick", 0xb22222),
+    ("floralwhite", 0xfffaf0),
+    ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff),
+    ("gainsboro", 0xdcdcdc),
+    ("ghostwhite", 0xf8f8ff),
+    ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xadff2f),
+    ("grey", 0x808080),
+    ("honeydew", 0xf0fff0),
+    ("hotpink", 0xff69b4),
+    ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082),
+    ("ivory", 0xfffff0),
+    ("khaki", 0xf0e68c),
+    ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5),
+    ("lawngreen", 0x7cfc00),
+    ("lemonchiffon", 0xfffacd),
+    ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080),
+    ("lightcyan", 0xe0ffff),
+    ("lightgoldenrodyellow", 0xfafad2),
+    ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90),
+    ("lightgrey", 0xd3d3d3),
+    ("lightpink", 0xffb6c1),
+    ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa),
+    ("lightskyblue", 0x87cefa),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de),
+    ("lightyellow", 0xffffe0),
+    ("lime", 0x00ff00),
+    ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6),
+    ("magenta", 0xff00ff),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd),
+    ("mediumorchid", 0xba55d3),
+    ("mediumpurple", 0x9370db),
+    ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee),
+    ("mediumspringgreen", 0x00fa9a),
+    ("mediumturquoise", 0x48d1cc),
+    ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xf5fffa),
+    ("mistyrose", 0xffe4e1),
+    ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead),
+    ("navy", 0x000080),
+    ("oldlace", 0xfdf5e6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23),
+    ("orange", 0xffa500),
+    ("orangered", 0xff4500),
+    ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa),
+    ("palegreen", 0x98fb98),
+    ("paleturquoise", 0xafeeee),
+    ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5),
+    ("peachpuff", 0xffdab9),
+    ("peru", 0xcd853f),
+    ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd),
+    ("powderblue", 0xb0e0e6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xff0000),
+    ("rosybrown", 0xbc8f8f),
+    ("royalblue", 0x4169e1),
+    ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072),
+    ("sandybrown", 0xf4a460),
+    ("seagreen", 0x2e8b57),
+    ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d),
+    ("silver", 0xc0c0c0),
+    ("skyblue", 0x87ceeb),
+    ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xfffafa),
+    ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4),
+    ("tan", 0xd2b48c),
+    ("teal", 0x008080),
+    ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347),
+    ("turquoise", 0x40e0d0),
+    ("violet", 0xee82ee),
+    ("wheat", 0xf5deb3),
+    ("white", 0xffffff),
+    ("whitesmoke", 0xf5f5f5),
+    ("yellow", 0xffff00),
+    ("yellowgreen", 0x9acd32),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_round_trip() {
+        let color = "#214f72".parse::<Color>().unwrap();
+        assert_eq!(color, Color::from(crate::hex(0x214f72)));
+        assert_eq!(serde_json::to_string(&color).unwrap(), "\"#214f72\"");
+        assert!("#21f72".parse::<Color>().is_err());
+    }
+
+    #[test]
+    fn test_color_forms() {
+        let rgb = |value: &str| image::Rgb::from(value.parse::<Color>().unwrap());
+        let steelblue = crate::hex(0x4682b4);
+        assert_eq!(rgb("#fff"), crate::hex(0xffffff));
+        assert_eq!(rgb("SteelBlue"), steelblue);
+        assert_eq!(rgb("rgb(70 130 180)"), steelblue);
+        assert_eq!(rgb("rgba(70, 130, 180, 1)"), steelblue);
+        assert_eq!(rgb("hsl(207deg 44% 49%)"), steelblue);
+        assert_eq!(rgb("oklch(58.8% 0.0993 245.74)"), steelblue);
+        assert_eq!(rgb("hsl(0.5turn 100% 50%)"), crate::hex(0x00ffff));
+        assert!("rgb(1 2)".parse::<Color>().is_err());
+        assert!("notacolor".parse::<Color>().is_err());
+
+        // Alpha mixes with the background.
+        let half = "rgb(255 255 255 / 50%)".parse::<Color>().unwrap();
+        assert_eq!(half.0, image::Rgba([255, 255, 255, 128]));
+        assert_eq!(half.over(crate::hex(0x000000)), crate::hex(0x808080));
+        assert_eq!(serde_json::to_string(&half).unwrap(), "\"#ffffff80\"");
+        assert_eq!("#fff8".parse::<Color>().unwrap().0[3], 0x88);
+    }
+}
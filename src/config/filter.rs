@@ -0,0 +1,360 @@
+use super::length::Length;
+use super::Color;
+use crate::color::Space;
+use crate::error::{Error, Result};
+use crate::filter::{self, ColorSpace, DitherMethod, Filter};
+use crate::palette::{self, Extraction};
+use crate::position::Position;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// A single filter in a pipeline, tagged by its `type`.  Filters
+/// are applied in the order they're given.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FilterConfig {
+    Abberate(AbberateConfig),
+    Null(NullConfig),
+    Barrel(BarrelConfig),
+    Blur(BlurConfig),
+    Crop(CropConfig),
+    Dither(DitherConfig),
+    March(MarchConfig),
+    Noise(NoiseConfig),
+    Palette(PaletteConfig),
+    Scan(ScanConfig),
+    Vignette(VignetteConfig),
+}
+
+impl FilterConfig {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FilterConfig::Abberate(_) => "abberate",
+            FilterConfig::Null(_) => "null",
+            FilterConfig::Barrel(_) => "barrel",
+            FilterConfig::Blur(_) => "blur",
+            FilterConfig::Crop(_) => "crop",
+            FilterConfig::Dither(_) => "dither",
+            FilterConfig::March(_) => "march",
+            FilterConfig::Noise(_) => "noise",
+            FilterConfig::Palette(_) => "palette",
+            FilterConfig::Scan(_) => "scan",
+            FilterConfig::Vignette(_) => "vignette",
+        }
+    }
+
+    /// Builds the filter.  Relative lengths are resolved against
+    /// `size`, the size of the image the pipeline makes.
+    pub fn build(&self, size: Position) -> Result<Box<dyn Filter>> {
+        Ok(match self {
+            FilterConfig::Abberate(c) => {
+                let [r, g, b] = c.channel_shifts;
+                let mut filter = filter::AbberateFilter::new(r, g, b);
+                filter.color_space = c.color_space;
+                Box::new(filter)
+            }
+            FilterConfig::Null(c) => {
+                let mut filter = filter::NullFilter::new(c.selection, c.average_null, c.component)?;
+                filter.color_space = c.color_space;
+                Box::new(filter)
+            }
+            FilterConfig::Barrel(c) => Box::new(filter::BarrelFilter::new(
+                c.scale.pixels(size) as f32,
+                c.background_color.into(),
+            )),
+            FilterConfig::Blur(c) => Box::new(filter::BlurFilter::new(c.sigma.pixels(size) as f32)),
+            FilterConfig::Crop(c) => Box::new(filter::CropFilter::new(
+                c.top.whole(size, "crop", "top")?,
+                c.right.whole(size, "crop", "right")?,
+                c.bottom.whole(size, "crop", "bottom")?,
+                c.left.whole(size, "crop", "left")?,
+            )),
+            FilterConfig::Dither(c) => {
+                let mut filter =
+                    filter::DitherFilter::new(c.levels, c.white.into(), c.black.into())?;
+                filter.method = c.method;
+                filter.serpentine = c.serpentine;
+                filter.palette = match &c.palette {
+                    DitherPaletteConfig::Named(PaletteName::Bands) => filter::DitherPalette::Bands,
+                    DitherPaletteConfig::Named(PaletteName::TwoTone) => {
+                        filter::DitherPalette::TwoTone
+                    }
+                    DitherPaletteConfig::Colors(colors) => {
+                        filter::DitherPalette::Colors(colors.iter().map(|c| (*c).into()).collect())
+                    }
+                };
+                Box::new(filter)
+            }
+            FilterConfig::March(c) => {
+                let max_shift = c.max_shift.whole(size, "march", "max_shift")?;
+                let mut filter =
+                    filter::MarchFilter::new(c.selection, max_shift, c.background_color.into())?;
+                filter.shift_distribution =
+                    rand_distr::Normal::new(0.0, c.deviation).map_err(|_| {
+                        Error::parameter(
+                            "march",
+                            "deviation",
+                            format!("must be a positive number, not {}", c.deviation),
+                        )
+                    })?;
+                let keep = c.keep;
+                filter.keep_adjustment = Some(Box::new(move |f| f * keep));
+                Box::new(filter)
+            }
+            FilterConfig::Noise(c) => Box::new(filter::NoiseFilter::new(c.rate, c.low, c.high)?),
+            FilterConfig::Palette(c) => {
+                let mut filter = filter::PaletteFilter::new(c.colors()?, c.space)?;
+                filter.dither = c.dither;
+                filter.serpentine = c.serpentine;
+                Box::new(filter)
+            }
+            FilterConfig::Scan(c) => {
+                let mut filter = filter::ScanFilter::new(c.lines, c.vary);
+                filter.roll = c.roll;
+                Box::new(filter)
+            }
+            FilterConfig::Vignette(c) => {
+                Box::new(filter::VignetteFilter::new(c.distance_offset, c.scale))
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AbberateConfig {
+    /// How far to shift each of the three channels, in pixels.
+    /// Positive values shift to the right.
+    pub channel_shifts: [i32; 3],
+    #[serde(default = "default_yiq")]
+    pub color_space: ColorSpace,
+}
+
+fn default_yiq() -> ColorSpace {
+    ColorSpace::Yiq
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NullConfig {
+    /// The chance that any given pixel starts a nulled run.
+    pub selection: f64,
+    /// The average length of a nulled run, in pixels.
+    pub average_null: f32,
+    /// The channel to null; if not given, one is picked at random
+    /// for every run.
+    #[serde(default)]
+    pub component: Option<usize>,
+    #[serde(default = "default_rgb")]
+    pub color_space: ColorSpace,
+}
+
+fn default_rgb() -> ColorSpace {
+    ColorSpace::Rgb
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BarrelConfig {
+    /// How far the edges of the image are pulled in.
+    pub scale: Length,
+    pub background_color: Color,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlurConfig {
+    pub sigma: Length,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CropConfig {
+    #[serde(default)]
+    pub top: Length,
+    #[serde(default)]
+    pub right: Length,
+    #[serde(default)]
+    pub bottom: Length,
+    #[serde(default)]
+    pub left: Length,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DitherConfig {
+    /// The size of the Bayer matrix, for ordered dithering.
+    #[serde(default = "default_levels")]
+    pub levels: u16,
+    #[serde(default = "default_white")]
+    pub white: Color,
+    #[serde(default = "default_black")]
+    pub black: Color,
+    #[serde(default = "default_method")]
+    pub method: DitherMethod,
+    #[serde(default)]
+    pub serpentine: bool,
+    #[serde(default)]
+    pub palette: DitherPaletteConfig,
+}
+
+fn default_levels() -> u16 {
+    2
+}
+
+fn default_white() -> Color {
+    Color::from(crate::hex(0xffffff))
+}
+
+fn default_black() -> Color {
+    Color::from(crate::hex(0x000000))
+}
+
+fn default_method() -> DitherMethod {
+    DitherMethod::Ordered
+}
+
+/// The colors to dither down to: either `"bands"`, which bands each
+/// channel on its own, `"two_tone"`, which is `white` and `black`, or
+/// a list of colors.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum DitherPaletteConfig {
+    Named(PaletteName),
+    Colors(Vec<Color>),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PaletteName {
+    Bands,
+    TwoTone,
+}
+
+impl Default for DitherPaletteConfig {
+    fn default() -> Self {
+        DitherPaletteConfig::Named(PaletteName::Bands)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MarchConfig {
+    /// The chance that any given row starts a new shift.
+    pub selection: f64,
+    pub max_shift: Length,
+    pub background_color: Color,
+    /// The standard deviation of a new shift, as a fraction of
+    /// `max_shift`.
+    #[serde(default = "default_deviation")]
+    pub deviation: f64,
+    /// How much of the previous row's shift carries over into the
+    /// next row; `0.0` means shifts do not carry over.
+    #[serde(default = "default_keep")]
+    pub keep: f64,
+}
+
+fn default_deviation() -> f64 {
+    0.25
+}
+
+fn default_keep() -> f64 {
+    0.92
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NoiseConfig {
+    pub rate: f64,
+    pub low: u8,
+    pub high: u8,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PaletteConfig {
+    /// The palette is every color from `colors`, `file` and
+    /// `extract` put together.
+    #[serde(default)]
+    pub colors: Vec<Color>,
+    #[serde(default)]
+    pub file: Option<PathBuf>,
+    #[serde(default)]
+    pub extract: Option<ExtractConfig>,
+    #[serde(default = "default_space")]
+    pub space: Space,
+    #[serde(default)]
+    pub dither: Option<DitherMethod>,
+    #[serde(default)]
+    pub serpentine: bool,
+}
+
+/// Colors pulled out of a reference image.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExtractConfig {
+    pub image: PathBuf,
+    pub count: usize,
+    #[serde(default = "default_extraction")]
+    pub method: Extraction,
+}
+
+fn default_space() -> Space {
+    Space::Oklab
+}
+
+fn default_extraction() -> Extraction {
+    Extraction::Kmeans
+}
+
+impl PaletteConfig {
+    fn colors(&self) -> Result<Vec<image::Rgb<u8>>> {
+        let mut colors = self.colors.iter().map(|c| (*c).into()).collect::<Vec<_>>();
+        if let Some(path) = self.file.as_ref() {
+            colors.extend(palette::load(path)?);
+        }
+        if let Some(extract) = self.extract.as_ref() {
+            if extract.count == 0 {
+                return Err(Error::parameter(
+                    "palette",
+                    "extract",
+                    "count must be at least 1",
+                ));
+            }
+            let image = image::open(&extract.image).map_err(|e| {
+                Error::parameter(
+                    "palette",
+                    "extract",
+                    format!("could not load {}: {}", extract.image.display(), e),
+                )
+            })?;
+            colors.extend(palette::extract(
+                &image.to_rgb(),
+                extract.count,
+                extract.method,
+            ));
+        }
+        Ok(colors)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScanConfig {
+    pub lines: u32,
+    pub vary: i16,
+    /// How many pairs of scanlines roll past over an animation.
+    #[serde(default = "default_roll")]
+    pub roll: u32,
+}
+
+fn default_roll() -> u32 {
+    1
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VignetteConfig {
+    pub distance_offset: f64,
+    pub scale: f64,
+}
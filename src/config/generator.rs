@@ -0,0 +1,1008 @@
+use super::length::{whole_pair, Length};
+use super::Color;
+use crate::error::{Error, Result};
+use crate::generator::fractal::{Source, SourceKind, Warp};
+use crate::generator::maze::{Algorithm, Direction, Opening, Shape, Solution, SolutionStyle};
+use crate::generator::Animated;
+use crate::generator::{self, Generator, Resample, Scaled, Supersample, VectorGenerator};
+use crate::gradient::{Gradient, Interpolation, Stop};
+use crate::position::Position;
+use noise::{MultiFractal, NoiseFn, Seedable};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The generator at the start of a pipeline, tagged by its `type`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GeneratorConfig {
+    Noise(NoiseConfig),
+    Maze(MazeConfig),
+    Stripe(StripeConfig),
+    Bars(BarsConfig),
+    Random(RandomConfig),
+    File(FileConfig),
+}
+
+impl GeneratorConfig {
+    pub fn name(&self) -> &'static str {
+        match self {
+            GeneratorConfig::Noise(_) => "noise",
+            GeneratorConfig::Maze(_) => "maze",
+            GeneratorConfig::Stripe(_) => "stripe",
+            GeneratorConfig::Bars(_) => "bars",
+            GeneratorConfig::Random(_) => "random",
+            GeneratorConfig::File(_) => "file",
+        }
+    }
+
+    /// The size of the image this generator will produce, if it's
+    /// configurable.
+    pub fn image_size_mut(&mut self) -> Option<&mut Position> {
+        match self {
+            GeneratorConfig::Noise(c) => Some(&mut c.image_size),
+            GeneratorConfig::Maze(c) => Some(&mut c.image_size),
+            GeneratorConfig::Stripe(c) => Some(&mut c.image_size),
+            GeneratorConfig::Bars(c) => Some(&mut c.image_size),
+            GeneratorConfig::Random(c) => Some(&mut c.image_size),
+            GeneratorConfig::File(_) => None,
+        }
+    }
+
+    /// The size of the image this generator will produce.  For the
+    /// file generator, this reads the size of the file.
+    pub fn image_size(&self) -> Result<Position> {
+        match self {
+            GeneratorConfig::Noise(c) => Ok(c.image_size),
+            GeneratorConfig::Maze(c) => Ok(c.image_size),
+            GeneratorConfig::Stripe(c) => Ok(c.image_size),
+            GeneratorConfig::Bars(c) => Ok(c.image_size),
+            GeneratorConfig::Random(c) => Ok(c.image_size),
+            GeneratorConfig::File(c) => image::image_dimensions(&c.path)
+                .map(Position::from)
+                .map_err(|e| {
+                    Error::parameter(
+                        "file",
+                        "path",
+                        format!("could not load {}: {}", c.path.display(), e),
+                    )
+                }),
+        }
+    }
+
+    /// Builds the generator.  Relative lengths are resolved against
+    /// `size`, the size of the image the pipeline makes; this is the
+    /// generator's own size, unless the pipeline has a bleed.  With a
+    /// supersample, the generator draws a larger image that's scaled
+    /// back down.
+    pub fn build(
+        &self,
+        size: Position,
+        supersample: Option<&SupersampleConfig>,
+    ) -> Result<Box<dyn Generator<image::Rgb<u8>>>> {
+        match self {
+            GeneratorConfig::Noise(c) => c.build(supersample),
+            GeneratorConfig::Maze(c) => boxed(c.build(size)?, supersample),
+            GeneratorConfig::Stripe(c) => boxed(c.build(size)?, supersample),
+            GeneratorConfig::Bars(c) => boxed(c.build(size)?, supersample),
+            GeneratorConfig::Random(c) => boxed(c.build(size)?, supersample),
+            GeneratorConfig::File(_) if supersample.is_some() => Err(Error::parameter(
+                "supersample",
+                "factor",
+                "the file generator can't be drawn any larger",
+            )),
+            GeneratorConfig::File(c) => Ok(Box::new(c.build())),
+        }
+    }
+
+    /// Builds the generator for drawing as an SVG document.  Only the
+    /// generators that draw shapes can be.
+    pub fn build_vector(&self, size: Position) -> Result<Box<dyn VectorGenerator>> {
+        Ok(match self {
+            GeneratorConfig::Maze(c) => Box::new(c.build(size)?),
+            GeneratorConfig::Stripe(c) => Box::new(c.build(size)?),
+            GeneratorConfig::Bars(c) => Box::new(c.build(size)?),
+            GeneratorConfig::Random(c) => Box::new(c.build(size)?),
+            GeneratorConfig::Noise(_) | GeneratorConfig::File(_) => {
+                return Err(Error::parameter(
+                    self.name(),
+                    "type",
+                    "only the maze, stripe, bars and random generators can be drawn as svg",
+                ))
+            }
+        })
+    }
+}
+
+/// Draws the generator's image larger than it's needed, and scales it
+/// back down, which smooths the edges of everything in it.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SupersampleConfig {
+    /// How many times as wide and as tall the larger image is.
+    pub factor: u32,
+    #[serde(default)]
+    pub filter: Resample,
+}
+
+/// Boxes a generator, supersampling it if asked to.
+fn boxed<G>(
+    generator: G,
+    supersample: Option<&SupersampleConfig>,
+) -> Result<Box<dyn Generator<image::Rgb<u8>>>>
+where
+    G: Generator<image::Rgb<u8>> + Scaled + 'static,
+{
+    Ok(match supersample {
+        Some(s) => Box::new(Supersample::new(generator, s.factor, s.filter)?),
+        None => Box::new(generator),
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NoiseConfig {
+    pub image_size: Position,
+    /// The two colors that the noise blends between.  These are
+    /// needed unless there's a gradient, which replaces them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background_color: Option<Color>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_color: Option<Color>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gradient: Option<GradientConfig>,
+    /// Either a single scale for both axes, or an `[x, y]` pair.
+    pub image_scale: Scale,
+    #[serde(default = "default_value_scale")]
+    pub value_scale: f64,
+    /// How far the noise moves over the course of an animation.  At
+    /// `0.0`, the noise stays still.
+    #[serde(default)]
+    pub motion: f64,
+    /// The noise function.  Without one, still images use Perlin
+    /// noise and moving ones OpenSimplex, as they always have.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<SourceConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warp: Option<WarpConfig>,
+    /// Draws the noise as a topographic map instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contours: Option<ContourConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SourceConfig {
+    #[serde(rename = "type")]
+    pub kind: SourceKind,
+    /// How many layers of finer and finer noise are added together.
+    /// Defaults to one for plain noise, and six for the fractal
+    /// kinds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub octaves: Option<usize>,
+    /// How much each octave is scaled down from the last.
+    #[serde(default = "default_persistence")]
+    pub persistence: f64,
+    /// How much finer each octave is than the last.
+    #[serde(default = "default_lacunarity")]
+    pub lacunarity: f64,
+    /// A multiplier on the `image_scale`.
+    #[serde(default = "default_frequency")]
+    pub frequency: f64,
+    /// Fills each Worley cell with a single value, instead of shading
+    /// it by the distance to its center.
+    #[serde(default)]
+    pub flat: bool,
+}
+
+fn default_persistence() -> f64 {
+    0.5
+}
+
+fn default_lacunarity() -> f64 {
+    2.0
+}
+
+fn default_frequency() -> f64 {
+    1.0
+}
+
+/// Domain warping, which pushes the noise around by a second copy of
+/// itself.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WarpConfig {
+    /// How far the noise is pushed, in the same units as
+    /// `image_scale`.
+    pub strength: f64,
+    /// How much finer the warping is than the noise.
+    #[serde(default = "default_frequency")]
+    pub frequency: f64,
+}
+
+/// Lines along the noise at every `interval`, like the contour lines
+/// of a map.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ContourConfig {
+    /// The difference in noise value between lines, from `0.0` to
+    /// `1.0`.
+    pub interval: f32,
+    /// The width of the lines, in pixels.
+    #[serde(default = "default_contour_width")]
+    pub width: f32,
+    pub color: Color,
+    /// Makes every nth line an index line, which defaults to twice as
+    /// wide as the others and to the same color.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index_every: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index_width: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index_color: Option<Color>,
+    /// Colors for the bands between the lines, from the lowest to the
+    /// highest.  Without any, the noise is drawn under the lines with
+    /// its own colors.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bands: Vec<Color>,
+}
+
+fn default_contour_width() -> f32 {
+    1.0
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Scale {
+    Uniform(f64),
+    Axes([f64; 2]),
+}
+
+fn default_value_scale() -> f64 {
+    1.0
+}
+
+impl NoiseConfig {
+    fn build(
+        &self,
+        supersample: Option<&SupersampleConfig>,
+    ) -> Result<Box<dyn Generator<image::Rgb<u8>>>> {
+        let (background, block) = match (self.background_color, self.block_color) {
+            (Some(background), Some(block)) => {
+                let background = background.into();
+                (background, block.over(background))
+            }
+            _ if self.gradient.is_some() || self.has_bands() => {
+                (crate::hex(0x000000), crate::hex(0x000000))
+            }
+            _ => {
+                return Err(Error::parameter(
+                    "noise",
+                    "block_color",
+                    "needs a background_color and block_color, a gradient, or contour bands",
+                ))
+            }
+        };
+        let mut generator = generator::NoiseGenerator::new(
+            self.image_size,
+            background,
+            block,
+            1.0,
+            self.value_scale,
+        );
+        generator.gradient = self.gradient.as_ref().map(|g| g.build()).transpose()?;
+        generator.image_scale = match self.image_scale {
+            Scale::Uniform(v) => [v, v],
+            Scale::Axes(v) => v,
+        };
+        // Moving noise is sampled from 4D noise instead, which doesn't
+        // look the same as the 2D noise that still images have always
+        // used.  That's OpenSimplex, since `noise`'s 4D Perlin jumps
+        // wherever a coordinate crosses a whole number.
+        if self.source.is_none() && self.warp.is_none() {
+            if self.motion == 0.0 {
+                return self.finish(generator, supersample);
+            }
+            let f = generator::DownSample::new(noise::OpenSimplex::new(), 0.0, 0.0)
+                .with_motion(self.motion);
+            return self.finish(generator.with_function(f), supersample);
+        }
+
+        let source = match self.source.as_ref() {
+            Some(source) => source.build()?,
+            None if self.motion == 0.0 => Source::new(SourceKind::Perlin),
+            None => Source::new(SourceKind::OpenSimplex),
+        };
+        if self.motion == 0.0 {
+            return self.finish(generator.with_function(source), supersample);
+        }
+        if matches!(&self.source, Some(s) if s.kind.is_perlin()) {
+            return Err(Error::parameter(
+                "noise",
+                "source",
+                "noise built on Perlin noise can't move; use open_simplex, worley or value",
+            ));
+        }
+        let f = generator::DownSample::new(source, 0.0, 0.0).with_motion(self.motion);
+        self.finish(generator.with_function(f), supersample)
+    }
+
+    fn has_bands(&self) -> bool {
+        matches!(&self.contours, Some(c) if !c.bands.is_empty())
+    }
+
+    /// Boxes the generator, warping its noise first if there's a
+    /// warp, and drawing it as contours if there are any.
+    fn finish<F>(
+        &self,
+        generator: generator::NoiseGenerator<F>,
+        supersample: Option<&SupersampleConfig>,
+    ) -> Result<Box<dyn Generator<image::Rgb<u8>>>>
+    where
+        F: NoiseFn<[f64; 2]> + Seedable + Animated + Send + Sync + 'static,
+    {
+        let warp = match self.warp.as_ref() {
+            Some(warp) => warp,
+            None => return self.contoured(generator, supersample),
+        };
+        let f = generator.function().clone();
+        let f = Warp::new(f, warp.strength).with_frequency(warp.frequency);
+        self.contoured(generator.with_function(f), supersample)
+    }
+
+    fn contoured<F>(
+        &self,
+        generator: generator::NoiseGenerator<F>,
+        supersample: Option<&SupersampleConfig>,
+    ) -> Result<Box<dyn Generator<image::Rgb<u8>>>>
+    where
+        F: NoiseFn<[f64; 2]> + Seedable + Animated + Send + Sync + 'static,
+    {
+        match self.contours.as_ref() {
+            Some(contours) => boxed(contours.build(generator)?, supersample),
+            None => boxed(generator, supersample),
+        }
+    }
+}
+
+impl ContourConfig {
+    pub fn build<F: NoiseFn<[f64; 2]>>(
+        &self,
+        noise: generator::NoiseGenerator<F>,
+    ) -> Result<generator::ContourGenerator<F>> {
+        if !(self.interval > 0.0 && self.interval < 1.0) {
+            return Err(Error::parameter(
+                "noise",
+                "contours.interval",
+                format!("must be between 0 and 1, not {}", self.interval),
+            ));
+        }
+        let widths = [
+            ("contours.width", Some(self.width)),
+            ("contours.index_width", self.index_width),
+        ];
+        for (name, width) in widths.iter() {
+            if matches!(width, Some(w) if *w <= 0.0) {
+                return Err(Error::parameter(
+                    "noise",
+                    name,
+                    "lines must be wider than 0 pixels",
+                ));
+            }
+        }
+        if self.index_every == Some(0) {
+            return Err(Error::parameter(
+                "noise",
+                "contours.index_every",
+                "must be at least 1",
+            ));
+        }
+
+        let mut contours = generator::ContourGenerator::new(noise, self.interval, self.color.0);
+        contours.width = self.width;
+        contours.index_every = self.index_every;
+        contours.index_width = self.index_width.unwrap_or(self.width * 2.0);
+        contours.index_color = self.index_color.unwrap_or(self.color).0;
+        contours.bands = self.bands.iter().map(|&color| color.into()).collect();
+        Ok(contours)
+    }
+}
+
+impl SourceConfig {
+    pub fn build(&self) -> Result<Source> {
+        let octaves = self.octaves.unwrap_or_else(|| self.kind.default_octaves());
+        if !(1..=noise::Fbm::MAX_OCTAVES).contains(&octaves) {
+            return Err(Error::parameter(
+                "noise",
+                "source.octaves",
+                format!("must be from 1 to {}", noise::Fbm::MAX_OCTAVES),
+            ));
+        }
+        if self.flat && self.kind != SourceKind::Worley {
+            return Err(Error::parameter(
+                "noise",
+                "source.flat",
+                "only worley noise has flat cells",
+            ));
+        }
+        let source = Source::new(self.kind)
+            .set_octaves(octaves)
+            .set_persistence(self.persistence)
+            .set_lacunarity(self.lacunarity)
+            .set_frequency(self.frequency);
+        Ok(match self.flat {
+            true => source.with_flat_cells(),
+            false => source,
+        })
+    }
+}
+
+/// A gradient through any number of colors.  Stops are either a
+/// color, or a `{ position, color }` table; stops without a position
+/// are spread evenly between the ones around them, and the first and
+/// last default to `0.0` and `1.0`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GradientConfig {
+    pub stops: Vec<StopConfig>,
+    #[serde(default = "default_interpolation")]
+    pub interpolation: Interpolation,
+    /// Holds each color up to the next stop, instead of blending, for
+    /// flat bands of color.
+    #[serde(default)]
+    pub hard: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum StopConfig {
+    Color(Color),
+    Stop(StopEntry),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StopEntry {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<f32>,
+    pub color: Color,
+}
+
+fn default_interpolation() -> Interpolation {
+    Interpolation::Oklab
+}
+
+impl GradientConfig {
+    pub fn build(&self) -> Result<Gradient> {
+        let mut positions = self
+            .stops
+            .iter()
+            .map(|stop| match stop {
+                StopConfig::Color(_) => None,
+                StopConfig::Stop(entry) => entry.position,
+            })
+            .collect::<Vec<_>>();
+        if let Some(first) = positions.first_mut() {
+            first.get_or_insert(0.0);
+        }
+        if let Some(last) = positions.last_mut() {
+            last.get_or_insert(1.0);
+        }
+        let mut start = 0;
+        for end in 1..positions.len() {
+            if let Some(to) = positions[end] {
+                let from = positions[start].expect("the stop before a gap has a position");
+                let gap = (end - start) as f32;
+                for (i, position) in positions[start + 1..end].iter_mut().enumerate() {
+                    *position = Some(from + (to - from) * (i + 1) as f32 / gap);
+                }
+                start = end;
+            }
+        }
+
+        let stops = self
+            .stops
+            .iter()
+            .zip(positions)
+            .map(|(stop, position)| {
+                let color = match stop {
+                    StopConfig::Color(color) => color,
+                    StopConfig::Stop(entry) => &entry.color,
+                };
+                Stop {
+                    position: position.expect("every position is filled in"),
+                    color: (*color).into(),
+                }
+            })
+            .collect();
+        let gradient = Gradient::new(stops, self.interpolation)?;
+        Ok(match self.hard {
+            true => gradient.with_hard_stops(),
+            false => gradient,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MazeConfig {
+    pub cell_size: [Length; 2],
+    pub wall_size: [Length; 2],
+    pub image_size: Position,
+    #[serde(default)]
+    pub padding: [Length; 2],
+    pub background_color: Color,
+    pub block_color: Color,
+    #[serde(default)]
+    /// Kept sorted, so that the pipeline recorded in an image is the
+    /// same every time.
+    pub direction_weights: BTreeMap<Direction, u32>,
+    #[serde(default = "default_weight")]
+    pub default_weight: u32,
+    #[serde(default)]
+    pub algorithm: Algorithm,
+    /// How often the growing tree algorithm carves from the newest
+    /// cell, from `0.0` to `1.0`; the rest of the time, it carves from
+    /// a random one.  Defaults to half of the time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub newest: Option<f64>,
+    #[serde(default)]
+    pub shape: Shape,
+    /// How many of the dead ends have a wall knocked down, from `0.0`
+    /// to `1.0`, which adds loops to the maze.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub braid: Option<f64>,
+    /// How many of the cells are cut away from the ends of the maze,
+    /// from `0.0` to `1.0`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sparseness: Option<f64>,
+    #[serde(default = "default_entrance")]
+    pub entrance: Opening,
+    #[serde(default = "default_exit")]
+    pub exit: Opening,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub solution: Option<SolutionConfig>,
+    /// Colors the paths by how far they are from the entrance.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heatmap: Option<GradientConfig>,
+    #[serde(default)]
+    pub antialias: bool,
+}
+
+fn default_weight() -> u32 {
+    10
+}
+
+/// The top left cell, opened to the north in a square maze; the
+/// middle of a theta maze isn't opened at all.
+fn default_entrance() -> Opening {
+    Opening::new([0, 0], None)
+}
+
+/// The bottom right cell, opened to the south in a square maze, or
+/// the last cell of the outside ring of a theta maze.
+fn default_exit() -> Opening {
+    Opening::new([-1, -1], None)
+}
+
+/// Checks that a maze parameter is between `0.0` and `1.0`.
+fn fraction(value: f64, field: &'static str) -> Result<f64> {
+    match (0.0..=1.0).contains(&value) {
+        true => Ok(value),
+        false => Err(Error::parameter(
+            "maze",
+            field,
+            format!("must be between 0.0 and 1.0, not {}", value),
+        )),
+    }
+}
+
+/// The shortest path from the entrance to the exit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SolutionConfig {
+    pub color: Color,
+    #[serde(default)]
+    pub style: SolutionStyle,
+    /// The width of the line.  Defaults to a third of the cells.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<Length>,
+}
+
+impl MazeConfig {
+    fn build(&self, size: Position) -> Result<generator::MazeGenerator> {
+        if self.newest.is_some() && self.algorithm != Algorithm::GrowingTree {
+            return Err(Error::parameter(
+                "maze",
+                "newest",
+                "only the growing_tree algorithm carves from the newest cell",
+            ));
+        }
+        if self.shape != Shape::Square && self.algorithm.is_square_only() {
+            return Err(Error::parameter(
+                "maze",
+                "algorithm",
+                format!("{:?} only works on square mazes", self.algorithm),
+            ));
+        }
+        let newest = fraction(self.newest.unwrap_or(0.5), "newest")?;
+        let braid = fraction(self.braid.unwrap_or(0.0), "braid")?;
+        let sparseness = fraction(self.sparseness.unwrap_or(0.0), "sparseness")?;
+        let background = self.background_color.into();
+        let cell_size = whole_pair(self.cell_size, size, "maze", "cell_size")?;
+        let solution = match self.solution.as_ref() {
+            Some(solution) => {
+                let width = match solution.width {
+                    Some(width) => width.pixels(size) as f32,
+                    None => (cell_size.x().min(cell_size.y()) as f32 / 3.0).max(1.0),
+                };
+                if width <= 0.0 {
+                    return Err(Error::parameter(
+                        "maze",
+                        "solution.width",
+                        "the line must be wider than 0 pixels",
+                    ));
+                }
+                Some(Solution {
+                    color: solution.color.over(background),
+                    style: solution.style,
+                    width,
+                })
+            }
+            None => None,
+        };
+        Ok(generator::MazeGenerator {
+            cell_size,
+            wall_size: whole_pair(self.wall_size, size, "maze", "wall_size")?,
+            image_size: self.image_size,
+            padding: whole_pair(self.padding, size, "maze", "padding")?,
+            background_color: background,
+            block_color: self.block_color.over(background),
+            direction_weights: self.direction_weights.clone().into_iter().collect(),
+            default_weight: self.default_weight,
+            algorithm: self.algorithm,
+            newest,
+            shape: self.shape,
+            braid,
+            sparseness,
+            entrance: self.entrance,
+            exit: self.exit,
+            solution,
+            heatmap: self.heatmap.as_ref().map(|g| g.build()).transpose()?,
+            antialias: self.antialias,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StripeConfig {
+    pub image_size: Position,
+    pub stripes: Vec<StripeEntry>,
+    #[serde(default)]
+    pub stripe_shift: f64,
+    #[serde(default)]
+    pub top_offset: Length,
+    pub background_color: Color,
+    #[serde(default = "default_error_color")]
+    pub error_color: Color,
+    #[serde(default)]
+    pub antialias: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StripeEntry {
+    pub width: Length,
+    pub color: Color,
+    #[serde(default)]
+    pub padding_bottom: Length,
+}
+
+fn default_error_color() -> Color {
+    Color::from(crate::hex(0xff0000))
+}
+
+impl StripeConfig {
+    fn build(&self, size: Position) -> Result<generator::StripeGenerator> {
+        let background = self.background_color.into();
+        let stripes = self
+            .stripes
+            .iter()
+            .map(|s| {
+                Ok(generator::stripe::Stripe::new(
+                    s.width.whole(size, "stripe", "stripes.width")?,
+                    s.color.over(background),
+                    s.padding_bottom
+                        .whole(size, "stripe", "stripes.padding_bottom")?,
+                ))
+            })
+            .collect::<Result<_>>()?;
+        Ok(generator::StripeGenerator {
+            image_size: self.image_size,
+            stripes,
+            stripe_shift: self.stripe_shift,
+            top_offset: self.top_offset.whole(size, "stripe", "top_offset")?,
+            background_color: background,
+            error_color: self.error_color.over(background),
+            antialias: self.antialias,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BarsConfig {
+    pub image_size: Position,
+    pub bars: Vec<BarEntry>,
+    #[serde(default)]
+    pub initial_offset: Length,
+    /// How far the bars are turned, in degrees; clockwise for
+    /// vertical bars, and counterclockwise for horizontal ones.
+    #[serde(default)]
+    pub angle: f64,
+    /// Lays the bars out from left to right, instead of from top to
+    /// bottom, before they're turned.
+    #[serde(default)]
+    pub vertical: bool,
+    /// Repeats the bars over the whole image.
+    #[serde(default)]
+    pub repeat: bool,
+    pub background_color: Color,
+    #[serde(default)]
+    pub antialias: bool,
+}
+
+/// A single bar.  Bars are laid out one after the other; `gap` is
+/// the distance from the end of the previous bar to the start of
+/// this one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BarEntry {
+    #[serde(default)]
+    pub gap: Length,
+    pub size: Length,
+    /// The color of the bar, which is needed unless there's a
+    /// gradient across it instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<Color>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gradient: Option<GradientConfig>,
+}
+
+impl BarsConfig {
+    fn build(&self, size: Position) -> Result<generator::BarGenerator> {
+        let background = self.background_color.into();
+        // Unturned, horizontal bars are laid out down the image, and
+        // vertical ones across it.  The angle leans the far end of each
+        // bar back towards the start, the way it always has, and so
+        // turns horizontal bars the other way to vertical ones.
+        let angle = match self.vertical {
+            true => self.angle,
+            false => 90.0 - self.angle,
+        }
+        .to_radians();
+        let mut generator = generator::BarGenerator {
+            image_size: self.image_size,
+            bars: vec![],
+            initial_offset: self.initial_offset.whole(size, "bars", "initial_offset")?,
+            direction: [angle.cos(), angle.sin()],
+            repeat: self.repeat,
+            background_color: background,
+            antialias: self.antialias,
+        };
+        for bar in self.bars.iter() {
+            let gradient = bar.gradient.as_ref().map(|g| g.build()).transpose()?;
+            let color = match bar.color {
+                Some(color) => color.over(background),
+                None if gradient.is_some() => background,
+                None => {
+                    return Err(Error::parameter(
+                        "bars",
+                        "bars",
+                        "each bar needs a color or a gradient",
+                    ))
+                }
+            };
+            generator.push_bar(
+                bar.gap.whole(size, "bars", "bars.gap")?,
+                bar.size.whole(size, "bars", "bars.size")?,
+                color,
+                gradient,
+            );
+        }
+        Ok(generator)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RandomConfig {
+    pub block_size: [Length; 2],
+    pub image_size: Position,
+    #[serde(default)]
+    pub padding: [Length; 2],
+    pub block_color: Color,
+    pub background_color: Color,
+    #[serde(default = "default_distribution")]
+    pub distribution: f64,
+}
+
+fn default_distribution() -> f64 {
+    0.5
+}
+
+impl RandomConfig {
+    fn build(&self, size: Position) -> Result<generator::RandomGenerator<image::Rgb<u8>>> {
+        let background = self.background_color.into();
+        Ok(generator::RandomGenerator {
+            block_size: whole_pair(self.block_size, size, "random", "block_size")?,
+            image_size: self.image_size,
+            padding: whole_pair(self.padding, size, "random", "padding")?,
+            block_color: self.block_color.over(background),
+            background_color: background,
+            distribution: self.distribution,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileConfig {
+    pub path: std::path::PathBuf,
+}
+
+impl FileConfig {
+    fn build(&self) -> generator::FileGenerator {
+        generator::FileGenerator::new(&self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::Context;
+
+    fn bars(options: &str) -> image::RgbImage {
+        let config: BarsConfig = toml::from_str(&format!(
+            r##"
+            image_size = [24, 20]
+            initial_offset = 3
+            background_color = "#000000"
+            bars = [
+                {{ size = 4, color = "#ffffff" }},
+                {{ gap = 2, size = 6, color = "#ff0000" }},
+            ]
+            {}
+            "##,
+            options
+        ))
+        .unwrap();
+        config
+            .build(config.image_size)
+            .unwrap()
+            .generate(&Context::new(1))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_bars_match_their_old_output() {
+        // How bars used to be drawn: each covers the offsets strictly
+        // between where it starts and ends, measured from the initial
+        // offset, and later bars cover up earlier ones.
+        let old = |offset: u32| {
+            let offset = offset.checked_sub(3)?;
+            [(0, 4, 0xffffff), (6, 6, 0xff0000)]
+                .iter()
+                .rev()
+                .find(|&&(o, s, _)| offset > o && offset < o + s)
+                .map(|&(_, _, color)| crate::hex(color))
+        };
+        for &vertical in [false, true].iter() {
+            let image = bars(&format!("vertical = {}", vertical));
+            for (x, y, pixel) in image.enumerate_pixels() {
+                let offset = if vertical { x } else { y };
+                assert_eq!(*pixel, old(offset).unwrap_or(crate::hex(0)));
+            }
+        }
+
+        // Turning the bars leans the far end of each back towards the
+        // start, so horizontal bars rise to the right, and vertical
+        // ones lean to the left going down.
+        let first = |image: &image::RgbImage, across: u32, vertical: bool| {
+            (0..20)
+                .find(|&along| {
+                    let (x, y) = if vertical {
+                        (along, across)
+                    } else {
+                        (across, along)
+                    };
+                    image.get_pixel(x, y)[1] == 0xff
+                })
+                .unwrap()
+        };
+        let image = bars("angle = 10.0");
+        assert!(first(&image, 19, false) < first(&image, 0, false));
+        let image = bars("angle = 10.0\nvertical = true");
+        assert!(first(&image, 19, true) < first(&image, 0, true));
+    }
+
+    #[test]
+    fn test_gradient_stops() {
+        let gradient: GradientConfig = toml::from_str(
+            r##"stops = ["navy", "teal", { position = 0.8, color = "wheat" }, "white"]"##,
+        )
+        .unwrap();
+        let positions = gradient
+            .build()
+            .unwrap()
+            .stops()
+            .iter()
+            .map(|stop| stop.position)
+            .collect::<Vec<_>>();
+        assert_eq!(positions, vec![0.0, 0.4, 0.8, 1.0]);
+
+        // Noise needs either colors or a gradient.
+        let missing: NoiseConfig = toml::from_str(
+            r##"
+            image_size = [8, 8]
+            image_scale = 1.0
+            "##,
+        )
+        .unwrap();
+        assert!(missing.build(None).is_err());
+    }
+
+    #[test]
+    fn test_noise_sources() {
+        let moving = |source: &str| {
+            let mut config: NoiseConfig = toml::from_str(
+                r##"
+                image_size = [8, 8]
+                image_scale = 1.0
+                block_color = "navy"
+                background_color = "white"
+                motion = 0.5
+                "##,
+            )
+            .unwrap();
+            config.source = Some(toml::from_str(source).unwrap());
+            config.build(None)
+        };
+        assert!(moving(r#"type = "worley""#).is_ok());
+        assert!(moving(r#"type = "ridged_multi""#).is_err());
+        assert!(moving("type = \"value\"\noctaves = 40").is_err());
+        assert!(moving("type = \"value\"\nflat = true").is_err());
+    }
+
+    #[test]
+    fn test_only_shapes_are_vectors() {
+        let noise: GeneratorConfig = toml::from_str(
+            r##"
+            type = "noise"
+            image_size = [8, 8]
+            image_scale = 1.0
+            block_color = "navy"
+            background_color = "white"
+            "##,
+        )
+        .unwrap();
+        assert!(noise.build_vector(Position::new(8, 8)).is_err());
+    }
+
+    #[test]
+    fn test_bars() {
+        // Horizontal bars turned a quarter of the way around are
+        // vertical bars.
+        assert_eq!(bars("angle = 90.0"), bars("vertical = true"));
+
+        let config: BarsConfig = toml::from_str(
+            r##"
+            image_size = [12, 10]
+            background_color = "#000000"
+            bars = [{ size = 10 }]
+            "##,
+        )
+        .unwrap();
+        assert!(config.build(config.image_size).is_err());
+    }
+}
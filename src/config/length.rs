@@ -0,0 +1,154 @@
+use crate::error::{Error, Result};
+use crate::position::Position;
+use serde::{Deserialize, Serialize};
+
+/// A distance in a pipeline description.  Numbers are in pixels, and
+/// strings like `"2.5%"` are a percentage of the shorter side of the
+/// image, so that a pipeline looks the same at every size it's
+/// rendered at.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Length {
+    Pixels(f64),
+    Percent(f64),
+}
+
+impl Length {
+    /// The length in pixels, in an image of the given size.
+    pub fn pixels(self, size: Position) -> f64 {
+        match self {
+            Length::Pixels(v) => v,
+            Length::Percent(v) => v / 100.0 * size.x().min(size.y()) as f64,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        matches!(self, Length::Pixels(v) | Length::Percent(v) if *v == 0.0)
+    }
+
+    /// The length as a whole number of pixels, in an image of the
+    /// given size.  Negative lengths are an error for the given
+    /// field.
+    pub(crate) fn whole(
+        self,
+        size: Position,
+        stage: &'static str,
+        field: &'static str,
+    ) -> Result<u32> {
+        let pixels = self.pixels(size).round();
+        if pixels < 0.0 || !pixels.is_finite() {
+            return Err(Error::parameter(
+                stage,
+                field,
+                format!("must not be negative, not {}", self),
+            ));
+        }
+        Ok(pixels as u32)
+    }
+
+    fn parse(value: &str) -> Option<Length> {
+        let value = value.trim();
+        match value.strip_suffix('%') {
+            Some(percent) => percent.trim().parse().ok().map(Length::Percent),
+            None => value.parse().ok().map(Length::Pixels),
+        }
+    }
+}
+
+/// Resolves a pair of lengths, like a padding or a cell size, into
+/// whole pixels.
+pub(crate) fn whole_pair(
+    lengths: [Length; 2],
+    size: Position,
+    stage: &'static str,
+    field: &'static str,
+) -> Result<Position> {
+    Ok(Position::new(
+        lengths[0].whole(size, stage, field)?,
+        lengths[1].whole(size, stage, field)?,
+    ))
+}
+
+impl Default for Length {
+    fn default() -> Self {
+        Length::Pixels(0.0)
+    }
+}
+
+impl From<u32> for Length {
+    fn from(pixels: u32) -> Self {
+        Length::Pixels(pixels as f64)
+    }
+}
+
+impl std::fmt::Display for Length {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Length::Pixels(v) => write!(f, "{}", v),
+            Length::Percent(v) => write!(f, "\"{}%\"", v),
+        }
+    }
+}
+
+impl Serialize for Length {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match *self {
+            Length::Pixels(v) if v.fract() == 0.0 && v >= 0.0 && v <= u32::MAX as f64 => {
+                serializer.serialize_u32(v as u32)
+            }
+            Length::Pixels(v) => serializer.serialize_f64(v),
+            Length::Percent(v) => serializer.serialize_str(&format!("{}%", v)),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Length {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = Length;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str("a number of pixels, or a percentage like \"2.5%\"")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<Length, E> {
+                Length::parse(value)
+                    .ok_or_else(|| E::invalid_value(serde::de::Unexpected::Str(value), &self))
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<Length, E> {
+                Ok(Length::Pixels(value as f64))
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, value: i64) -> Result<Length, E> {
+                Ok(Length::Pixels(value as f64))
+            }
+
+            fn visit_f64<E: serde::de::Error>(self, value: f64) -> Result<Length, E> {
+                Ok(Length::Pixels(value))
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lengths() {
+        let lengths: Vec<Length> = serde_json::from_str(r#"[12, 2.5, "10%"]"#).unwrap();
+        let size = Position::new(1920, 1080);
+        assert_eq!(lengths[0].pixels(size), 12.0);
+        assert_eq!(lengths[1].pixels(size), 2.5);
+        assert_eq!(lengths[2].pixels(size), 108.0);
+        assert_eq!(
+            serde_json::to_string(&lengths).unwrap(),
+            r#"[12,2.5,"10%"]"#
+        );
+        assert!(Length::Pixels(-1.0).whole(size, "crop", "top").is_err());
+    }
+}
@@ -0,0 +1,200 @@
+//! Declarative pipeline descriptions.  A pipeline is a single
+//! generator followed by an ordered list of filters, and can be
+//! loaded from either a TOML or a JSON file:
+//!
+//! ```toml
+//! [generator]
+//! type = "noise"
+//! image_size = [1080, 1920]
+//! background_color = "#214f72"
+//! block_color = "#7addaa"
+//! image_scale = 4.0
+//!
+//! [[filters]]
+//! type = "blur"
+//! sigma = 1.0
+//! ```
+
+mod color;
+pub mod filter;
+pub mod generator;
+mod length;
+
+use crate::animation::Animation;
+use crate::error::{Error, Result};
+use crate::layout::Layout;
+use crate::position::Position;
+use crate::scheme::Export;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+pub use self::color::Color;
+pub use self::filter::FilterConfig;
+pub use self::generator::{GeneratorConfig, SupersampleConfig};
+pub use self::length::Length;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// The master seed for the pipeline.  If this isn't given, a
+    /// random one is picked for every run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    pub generator: GeneratorConfig,
+    #[serde(default)]
+    pub filters: Vec<FilterConfig>,
+    /// Extra room rendered around every side of the image, which is
+    /// cropped off again after the last filter.  This keeps filters
+    /// that pull in or shift the edges of the image, like `barrel`,
+    /// from showing the background.  The generator's own lengths,
+    /// like its padding, are measured in the grown image.
+    #[serde(default, skip_serializing_if = "Length::is_zero")]
+    pub bleed: Length,
+    /// The sizes to render the pipeline at.  If there are none, the
+    /// image is rendered once, at the generator's `image_size`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub targets: Vec<Target>,
+    /// The monitors to span the image across.  The generator's
+    /// `image_size` is replaced with the size of the canvas that
+    /// covers all of them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layout: Option<Layout>,
+    /// Renders the pipeline as a looping animation, instead of a
+    /// still image.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub animation: Option<Animation>,
+    /// Writes a terminal color scheme taken from the finished image.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scheme: Option<Export>,
+    /// Draws the generator's image several times as large, and scales
+    /// it back down before the filters, to smooth its edges.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supersample: Option<SupersampleConfig>,
+}
+
+/// A named size to render a pipeline at, like a phone or a monitor.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Target {
+    pub name: String,
+    pub size: Position,
+}
+
+impl Config {
+    /// Loads a pipeline from the given path.  The format is picked
+    /// based off of the extension of the file; `.json` files are
+    /// parsed as JSON, and everything else is parsed as TOML.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Config> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => Config::from_json(&source),
+            _ => Config::from_toml(&source),
+        }
+    }
+
+    pub fn from_toml(source: &str) -> Result<Config> {
+        Ok(toml::from_str(source)?)
+    }
+
+    pub fn from_json(source: &str) -> Result<Config> {
+        Ok(serde_json::from_str(source)?)
+    }
+
+    /// The same pipeline, with the generator's `image_size` replaced.
+    /// This fails for generators that don't have a size.
+    pub fn with_size(&self, size: Position) -> Result<Config> {
+        let mut config = self.clone();
+        match config.generator.image_size_mut() {
+            Some(image_size) => *image_size = size,
+            None => {
+                return Err(Error::parameter(
+                    config.generator.name(),
+                    "image_size",
+                    "this generator has no size to change",
+                ))
+            }
+        }
+        Ok(config)
+    }
+
+    /// The pipeline for a single one of its targets; this is the
+    /// pipeline at the target's size, without any targets of its
+    /// own.
+    pub fn for_target(&self, target: &Target) -> Result<Config> {
+        let mut config = self.with_size(target.size)?;
+        config.targets.clear();
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_toml() {
+        let config = Config::from_toml(
+            r##"
+            [generator]
+            type = "maze"
+            cell_size = [14, 14]
+            wall_size = [6, 6]
+            image_size = [3560, 1560]
+            padding = [254, 204]
+            background_color = "#333333"
+            block_color = 0xc0c0c0
+            default_weight = 10
+            direction_weights = { west = 30, east = 30 }
+
+            [[filters]]
+            type = "blur"
+            sigma = 1.0
+
+            [[filters]]
+            type = "crop"
+            top = 60
+            right = 60
+            bottom = 60
+            left = 60
+            "##,
+        )
+        .unwrap();
+
+        match config.generator {
+            GeneratorConfig::Maze(maze) => {
+                assert_eq!(maze.block_color, Color::from(crate::hex(0xc0c0c0)));
+                assert_eq!(maze.direction_weights.len(), 2);
+            }
+            other => panic!("unexpected generator {:?}", other),
+        }
+        assert_eq!(config.filters.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let config = Config::from_json(
+            r##"{
+                "generator": { "type": "file", "path": "v.png" },
+                "filters": [{ "type": "scan", "lines": 480, "vary": 8 }]
+            }"##,
+        )
+        .unwrap();
+
+        assert_eq!(config.filters.len(), 1);
+    }
+
+    #[test]
+    fn test_unknown_fields() {
+        let result = Config::from_toml(
+            r##"
+            [generator]
+            type = "file"
+            path = "v.png"
+            size = [10, 10]
+            "##,
+        );
+
+        assert!(result.is_err());
+    }
+}
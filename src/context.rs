@@ -0,0 +1,147 @@
+use rand::SeedableRng;
+
+/// The random number generator handed out to generators and filters.
+/// This is picked over `rand`'s `StdRng` and `SmallRng` because its
+/// output is stable across platforms and versions, which is what
+/// makes a seed reproducible.
+pub type StageRng = rand_pcg::Pcg64Mcg;
+
+/// The state of a single pipeline run that is shared with every
+/// generator and filter in it.
+///
+/// A run starts with one master seed.  Each stage of the pipeline
+/// then gets its own context via [`Context::stage`], whose seed is
+/// derived from the master seed and the position of the stage, so
+/// that adding randomness to one stage doesn't change the output of
+/// any of the others.
+///
+/// When a pipeline is animated, it's rendered once for every frame,
+/// with a context from [`Context::at_frame`].  Stages that move over
+/// time read [`Context::time`]; a still image is frame `0` of `1`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Context {
+    seed: u64,
+    frame: u32,
+    frames: u32,
+}
+
+impl Context {
+    pub fn new(seed: u64) -> Self {
+        Context {
+            seed,
+            frame: 0,
+            frames: 1,
+        }
+    }
+
+    /// Creates a context with a random master seed.  Random seeds
+    /// are kept to 53 bits, so that they survive a trip through
+    /// both TOML (which only has signed integers) and JSON parsers
+    /// that read every number as a double.
+    pub fn random() -> Self {
+        Context::new(rand::random::<u64>() >> 11)
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The context for `frame` of an animation that is `frames` long.
+    pub fn at_frame(&self, frame: u32, frames: u32) -> Context {
+        Context {
+            frame,
+            frames: frames.max(1),
+            ..*self
+        }
+    }
+
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// How far through the animation this frame is, from `0.0` up to
+    /// (but not including) `1.0`.  Anything that moves should be back
+    /// where it started at `1.0`, so that the animation loops without
+    /// a jump.
+    pub fn time(&self) -> f64 {
+        self.frame as f64 / self.frames as f64
+    }
+
+    /// The context for the stage at `index` in the pipeline.
+    pub fn stage(&self, index: u64) -> Context {
+        Context {
+            seed: split(self.seed, index),
+            ..*self
+        }
+    }
+
+    /// A fresh random number generator for this context.  Calling
+    /// this twice gives two generators with the same output.
+    pub fn rng(&self) -> StageRng {
+        StageRng::seed_from_u64(self.seed)
+    }
+
+    /// A random number generator for the row `y` of an image.  Each
+    /// row gets its own stream, so that rows can be worked on in any
+    /// order, or all at once, and still give the same output.
+    pub fn row_rng(&self, y: u32) -> StageRng {
+        StageRng::seed_from_u64(split(self.seed, y as u64))
+    }
+
+    /// A random number generator that is different for every frame
+    /// of an animation, for stages that should change from frame to
+    /// frame.  On the first frame, and so in a still image, this is
+    /// the same as [`Context::rng`].
+    pub fn frame_rng(&self) -> StageRng {
+        match self.frame {
+            0 => self.rng(),
+            // Inverted, so as not to share a stream with a row.
+            frame => StageRng::seed_from_u64(split(self.seed, !(frame as u64))),
+        }
+    }
+}
+
+// SplitMix64, over the seed offset by the stream.  This is the usual
+// way of turning one seed into many well-distributed ones.
+fn split(seed: u64, stream: u64) -> u64 {
+    let mut z = seed.wrapping_add(stream.wrapping_add(1).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn test_stages_are_reproducible() {
+        let context = Context::new(0x5eed);
+        assert_eq!(context.stage(3), Context::new(0x5eed).stage(3));
+        assert_ne!(context.stage(0), context.stage(1));
+        assert_ne!(context.stage(0), Context::new(0x5eee).stage(0));
+
+        let a: u64 = context.stage(2).rng().gen();
+        let b: u64 = context.stage(2).rng().gen();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_frames_keep_their_stages() {
+        let context = Context::new(0x5eed);
+        let frame = context.at_frame(3, 4).stage(2);
+        assert_eq!(frame.seed(), context.stage(2).seed());
+        assert_eq!(frame.time(), 0.75);
+
+        let a: u64 = context.at_frame(0, 4).frame_rng().gen();
+        let b: u64 = context.rng().gen();
+        let c: u64 = context.at_frame(1, 4).frame_rng().gen();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+}
@@ -0,0 +1,91 @@
+use std::fmt;
+
+/// Everything that can go wrong while loading, rendering or saving
+/// a pipeline.
+#[derive(Debug)]
+pub enum Error {
+    /// A generator or filter was given a parameter it can't work
+    /// with.  `stage` is the name of the generator or filter, as used
+    /// in a pipeline.
+    Parameter {
+        stage: &'static str,
+        field: &'static str,
+        reason: String,
+    },
+    Io(std::io::Error),
+    Image(image::ImageError),
+    Toml(toml::de::Error),
+    Json(serde_json::Error),
+    PngEncoding(png::EncodingError),
+    PngDecoding(png::DecodingError),
+    GifEncoding(gif::EncodingError),
+    /// An image doesn't have a pipeline recorded in it.
+    MissingPipeline,
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+impl Error {
+    pub fn parameter<R: Into<String>>(stage: &'static str, field: &'static str, reason: R) -> Self {
+        Error::Parameter {
+            stage,
+            field,
+            reason: reason.into(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Parameter {
+                stage,
+                field,
+                reason,
+            } => write!(f, "invalid {}.{}: {}", stage, field, reason),
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Image(e) => write!(f, "{}", e),
+            Error::Toml(e) => write!(f, "invalid pipeline: {}", e),
+            Error::Json(e) => write!(f, "invalid pipeline: {}", e),
+            Error::PngEncoding(e) => write!(f, "could not write png: {}", e),
+            Error::PngDecoding(e) => write!(f, "could not read png: {}", e),
+            Error::GifEncoding(e) => write!(f, "could not write gif: {}", e),
+            Error::MissingPipeline => {
+                f.write_str("the image does not have a pipeline recorded in it")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Image(e) => Some(e),
+            Error::Toml(e) => Some(e),
+            Error::Json(e) => Some(e),
+            Error::PngEncoding(e) => Some(e),
+            Error::PngDecoding(e) => Some(e),
+            Error::GifEncoding(e) => Some(e),
+            Error::Parameter { .. } | Error::MissingPipeline => None,
+        }
+    }
+}
+
+macro_rules! from {
+    ($variant:ident, $source:ty) => {
+        impl From<$source> for Error {
+            fn from(e: $source) -> Self {
+                Error::$variant(e)
+            }
+        }
+    };
+}
+
+from!(Io, std::io::Error);
+from!(Image, image::ImageError);
+from!(Toml, toml::de::Error);
+from!(Json, serde_json::Error);
+from!(PngEncoding, png::EncodingError);
+from!(PngDecoding, png::DecodingError);
+from!(GifEncoding, gif::EncodingError);
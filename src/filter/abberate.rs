@@ -1,10 +1,15 @@
-use std::{cell::RefCell, num::NonZeroUsize};
+use std::num::NonZeroUsize;
+
+use crate::context::Context;
+use crate::error::{Error, Result};
 
 use rand::distributions::{Bernoulli, Distribution};
 use rand::Rng;
 use rand_distr::Normal;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ColorSpace {
     Rgb,
     Yiq,
@@ -25,23 +30,22 @@ impl AbberateFilter {
 }
 
 impl super::Filter for AbberateFilter {
-    fn filter(&self, image: &mut image::RgbImage) {
-        let height = image.dimensions().1;
-        for y in 0..height {
+    fn filter(&self, image: &mut image::RgbImage, _context: &Context) -> Result<()> {
+        crate::rows::for_each_row(image, |mut row| {
             for (i, shift) in self.channel_shifts.iter().cloned().enumerate() {
                 let direction = shift.is_positive();
-                let norm = shift.abs() as u32;
+                let norm = shift.unsigned_abs();
 
                 super::shift_row(
-                    image,
-                    y,
+                    &mut row,
                     norm,
                     direction,
                     |from, to| shift_color(i, self.color_space, from, to),
                     |_to| {},
                 )
             }
-        }
+        });
+        Ok(())
     }
 }
 
@@ -71,20 +75,40 @@ impl NullFilter<Normal<f32>> {
         selection: f64,
         average_null: f32,
         null_component: impl Into<Option<usize>>,
-    ) -> Self {
-        NullFilter {
-            null_start_chance: Bernoulli::new(selection)
-                .expect("selection distribution should be between 0 and 1"),
-            null_distribution: Normal::new(average_null, average_null / 2.0).expect("???"),
-            null_component: null_component.into(),
-            color_space: ColorSpace::Rgb,
+    ) -> Result<Self> {
+        let null_component = null_component.into();
+        if let Some(component) = null_component.filter(|c| *c > 2) {
+            return Err(Error::parameter(
+                "null",
+                "component",
+                format!("must be 0, 1 or 2, not {}", component),
+            ));
         }
+
+        Ok(NullFilter {
+            null_start_chance: Bernoulli::new(selection).map_err(|_| {
+                Error::parameter(
+                    "null",
+                    "selection",
+                    format!("must be between 0.0 and 1.0, not {}", selection),
+                )
+            })?,
+            null_distribution: Normal::new(average_null, average_null / 2.0).map_err(|_| {
+                Error::parameter(
+                    "null",
+                    "average_null",
+                    format!("must be a positive number, not {}", average_null),
+                )
+            })?,
+            null_component,
+            color_space: ColorSpace::Rgb,
+        })
     }
 }
 
-impl<D: Distribution<f32>> super::Filter for NullFilter<D> {
-    fn filter(&self, image: &mut image::RgbImage) {
-        let mut rng = rand::thread_rng();
+impl<D: Distribution<f32> + Send + Sync> super::Filter for NullFilter<D> {
+    fn filter(&self, image: &mut image::RgbImage, context: &Context) -> Result<()> {
+        let mut rng = context.rng();
         let mut current_null: Option<NonZeroUsize> = None;
         let mut current_component: usize =
             self.null_component.unwrap_or_else(|| rng.gen_range(0, 3));
@@ -97,7 +121,7 @@ impl<D: Distribution<f32>> super::Filter for NullFilter<D> {
                         null_pixel(image.get_pixel(x, y), current_component, self.color_space);
                     image.put_pixel(x, y, pixel);
                 } else {
-                    if rng.sample(&self.null_start_chance) {
+                    if rng.sample(self.null_start_chance) {
                         let stretch = rng.sample(&self.null_distribution).max(1.0) as usize;
                         current_null = NonZeroUsize::new(stretch);
                         current_component =
@@ -106,6 +130,7 @@ impl<D: Distribution<f32>> super::Filter for NullFilter<D> {
                 }
             }
         }
+        Ok(())
     }
 }
 
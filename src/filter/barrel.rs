@@ -1,3 +1,6 @@
+use crate::context::Context;
+use crate::error::Result;
+
 pub struct BarrelFilter {
     background_color: image::Rgb<u8>,
     scale: f32,
@@ -13,7 +16,7 @@ impl BarrelFilter {
 }
 
 impl super::Filter for BarrelFilter {
-    fn filter(&self, image: &mut image::RgbImage) {
+    fn filter(&self, image: &mut image::RgbImage, _context: &Context) -> Result<()> {
         let center_x = image.dimensions().0 as f32 / 2.0;
         let center_y = image.dimensions().1 as f32 / 2.0;
         *image = imageproc::geometric_transformations::warp_with(
@@ -23,7 +26,7 @@ impl super::Filter for BarrelFilter {
 
                 fn center_curve(center: f32, pos: f32) -> f32 {
                     let adjusted = (pos / center) - 1.0; // should be 0..1..2
-                    -1.0 * adjusted.powi(2) + 1.0 // should be between 0 and 1, 1 being when at the center
+                    -adjusted.powi(2) + 1.0 // should be between 0 and 1, 1 being when at the center
                 }
 
                 let adjust = (1.0 - center_curve(center_x, x))
@@ -34,6 +37,7 @@ impl super::Filter for BarrelFilter {
             },
             imageproc::geometric_transformations::Interpolation::Bilinear,
             self.background_color,
-        )
+        );
+        Ok(())
     }
 }
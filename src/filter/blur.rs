@@ -1,3 +1,6 @@
+use crate::context::Context;
+use crate::error::Result;
+
 pub struct BlurFilter {
     pub sigma: f32,
 }
@@ -9,9 +12,10 @@ impl BlurFilter {
 }
 
 impl super::Filter for BlurFilter {
-    fn filter(&self, image: &mut image::RgbImage) {
+    fn filter(&self, image: &mut image::RgbImage, _context: &Context) -> Result<()> {
         // let mut temp = image::RgbImage::from_raw(0, 0, vec![]).unwrap();
 
-        *image = image::imageops::blur(image, self.sigma)
+        *image = image::imageops::blur(image, self.sigma);
+        Ok(())
     }
 }
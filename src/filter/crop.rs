@@ -1,3 +1,6 @@
+use crate::context::Context;
+use crate::error::{Error, Result};
+
 pub struct CropFilter {
     top: u32,
     right: u32,
@@ -17,9 +20,49 @@ impl CropFilter {
 }
 
 impl super::Filter for CropFilter {
-    fn filter(&self, image: &mut image::RgbImage) {
-        let width = image.dimensions().0 - self.left - self.right;
-        let height = image.dimensions().1 - self.top - self.bottom;
+    fn filter(&self, image: &mut image::RgbImage, _context: &Context) -> Result<()> {
+        let (width, height) = image.dimensions();
+        let too_large = |field, sides| {
+            Error::parameter(
+                "crop",
+                field,
+                format!(
+                    "cropping {} from an image of {}x{} leaves nothing",
+                    sides, width, height
+                ),
+            )
+        };
+        let width = self
+            .left
+            .checked_add(self.right)
+            .and_then(|m| width.checked_sub(m))
+            .filter(|w| *w > 0)
+            .ok_or_else(|| too_large("left", format!("{} + {}", self.left, self.right)))?;
+        let height = self
+            .top
+            .checked_add(self.bottom)
+            .and_then(|m| height.checked_sub(m))
+            .filter(|h| *h > 0)
+            .ok_or_else(|| too_large("top", format!("{} + {}", self.top, self.bottom)))?;
         *image = image::imageops::crop_imm(image, self.left, self.top, width, height).to_image();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::Filter;
+
+    #[test]
+    fn test_crop() {
+        let context = Context::new(1);
+        let mut image = image::RgbImage::new(10, 8);
+        CropFilter::new(1, 2, 3, 4)
+            .filter(&mut image, &context)
+            .unwrap();
+        assert_eq!(image.dimensions(), (4, 4));
+        let filter = CropFilter::new(0, u32::MAX, 0, 1);
+        assert!(filter.filter(&mut image, &context).is_err());
     }
 }
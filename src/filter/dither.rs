@@ -1,22 +1,103 @@
+use crate::color;
+use crate::context::Context;
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+
 pub struct DitherFilter {
     matrix: Vec<Vec<f32>>,
     white: image::Rgb<u8>,
     black: image::Rgb<u8>,
+    pub method: DitherMethod,
+    /// Whether error diffusion runs back and forth across the image,
+    /// instead of left to right on every row.  This breaks up the
+    /// diagonal streaks that diffusion tends to leave behind.
+    pub serpentine: bool,
+    pub palette: DitherPalette,
+}
+
+/// How the filter decides which color each pixel ends up as.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DitherMethod {
+    /// Compares each pixel against a Bayer matrix.
+    Ordered,
+    FloydSteinberg,
+    /// Only spreads three quarters of the error, which keeps more
+    /// contrast at the cost of detail in the darkest and lightest
+    /// areas.
+    Atkinson,
+    JarvisJudiceNinke,
+    Stucki,
+    Sierra,
+}
+
+/// The colors that the filter dithers down to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DitherPalette {
+    /// Each channel on its own, in 15 even bands.
+    Bands,
+    /// The filter's white and black.
+    TwoTone,
+    Colors(Vec<image::Rgb<u8>>),
 }
 
 impl DitherFilter {
-    pub fn new(levels: u16, white: image::Rgb<u8>, black: image::Rgb<u8>) -> Self {
-        DitherFilter {
+    /// The largest `levels` accepted; the matrix has `2^(levels + 1)`
+    /// rows and columns, so anything past this is mostly memory.
+    pub const MAX_LEVELS: u16 = 8;
+
+    pub fn new(levels: u16, white: image::Rgb<u8>, black: image::Rgb<u8>) -> Result<Self> {
+        if levels > Self::MAX_LEVELS {
+            return Err(Error::parameter(
+                "dither",
+                "levels",
+                format!("must be at most {}, not {}", Self::MAX_LEVELS, levels),
+            ));
+        }
+
+        Ok(DitherFilter {
             matrix: (generate_matrix(levels)),
-            white, black
+            white, black,
+            method: DitherMethod::Ordered,
+            serpentine: false,
+            palette: DitherPalette::Bands,
+        })
+    }
+
+    /// The palette color closest to the given color, whose channels
+    /// are from `0.0` to `255.0`.
+    fn nearest(&self, color: [f32; 3]) -> image::Rgb<u8> {
+        match &self.palette {
+            DitherPalette::Bands => {
+                let band = |v: f32| {
+                    let band = (v / 255.0 * BANDS).round().clamp(0.0, BANDS);
+                    (band / BANDS * 255.0) as u8
+                };
+                image::Rgb([band(color[0]), band(color[1]), band(color[2])])
+            }
+            DitherPalette::TwoTone => closest(&[self.black, self.white], color),
+            DitherPalette::Colors(colors) => closest(colors, color),
         }
     }
 }
 
-type Matrix = Vec<Vec<f32>>;
+fn closest(colors: &[image::Rgb<u8>], color: [f32; 3]) -> image::Rgb<u8> {
+    let distance = |c: &image::Rgb<u8>| {
+        (0..3)
+            .map(|i| (c.0[i] as f32 - color[i]).powi(2))
+            .sum::<f32>()
+    };
+    colors
+        .iter()
+        .copied()
+        .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+        .expect("palettes are never empty")
+}
+
+pub(super) type Matrix = Vec<Vec<f32>>;
 
 // size is a power of 2.
-fn generate_matrix(size: u16) -> Matrix {
+pub(super) fn generate_matrix(size: u16) -> Matrix {
     // let mut out = Vec::with_capacity(levels as usize);
     // out.resize_with(levels as usize, || Vec::with_capacity(levels as usize));
     // for i in 0..2 {
@@ -31,7 +112,7 @@ fn generate_matrix(size: u16) -> Matrix {
 
     let mut matrix = base_matrix();
 
-    for i in 0..size {
+    for _i in 0..size {
         let mut set = Vec::with_capacity(4);
         multiply_matrix(&mut matrix, 4.0);
         set.push(matrix.clone());
@@ -63,7 +144,7 @@ fn generate_matrix(size: u16) -> Matrix {
 fn multiply_matrix(matrix: &mut Matrix, value: f32) {
     for row in matrix.iter_mut() {
         for item in row.iter_mut() {
-            *item = *item * value;
+            *item *= value;
         }
     }
 }
@@ -71,7 +152,7 @@ fn multiply_matrix(matrix: &mut Matrix, value: f32) {
 fn divide_matrix(matrix: &mut Matrix, value: f32) {
     for row in matrix.iter_mut() {
         for item in row.iter_mut() {
-            *item = *item / value;
+            *item /= value;
         }
     }
 }
@@ -79,7 +160,7 @@ fn divide_matrix(matrix: &mut Matrix, value: f32) {
 fn add_matrix(matrix: &mut Matrix, value: f32) {
     for row in matrix.iter_mut() {
         for item in row.iter_mut() {
-            *item = *item + value;
+            *item += value;
         }
     }
 }
@@ -115,63 +196,249 @@ fn combine_matricies(set: Vec<Matrix>) -> Matrix {
 
 fn base_matrix() -> Matrix {
     [[0f32, 2f32], [3f32, 1f32]].iter()
-        .map(|v| v.iter().cloned().collect::<Vec<_>>())
+        .map(|v| v.to_vec())
         .collect::<Vec<_>>()
 }
 
-fn interleave(a: u16, b: u16) -> u32 {
-    fn interleave_zeros(input: u16) -> u32 {
-        let mut word = input as u32;
-        word = (word ^ (word << 16)) & 0x0000ffff;
-        word = (word ^ (word << 8 )) & 0x00ff00ff;
-        word = (word ^ (word << 4 )) & 0x0f0f0f0f;
-        word = (word ^ (word << 2 )) & 0x33333333;
-        word = (word ^ (word << 1 )) & 0x55555555;
-        word
+impl super::Filter for DitherFilter {
+    fn filter(&self, image: &mut image::RgbImage, _context: &Context) -> Result<()> {
+        let spread = match (&self.palette, self.method) {
+            (DitherPalette::Colors(colors), _) if colors.is_empty() => {
+                return Err(Error::parameter(
+                    "dither",
+                    "palette",
+                    "must have at least one color",
+                ));
+            }
+            (DitherPalette::Colors(colors), _) => spread(colors.len()),
+            // These have their own way of ordered dithering, from
+            // before there were palettes.
+            (_, DitherMethod::Ordered) => {
+                crate::rows::for_each_row(image, |mut row| {
+                    let y = row.y();
+                    for (x, pixel) in row.pixels_mut().enumerate() {
+                        *pixel = self.ordered(x as u32, y, pixel);
+                    }
+                });
+                return Ok(());
+            }
+            (_, _) => 0.0,
+        };
+
+        dither(
+            image,
+            self.method,
+            self.serpentine,
+            &self.matrix,
+            spread,
+            |color| self.nearest(color),
+        );
+        Ok(())
     }
-
-    interleave_zeros(a) | (interleave_zeros(b) << 1)
 }
 
-impl super::Filter for DitherFilter {
-    fn filter(&self, image: &mut image::RgbImage) {
-        // let mut temp = image::RgbImage::from_raw(0, 0, vec![]).unwrap();
-
-        let (width, height) = image.dimensions();
-
-        for y in 0..height {
-            for x in 0..width {
-                let pixel = image.get_pixel(x, y);
-                let pixel = mutate(x, y, pixel, &self.matrix, &self.white, &self.black);
-                image.put_pixel(x, y, pixel);
+impl DitherFilter {
+    fn ordered(&self, x: u32, y: u32, pixel: &image::Rgb<u8>) -> image::Rgb<u8> {
+        let value = threshold(&self.matrix, x, y);
+        if let DitherPalette::TwoTone = self.palette {
+            let low = color::brightness(&self.black);
+            let high = color::brightness(&self.white);
+            let brightness = (color::brightness(pixel) - low) / (high - low);
+            if brightness > value - 0.25 {
+                self.white
+            } else {
+                self.black
             }
+        } else {
+            new_colors(pixel, color::brightness(pixel) > value)
         }
     }
 }
 
-fn mutate(x: u32, y: u32, pixel: &image::Rgb<u8>, matrix: &Vec<Vec<f32>>, white: &image::Rgb<u8>, black: &image::Rgb<u8>) -> image::Rgb<u8> {
+// The matrix runs from 0.25 up to 1.25.
+fn threshold(matrix: &[Vec<f32>], x: u32, y: u32) -> f32 {
     let row = &matrix[x as usize % matrix.len()];
-    let value = row[y as usize % matrix.len()];
-    let brightness = pixel_brightness(pixel);
+    row[y as usize % matrix.len()]
+}
 
-    // if brightness > value {
-    //     *white
-    // } else {
-    //     *black
-    // }
+/// How far ordered dithering nudges each pixel, at most, to dither
+/// between the colors of a palette of the given size.  This is the
+/// distance between neighboring colors, as if the palette were
+/// spread evenly over every channel.
+pub(super) fn spread(colors: usize) -> f32 {
+    let per_channel = (colors as f32).cbrt();
+    255.0 / (per_channel - 1.0).max(1.0)
+}
 
-    new_colors(pixel, brightness > value)
+/// Dithers the image down to the colors picked by `nearest`.  It's
+/// given colors with channels from `0.0` to `255.0`, which can be out
+/// of that range once error has been added to them.  For ordered
+/// dithering, every pixel is nudged by up to half of `spread` in
+/// either direction, by the Bayer matrix, before its color is picked.
+pub(super) fn dither<N>(
+    image: &mut image::RgbImage,
+    method: DitherMethod,
+    serpentine: bool,
+    matrix: &[Vec<f32>],
+    spread: f32,
+    nearest: N,
+) where
+    N: Fn([f32; 3]) -> image::Rgb<u8> + Send + Sync,
+{
+    let kernel = match method {
+        DitherMethod::Ordered => {
+            crate::rows::for_each_row(image, |mut row| {
+                let y = row.y();
+                for (x, pixel) in row.pixels_mut().enumerate() {
+                    let offset = (threshold(matrix, x as u32, y) - 0.75) * spread;
+                    *pixel = nearest([
+                        pixel.0[0] as f32 + offset,
+                        pixel.0[1] as f32 + offset,
+                        pixel.0[2] as f32 + offset,
+                    ]);
+                }
+            });
+            return;
+        }
+        DitherMethod::FloydSteinberg => &FLOYD_STEINBERG,
+        DitherMethod::Atkinson => &ATKINSON,
+        DitherMethod::JarvisJudiceNinke => &JARVIS_JUDICE_NINKE,
+        DitherMethod::Stucki => &STUCKI,
+        DitherMethod::Sierra => &SIERRA,
+    };
+    diffuse(image, kernel, serpentine, nearest);
+}
 
+/// Error diffusion, which goes through the pixels in order,
+/// spreading the difference between each one and its new color over
+/// the pixels that are still to come.  This can't be done a row at a
+/// time, since every row depends on the one before it.
+fn diffuse<N: Fn([f32; 3]) -> image::Rgb<u8>>(
+    image: &mut image::RgbImage,
+    kernel: &Kernel,
+    serpentine: bool,
+    nearest: N,
+) {
+    let (width, height) = image.dimensions();
+    let rows = kernel.spread.iter().map(|s| s.1).max().unwrap_or(0) + 1;
+    // The error still to be added to each pixel, for this row and the
+    // ones after it, reused as the rows go by.
+    let mut errors = vec![vec![[0.0f32; 3]; width as usize]; rows];
+
+    for y in 0..height {
+        let backwards = serpentine && y % 2 == 1;
+        for i in 0..width {
+            let x = if backwards { width - 1 - i } else { i };
+            let pixel = image.get_pixel_mut(x, y);
+            let error = &mut errors[0][x as usize];
+            let wanted = [
+                pixel.0[0] as f32 + error[0],
+                pixel.0[1] as f32 + error[1],
+                pixel.0[2] as f32 + error[2],
+            ];
+            *error = [0.0; 3];
+            *pixel = nearest(wanted);
+            let error = [
+                wanted[0] - pixel.0[0] as f32,
+                wanted[1] - pixel.0[1] as f32,
+                wanted[2] - pixel.0[2] as f32,
+            ];
+
+            for &(dx, dy, weight) in kernel.spread.iter() {
+                let dx = if backwards { -dx } else { dx };
+                let nx = x as i64 + dx as i64;
+                if nx < 0 || nx >= width as i64 {
+                    continue;
+                }
+                let target = &mut errors[dy][nx as usize];
+                for c in 0..3 {
+                    target[c] += error[c] * weight / kernel.divisor;
+                }
+            }
+        }
+        errors.rotate_left(1);
+    }
+}
 
-    // if (brightness + (rand::random::<f32>() - 0.5) / 2.0) > 0.5 {
-    //     *white
-    // } else {
-    //     *black
-    // }
+/// Where the error from a pixel goes: `(dx, dy, weight)`, with each
+/// weight divided by `divisor`.
+struct Kernel {
+    spread: &'static [(i32, usize, f32)],
+    divisor: f32,
 }
 
+static FLOYD_STEINBERG: Kernel = Kernel {
+    spread: &[(1, 0, 7.0), (-1, 1, 3.0), (0, 1, 5.0), (1, 1, 1.0)],
+    divisor: 16.0,
+};
+
+static ATKINSON: Kernel = Kernel {
+    spread: &[
+        (1, 0, 1.0),
+        (2, 0, 1.0),
+        (-1, 1, 1.0),
+        (0, 1, 1.0),
+        (1, 1, 1.0),
+        (0, 2, 1.0),
+    ],
+    divisor: 8.0,
+};
+
+static JARVIS_JUDICE_NINKE: Kernel = Kernel {
+    spread: &[
+        (1, 0, 7.0),
+        (2, 0, 5.0),
+        (-2, 1, 3.0),
+        (-1, 1, 5.0),
+        (0, 1, 7.0),
+        (1, 1, 5.0),
+        (2, 1, 3.0),
+        (-2, 2, 1.0),
+        (-1, 2, 3.0),
+        (0, 2, 5.0),
+        (1, 2, 3.0),
+        (2, 2, 1.0),
+    ],
+    divisor: 48.0,
+};
+
+static STUCKI: Kernel = Kernel {
+    spread: &[
+        (1, 0, 8.0),
+        (2, 0, 4.0),
+        (-2, 1, 2.0),
+        (-1, 1, 4.0),
+        (0, 1, 8.0),
+        (1, 1, 4.0),
+        (2, 1, 2.0),
+        (-2, 2, 1.0),
+        (-1, 2, 2.0),
+        (0, 2, 4.0),
+        (1, 2, 2.0),
+        (2, 2, 1.0),
+    ],
+    divisor: 42.0,
+};
+
+static SIERRA: Kernel = Kernel {
+    spread: &[
+        (1, 0, 5.0),
+        (2, 0, 3.0),
+        (-2, 1, 2.0),
+        (-1, 1, 4.0),
+        (0, 1, 5.0),
+        (1, 1, 4.0),
+        (2, 1, 2.0),
+        (-1, 2, 2.0),
+        (0, 2, 3.0),
+        (1, 2, 2.0),
+    ],
+    divisor: 32.0,
+};
+
+const BANDS: f32 = 15.0;
+
 fn new_colors(pixel: &image::Rgb<u8>, upper: bool) -> image::Rgb<u8> {
-    const BANDS: f32 = 15.0;
     fn band(v: u8) -> f32 {
         ((v as f32 / 255.0) * BANDS).floor()
     }
@@ -191,9 +458,25 @@ fn new_colors(pixel: &image::Rgb<u8>, upper: bool) -> image::Rgb<u8> {
     }
 }
 
-fn pixel_brightness(pixel: &image::Rgb<u8>) -> f32 {
-    let r = pixel.0[0] as f32 / 255.0;
-    let g = pixel.0[1] as f32 / 255.0;
-    let b = pixel.0[2] as f32 / 255.0;
-    (r * 0.30 + g * 0.59 + b * 0.11).clamp(0.0, 1.0)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::Filter;
+
+    #[test]
+    fn test_diffusion_keeps_the_average() {
+        let white = crate::hex(0xffffff);
+        let black = crate::hex(0x000000);
+        let mut filter = DitherFilter::new(0, white, black).unwrap();
+        filter.method = DitherMethod::FloydSteinberg;
+        filter.serpentine = true;
+        filter.palette = DitherPalette::TwoTone;
+
+        let mut image = image::RgbImage::from_pixel(32, 32, image::Rgb([64, 64, 64]));
+        filter.filter(&mut image, &Context::new(0)).unwrap();
+        assert!(image.pixels().all(|p| *p == white || *p == black));
+        let lit = image.pixels().filter(|p| **p == white).count();
+        // A quarter of the way to white is a quarter of the pixels.
+        assert!((lit as i64 - 256).abs() < 16, "{} pixels lit", lit);
+    }
 }
@@ -5,34 +5,42 @@ mod crop;
 mod dither;
 mod march;
 mod noise;
+mod palette;
 mod scan;
 mod vignette;
 
-pub use self::abberate::{AbberateFilter, NullFilter};
+pub use self::abberate::{AbberateFilter, ColorSpace, NullFilter};
 pub use self::barrel::BarrelFilter;
 pub use self::blur::BlurFilter;
 pub use self::crop::CropFilter;
-pub use self::dither::DitherFilter;
+pub use self::dither::{DitherFilter, DitherMethod, DitherPalette};
 pub use self::march::MarchFilter;
 pub use self::noise::NoiseFilter;
+pub use self::palette::PaletteFilter;
 pub use self::scan::ScanFilter;
 pub use self::vignette::VignetteFilter;
 
-pub trait Filter {
-    fn filter(&self, image: &mut image::RgbImage);
+use crate::context::Context;
+use crate::error::Result;
+use crate::registry::{Descriptor, Kind, Parameter};
+use crate::rows::Row;
+
+pub trait Filter: Send + Sync {
+    /// Applies the filter to the image.  Any randomness should come
+    /// from the given context, so that the image can be reproduced.
+    fn filter(&self, image: &mut image::RgbImage, context: &Context) -> Result<()>;
 }
 
 // direction = false -> to the left, direction = true -> to the right
 fn shift_row<F: FnMut(image::Rgb<u8>, &mut image::Rgb<u8>), D: FnMut(&mut image::Rgb<u8>)>(
-    image: &mut image::RgbImage,
-    y: u32,
+    row: &mut Row<image::Rgb<u8>>,
     by: u32,
     direction: bool,
     mut action: F,
     mut default: D,
 ) {
-    let width = image.dimensions().0;
-    for x in 0..(image.dimensions().0) {
+    let width = row.width();
+    for x in 0..width {
         let (nx, offset) = if direction {
             let nx = width - 1 - x;
             if nx <= by {
@@ -49,8 +57,259 @@ fn shift_row<F: FnMut(image::Rgb<u8>, &mut image::Rgb<u8>), D: FnMut(&mut image:
         };
 
         match offset {
-            Some(off) => action(*image.get_pixel(off, y), image.get_pixel_mut(nx, y)),
-            None => default(image.get_pixel_mut(nx, y)),
+            Some(off) => action(row.get(off), row.get_mut(nx)),
+            None => default(row.get_mut(nx)),
         }
     }
 }
+
+const COLOR: &str = "color";
+/// Pixels, or a percentage of the shorter side of the image, like
+/// `"2%"`.
+const LENGTH: &str = "length";
+const COLOR_SPACE: &str = "\"rgb\" | \"yiq\"";
+
+/// Every filter that can be named in a pipeline.
+pub static FILTERS: &[Descriptor] = &[
+    Descriptor {
+        name: "abberate",
+        kind: Kind::Filter,
+        summary: "Chromatic abberation; shifts each color channel sideways.",
+        parameters: &[
+            Parameter::required(
+                "channel_shifts",
+                "[i32, i32, i32]",
+                "How far to shift each channel, in pixels; positive values shift right.",
+            ),
+            Parameter::optional(
+                "color_space",
+                COLOR_SPACE,
+                "\"yiq\"",
+                "The color space the channels are taken from.",
+            ),
+        ],
+    },
+    Descriptor {
+        name: "null",
+        kind: Kind::Filter,
+        summary: "Zeroes out a color channel over random runs of pixels.",
+        parameters: &[
+            Parameter::required(
+                "selection",
+                "f64",
+                "The chance that any pixel starts a run, from 0.0 to 1.0.",
+            ),
+            Parameter::required(
+                "average_null",
+                "f32",
+                "The average length of a run, in pixels.",
+            ),
+            Parameter::optional(
+                "component",
+                "0 | 1 | 2",
+                "random",
+                "The channel to zero out.",
+            ),
+            Parameter::optional(
+                "color_space",
+                COLOR_SPACE,
+                "\"rgb\"",
+                "The color space the channel is taken from.",
+            ),
+        ],
+    },
+    Descriptor {
+        name: "barrel",
+        kind: Kind::Filter,
+        summary: "Bends the image vertically, like an old CRT.",
+        parameters: &[
+            Parameter::required("scale", LENGTH, "How far the corners are pulled in."),
+            Parameter::required(
+                "background_color",
+                COLOR,
+                "The color of the space left behind.",
+            ),
+        ],
+    },
+    Descriptor {
+        name: "blur",
+        kind: Kind::Filter,
+        summary: "A gaussian blur.",
+        parameters: &[Parameter::required(
+            "sigma",
+            LENGTH,
+            "The standard deviation of the blur.",
+        )],
+    },
+    Descriptor {
+        name: "crop",
+        kind: Kind::Filter,
+        summary: "Removes pixels from the edges of the image.",
+        parameters: &[
+            Parameter::optional("top", LENGTH, "0", "How much to remove from the top."),
+            Parameter::optional("right", LENGTH, "0", "How much to remove from the right."),
+            Parameter::optional("bottom", LENGTH, "0", "How much to remove from the bottom."),
+            Parameter::optional("left", LENGTH, "0", "How much to remove from the left."),
+        ],
+    },
+    Descriptor {
+        name: "dither",
+        kind: Kind::Filter,
+        summary: "Ordered (Bayer) or error diffusion dithering.",
+        parameters: &[
+            Parameter::optional(
+                "method",
+                "ordered | floyd_steinberg | atkinson | jarvis_judice_ninke | stucki | sierra",
+                "\"ordered\"",
+                "How each pixel's color is picked.",
+            ),
+            Parameter::optional(
+                "levels",
+                "u16",
+                "2",
+                "The size of the Bayer matrix, as a power of two.",
+            ),
+            Parameter::optional(
+                "palette",
+                "bands | two_tone | [color]",
+                "\"bands\"",
+                "The colors to dither to; bands each channel into 15 steps.",
+            ),
+            Parameter::optional(
+                "white",
+                COLOR,
+                "\"#ffffff\"",
+                "The light color of two_tone.",
+            ),
+            Parameter::optional("black", COLOR, "\"#000000\"", "The dark color of two_tone."),
+            Parameter::optional(
+                "serpentine",
+                "bool",
+                "false",
+                "Whether error diffusion goes back and forth across the rows.",
+            ),
+        ],
+    },
+    Descriptor {
+        name: "march",
+        kind: Kind::Filter,
+        summary: "Shifts random rows sideways, like a bad signal.",
+        parameters: &[
+            Parameter::required(
+                "selection",
+                "f64",
+                "The chance that any row starts a new shift, from 0.0 to 1.0.",
+            ),
+            Parameter::required("max_shift", LENGTH, "The largest possible shift."),
+            Parameter::required(
+                "background_color",
+                COLOR,
+                "The color of the space left behind.",
+            ),
+            Parameter::optional(
+                "deviation",
+                "f64",
+                "0.25",
+                "The spread of new shifts, as a fraction of max_shift.",
+            ),
+            Parameter::optional(
+                "keep",
+                "f64",
+                "0.92",
+                "How much of a shift carries over to the next row.",
+            ),
+        ],
+    },
+    Descriptor {
+        name: "noise",
+        kind: Kind::Filter,
+        summary: "Replaces random pixels with gray specks.",
+        parameters: &[
+            Parameter::required(
+                "rate",
+                "f64",
+                "The chance that any pixel is replaced, from 0.0 to 1.0.",
+            ),
+            Parameter::required("low", "u8", "The darkest speck."),
+            Parameter::required("high", "u8", "The brightest speck."),
+        ],
+    },
+    Descriptor {
+        name: "palette",
+        kind: Kind::Filter,
+        summary: "Moves every pixel to the closest color in a palette.",
+        parameters: &[
+            Parameter::optional("colors", "[color]", "[]", "The colors of the palette."),
+            Parameter::optional(
+                "file",
+                "path",
+                "none",
+                "A GIMP .gpl palette, or a list of hex colors, to add to colors.",
+            ),
+            Parameter::optional(
+                "extract",
+                "{ image, count, method }",
+                "none",
+                "Adds count colors from an image, by median_cut or kmeans.",
+            ),
+            Parameter::optional(
+                "space",
+                "rgb | cielab | oklab",
+                "\"oklab\"",
+                "The color space to find the closest color in.",
+            ),
+            Parameter::optional(
+                "dither",
+                "ordered | floyd_steinberg | atkinson | ...",
+                "none",
+                "How to dither between the colors; see the dither filter.",
+            ),
+            Parameter::optional(
+                "serpentine",
+                "bool",
+                "false",
+                "Whether error diffusion goes back and forth across the rows.",
+            ),
+        ],
+    },
+    Descriptor {
+        name: "scan",
+        kind: Kind::Filter,
+        summary: "Alternating light and dark scanlines.",
+        parameters: &[
+            Parameter::required(
+                "lines",
+                "u32",
+                "The number of scanlines over the height of the image.",
+            ),
+            Parameter::required(
+                "vary",
+                "i16",
+                "How much each scanline brightens or darkens.",
+            ),
+            Parameter::optional(
+                "roll",
+                "u32",
+                "1",
+                "How many pairs of scanlines roll down the image over an animation.",
+            ),
+        ],
+    },
+    Descriptor {
+        name: "vignette",
+        kind: Kind::Filter,
+        summary: "Darkens the image towards its corners.",
+        parameters: &[
+            Parameter::required(
+                "distance_offset",
+                "f64",
+                "Added to the distance from the center before scaling.",
+            ),
+            Parameter::required(
+                "scale",
+                "f64",
+                "How strongly the distance darkens the image.",
+            ),
+        ],
+    },
+];
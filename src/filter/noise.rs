@@ -1,32 +1,61 @@
+use crate::context::Context;
+use crate::error::{Error, Result};
 use rand::distributions::{Bernoulli, Distribution, Uniform};
-use rand::rngs::SmallRng;
-use rand::{Rng, SeedableRng};
+use rand::Rng;
 
 pub struct NoiseFilter<D: Distribution<u8>> {
-    pub rate: f64,
+    /// Whether each pixel is replaced with noise.
+    pub rate: Bernoulli,
     pub luminescence_distribution: D,
 }
 
 impl NoiseFilter<Uniform<u8>> {
-    pub fn new(rate: f64, low: u8, high: u8) -> Self {
-        NoiseFilter {
+    pub fn new(rate: f64, low: u8, high: u8) -> Result<Self> {
+        if low > high {
+            return Err(Error::parameter(
+                "noise",
+                "low",
+                format!("must not be above high ({} > {})", low, high),
+            ));
+        }
+        let rate = Bernoulli::new(rate).map_err(|_| {
+            Error::parameter(
+                "noise",
+                "rate",
+                format!("must be between 0.0 and 1.0, not {}", rate),
+            )
+        })?;
+
+        Ok(NoiseFilter {
             rate,
             luminescence_distribution: Uniform::new_inclusive(low, high),
-        }
+        })
     }
 }
 
-impl<D: Distribution<u8>> super::Filter for NoiseFilter<D> {
-    fn filter(&self, image: &mut image::RgbImage) {
-        let dist =
-            Bernoulli::new(self.rate).expect("correct distribution should be between 0.0 and 1.0");
-        let mut rng = SmallRng::from_rng(&mut rand::thread_rng()).unwrap();
-
-        for pixel in image.pixels_mut() {
-            if rng.sample(&dist) {
-                let value = rng.sample(&self.luminescence_distribution);
-                *pixel = image::Rgb([value, value, value]);
+impl<D: Distribution<u8> + Send + Sync> super::Filter for NoiseFilter<D> {
+    fn filter(&self, image: &mut image::RgbImage, context: &Context) -> Result<()> {
+        crate::rows::for_each_row(image, |mut row| {
+            let mut rng = context.row_rng(row.y());
+            for pixel in row.pixels_mut() {
+                if rng.sample(self.rate) {
+                    let value = rng.sample(&self.luminescence_distribution);
+                    *pixel = image::Rgb([value, value, value]);
+                }
             }
-        }
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate() {
+        assert!(NoiseFilter::new(0.5, 0, 255).is_ok());
+        assert!(NoiseFilter::new(1.5, 0, 255).is_err());
+        assert!(NoiseFilter::new(0.5, 200, 100).is_err());
     }
 }
@@ -0,0 +1,99 @@
+use super::dither::{self, DitherMethod, Matrix};
+use crate::color::{self, Space};
+use crate::context::Context;
+use crate::error::{Error, Result};
+
+/// Moves every pixel to the closest color in a palette, optionally
+/// dithering between them.
+pub struct PaletteFilter {
+    colors: Vec<image::Rgb<u8>>,
+    // The colors, in `space`.
+    points: Vec<[f32; 3]>,
+    space: Space,
+    pub dither: Option<DitherMethod>,
+    /// See [`super::DitherFilter::serpentine`].
+    pub serpentine: bool,
+    matrix: Matrix,
+}
+
+impl PaletteFilter {
+    pub fn new(colors: Vec<image::Rgb<u8>>, space: Space) -> Result<Self> {
+        if colors.is_empty() {
+            return Err(Error::parameter(
+                "palette",
+                "colors",
+                "must have at least one color, from colors, file or extract",
+            ));
+        }
+
+        Ok(PaletteFilter {
+            points: colors
+                .iter()
+                .map(|c| space.coordinates(color::to_array(*c)))
+                .collect(),
+            colors,
+            space,
+            dither: None,
+            serpentine: false,
+            matrix: dither::generate_matrix(2),
+        })
+    }
+
+    fn nearest(&self, rgb: [f32; 3]) -> image::Rgb<u8> {
+        let point = self.space.coordinates(rgb);
+        let (i, _) = self
+            .points
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| {
+                color::distance(point, **a).total_cmp(&color::distance(point, **b))
+            })
+            .expect("palettes are never empty");
+        self.colors[i]
+    }
+}
+
+impl super::Filter for PaletteFilter {
+    fn filter(&self, image: &mut image::RgbImage, _context: &Context) -> Result<()> {
+        match self.dither {
+            Some(method) => dither::dither(
+                image,
+                method,
+                self.serpentine,
+                &self.matrix,
+                dither::spread(self.colors.len()),
+                |rgb| self.nearest(rgb),
+            ),
+            None => crate::rows::for_each_row(image, |mut row| {
+                for pixel in row.pixels_mut() {
+                    *pixel = self.nearest(color::to_array(*pixel));
+                }
+            }),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::Filter;
+
+    #[test]
+    fn test_perceptual_matching() {
+        // In sRGB, a dark blue is closer to black than to a bright
+        // blue, but it looks closer to the blue.
+        let colors = vec![crate::hex(0x000000), crate::hex(0x3050ff)];
+        let image = image::RgbImage::from_pixel(1, 1, crate::hex(0x141e6e));
+
+        let mut rgb = image.clone();
+        let filter = PaletteFilter::new(colors.clone(), Space::Rgb).unwrap();
+        filter.filter(&mut rgb, &Context::new(0)).unwrap();
+        assert_eq!(rgb.get_pixel(0, 0), &crate::hex(0x000000));
+
+        let mut oklab = image;
+        let filter = PaletteFilter::new(colors, Space::Oklab).unwrap();
+        filter.filter(&mut oklab, &Context::new(0)).unwrap();
+        assert_eq!(oklab.get_pixel(0, 0), &crate::hex(0x3050ff));
+    }
+}
@@ -1,30 +1,44 @@
+use crate::context::Context;
+use crate::error::Result;
+
 pub struct ScanFilter {
     pub lines: u32,
     pub vary: i16,
+    /// How many pairs of lines roll down the image over the course of
+    /// an animation.  A whole number of pairs puts the lines back
+    /// where they started at the end.
+    pub roll: u32,
 }
 
 impl ScanFilter {
     pub fn new(lines: u32, vary: i16) -> Self {
-        ScanFilter { lines, vary }
+        ScanFilter {
+            lines,
+            vary,
+            roll: 1,
+        }
     }
 }
 
 impl super::Filter for ScanFilter {
-    fn filter(&self, image: &mut image::RgbImage) {
-        let (width, height) = image.dimensions();
+    fn filter(&self, image: &mut image::RgbImage, context: &Context) -> Result<()> {
+        let height = image.dimensions().1;
         let pixels_per_line = (height as f64) / (self.lines as f64);
+        let offset = context.time() * self.roll as f64 * pixels_per_line * 2.0;
 
-        for y in 0..height {
-            let is_even = ((y as f64 / pixels_per_line as f64).floor() as u32) % 2 == 0;
-            let progress = (y as f64 % pixels_per_line) / pixels_per_line;
+        crate::rows::for_each_row(image, |mut row| {
+            let y = row.y() as f64 - offset;
+            let is_even = ((y / pixels_per_line).floor() as i64).rem_euclid(2) == 0;
+            let progress = y.rem_euclid(pixels_per_line) / pixels_per_line;
             let progress = (-(progress - 0.5).abs()) + 0.5;
-            let progress = if is_even { progress } else { progress * -1.0 };
+            let progress = if is_even { progress } else { -progress };
             let by = (progress * (self.vary as f64)) as i16;
 
-            for x in 0..width {
-                image.put_pixel(x, y, brighten(*image.get_pixel(x, y), by));
+            for pixel in row.pixels_mut() {
+                *pixel = brighten(*pixel, by);
             }
-        }
+        });
+        Ok(())
     }
 }
 
@@ -1,3 +1,5 @@
+use crate::context::Context;
+use crate::error::Result;
 use crate::position::Position;
 
 pub struct VignetteFilter {
@@ -15,20 +17,19 @@ impl VignetteFilter {
 }
 
 impl super::Filter for VignetteFilter {
-    fn filter(&self, image: &mut image::RgbImage) {
+    fn filter(&self, image: &mut image::RgbImage, _context: &Context) -> Result<()> {
         let center = Position::from(image.dimensions()) / 2;
 
-        for y in 0..image.dimensions().1 {
-            for x in 0..image.dimensions().0 {
-                let distance = distance_from_edge(center, Position::new(x, y));
+        crate::rows::for_each_row(image, |mut row| {
+            let y = row.y();
+            for (x, pixel) in row.pixels_mut().enumerate() {
+                let distance = distance_from_edge(center, Position::new(x as u32, y));
                 let value = (((distance + self.distance_offset) * self.scale) * 255.0)
                     .clamp(0.0, 255.0) as u8;
                 let mutate = |v: u8| v.saturating_sub(value);
-                let pixel = *image.get_pixel(x, y);
-                let new = image::Rgb([mutate(pixel.0[0]), mutate(pixel.0[1]), mutate(pixel.0[2])]);
-                image.put_pixel(x, y, new);
+                *pixel = image::Rgb([mutate(pixel.0[0]), mutate(pixel.0[1]), mutate(pixel.0[2])]);
             }
-        }
+        });
 
         // for (x, y, pixel) in image.enumerate_pixels_mut() {
         //     let distance = distance_from_edge(center, Position::new(x, y));
@@ -39,13 +40,12 @@ impl super::Filter for VignetteFilter {
         //         .iter_mut()
         //         .for_each(|v| *v = (*v + value).clamp(0, 255));
         // }
+        Ok(())
     }
 }
 
 fn distance_from_edge(center: Position, position: Position) -> f64 {
     let x = (center.x() as f64 - position.x() as f64) / center.x() as f64;
     let y = (center.y() as f64 - position.y() as f64) / center.y() as f64;
-    let d = (x.powi(2) + y.powi(2)).sqrt();
-
-    d
+    (x.powi(2) + y.powi(2)).sqrt()
 }
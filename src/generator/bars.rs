@@ -1,58 +1,423 @@
-use crate::position::{Position, Region};
-use rand::seq::SliceRandom;
-use std::collections::HashMap;
+use super::coverage;
+use crate::context::Context;
+use crate::error::Result;
+use crate::gradient::Gradient;
+use crate::position::Position;
+use crate::svg::{Document, Point};
+
+/// A single bar, which runs straight across the direction that the
+/// bars are laid out in.
+#[derive(Debug, Clone)]
+pub struct Bar {
+    /// How far along from the start of the first bar this one starts.
+    pub offset: u32,
+    /// How much room the bar takes up, in pixels.  It's drawn from a
+    /// pixel past its offset, as bars always have been, and so is a
+    /// pixel narrower than this.
+    pub size: u32,
+    pub color: image::Rgb<u8>,
+    /// Colors the bar across its width, from `0.0` where it starts to
+    /// `1.0` where it ends, instead of `color`.
+    pub gradient: Option<Gradient>,
+}
+
+impl Bar {
+    /// The color `t` pixels into the bar.
+    fn color_at(&self, t: f64) -> image::Rgb<u8> {
+        match self.gradient.as_ref() {
+            Some(gradient) => gradient.at((t / self.size as f64) as f32),
+            None => self.color,
+        }
+    }
+}
 
 pub struct BarGenerator {
     /// The size of the image, in pixels.
     pub image_size: Position,
     /// The bars present in the image.
-    pub bars: Vec<(u32, u32, image::Rgb<u8>)>,
+    pub bars: Vec<Bar>,
 
     /// The initial offset of the bars into the image.
     pub initial_offset: u32,
-    /// The angel the bars are placed at.
-    pub angle: f64,
-
-    /// Whether or not the bars are vertical.
-    pub vertical: bool,
+    /// The direction that the bars are laid out in, one after the
+    /// other, which doesn't need to be a unit vector.  They start from
+    /// the corner of the image that's the furthest back along it.
+    pub direction: [f64; 2],
+    /// Whether the bars repeat over the whole image, starting over
+    /// from the first bar where the last one ends.
+    pub repeat: bool,
+    /// Whether the edges of the bars are smoothed, by how much of
+    /// each pixel they cover.
+    pub antialias: bool,
 
     pub background_color: image::Rgb<u8>,
 }
 
+/// A stretch of a bar, as distances along the direction the bars are
+/// laid out in, from where they start.
+#[derive(Clone, Copy)]
+struct Span<'a> {
+    start: f64,
+    end: f64,
+    /// Where the bar that the span is part of starts.
+    origin: f64,
+    bar: &'a Bar,
+}
+
+impl<'a> Span<'a> {
+    fn color_at(&self, t: f64) -> image::Rgb<u8> {
+        self.bar.color_at(t - self.origin)
+    }
+
+    fn shifted(self, by: f64) -> Span<'a> {
+        Span {
+            start: self.start + by,
+            end: self.end + by,
+            origin: self.origin + by,
+            bar: self.bar,
+        }
+    }
+}
+
 impl BarGenerator {
-    pub fn push_bar(&mut self, plus: u32, size: u32, color: image::Rgb<u8>) {
-        let last = self.bars.last().map(|(o, s, _)| *o + *s).unwrap_or(0);
-        self.bars.push((last + plus, size, color));
+    pub fn push_bar(
+        &mut self,
+        plus: u32,
+        size: u32,
+        color: image::Rgb<u8>,
+        gradient: Option<Gradient>,
+    ) {
+        let last = self.bars.last().map(|b| b.offset + b.size).unwrap_or(0);
+        self.bars.push(Bar {
+            offset: last + plus,
+            size,
+            color,
+            gradient,
+        });
     }
 
-    fn find_bar(&self, offset: u32) -> Option<image::Rgb<u8>> {
-        self.bars.iter().rfind(|(o, s, _)| {
-            offset > *o && offset < (*o + *s)
-        }).map(|(_, _, v)| *v)
+    /// The direction as a unit vector.  Without any direction at all,
+    /// the bars are laid out down the image.
+    fn normal(&self) -> [f64; 2] {
+        let [x, y] = self.direction;
+        let length = x.hypot(y);
+        match length > 0.0 {
+            true => [x / length, y / length],
+            false => [0.0, 1.0],
+        }
+    }
+
+    /// How far along the normal the first bar starts: the initial
+    /// offset past the corner of the image that's the least far along
+    /// it.
+    fn start(&self, normal: [f64; 2]) -> f64 {
+        let (width, height) = (self.image_size.x() as f64, self.image_size.y() as f64);
+        (width * normal[0]).min(0.0) + (height * normal[1]).min(0.0) + self.initial_offset as f64
+    }
+
+    /// How long the bars are before they start over, if they repeat,
+    /// which is where the last of them ends.
+    fn period(&self) -> Option<f64> {
+        let end = self
+            .bars
+            .iter()
+            .map(|b| b.offset + b.size)
+            .max()
+            .unwrap_or(0);
+        match self.repeat && end > 0 {
+            true => Some(end as f64),
+            false => None,
+        }
+    }
+
+    /// The parts of the bars that aren't covered up by later ones.
+    fn visible(&self) -> Vec<Span<'_>> {
+        let mut covered: Vec<(f64, f64)> = Vec::new();
+        let mut visible = Vec::new();
+        for bar in self.bars.iter().rev() {
+            let (start, end) = ((bar.offset + 1) as f64, (bar.offset + bar.size) as f64);
+            let mut pieces = vec![(start, end)];
+            for &(from, to) in covered.iter() {
+                pieces = pieces
+                    .into_iter()
+                    .flat_map(|(start, end)| vec![(start, end.min(from)), (start.max(to), end)])
+                    .filter(|(start, end)| start < end)
+                    .collect();
+            }
+            visible.extend(pieces.into_iter().map(|(start, end)| Span {
+                start,
+                end,
+                origin: bar.offset as f64,
+                bar,
+            }));
+            covered.push((start, end));
+        }
+        visible
+    }
+
+    /// The visible spans, repeated if the bars repeat, that reach
+    /// in between `from` and `to`.
+    fn spans<'a>(
+        &self,
+        visible: &'a [Span<'a>],
+        from: f64,
+        to: f64,
+    ) -> impl Iterator<Item = Span<'a>> + 'a {
+        let (period, copies) = match self.period() {
+            Some(period) => (
+                period,
+                (from / period).floor() as i64..=(to / period).floor() as i64,
+            ),
+            None => (0.0, 0..=0),
+        };
+        copies
+            .flat_map(move |copy| {
+                visible
+                    .iter()
+                    .map(move |span| span.shifted(copy as f64 * period))
+            })
+            .filter(move |span| span.start < to && span.end > from)
+    }
+
+    /// The color of the bar `t` along from where the bars start, if
+    /// there's one there.
+    fn color_at(&self, visible: &[Span<'_>], t: f64) -> Option<image::Rgb<u8>> {
+        let t = match self.period() {
+            Some(period) => t.rem_euclid(period),
+            None => t,
+        };
+        visible
+            .iter()
+            .find(|span| span.start <= t && t < span.end)
+            .map(|span| span.color_at(t))
+    }
+
+    fn generate_smooth(&self) -> image::RgbImage {
+        let normal = self.normal();
+        let start = self.start(normal);
+        // How far the normal reaches across a pixel, from its corner
+        // that's the least far along it.
+        let (back, across) = (
+            normal[0].min(0.0) + normal[1].min(0.0),
+            normal[0].abs() + normal[1].abs(),
+        );
+        let visible = self.visible();
+        crate::rows::from_fn(self.image_size.x(), self.image_size.y(), |x, y| {
+            let low = x as f64 * normal[0] + y as f64 * normal[1] + back - start;
+            let high = low + across;
+            let middle = (low + high) / 2.0;
+            coverage::mix(
+                self.background_color,
+                self.spans(&visible, low, high).map(|span| {
+                    let part = coverage::band(
+                        [normal[0] as f32, normal[1] as f32],
+                        [x as f32, y as f32],
+                        (start + span.start) as f32,
+                        (start + span.end) as f32,
+                    );
+                    (part, span.color_at(middle.clamp(span.start, span.end)))
+                }),
+            )
+        })
     }
 }
 
 impl super::Generator<image::Rgb<u8>> for BarGenerator {
-    fn generate(&self) -> image::RgbImage {
-        fn angle_adjustment(other: u32, max: u32, angle: f64) -> u32 {
-            ((angle.sin() * (other as f64))) as u32
+    fn generate(&self, _context: &Context) -> Result<image::RgbImage> {
+        if self.antialias {
+            return Ok(self.generate_smooth());
         }
-        let parallel: fn(u32, u32) -> u32 = if self.vertical { |x, _y| x } else { |_x, y| y };
-        let perpendicular: fn(u32, u32) -> u32 = if self.vertical { |_x, y| y } else { |x, _y| x };
+        let normal = self.normal();
+        let start = self.start(normal);
+        let visible = self.visible();
+        Ok(crate::rows::from_fn(
+            self.image_size.x(),
+            self.image_size.y(),
+            |x, y| {
+                // Each pixel takes the color at its center.
+                let t = (x as f64 + 0.5) * normal[0] + (y as f64 + 0.5) * normal[1] - start;
+                self.color_at(&visible, t).unwrap_or(self.background_color)
+            },
+        ))
+    }
+}
 
-        image::ImageBuffer::from_fn(self.image_size.x(), self.image_size.y(), |x, y| {
-            let current_offset = parallel(x, y);
-            let adjustment = angle_adjustment(perpendicular(x, y), parallel(self.image_size.x(), self.image_size.y()), self.angle);
+impl super::Scaled for BarGenerator {
+    fn scaled(self, factor: u32) -> Self {
+        BarGenerator {
+            image_size: self.image_size * factor,
+            bars: self
+                .bars
+                .into_iter()
+                .map(|bar| Bar {
+                    offset: bar.offset * factor,
+                    size: bar.size * factor,
+                    ..bar
+                })
+                .collect(),
+            initial_offset: self.initial_offset * factor,
+            ..self
+        }
+    }
+}
 
-            // dbg!((parallel(x, y), perpendicular(x, y), adjustment));
-            let current_offset = current_offset.saturating_add(adjustment);
-            if current_offset < self.initial_offset {
-                self.background_color
-            } else {
-                let current_offset = current_offset.saturating_sub(self.initial_offset);//.saturating_sub(adjustment);
+/// The part of a `width` by `height` rectangle that's between `from`
+/// and `to` along `normal`.
+fn clip(width: f64, height: f64, normal: [f64; 2], from: f64, to: f64) -> Vec<[f64; 2]> {
+    let mut points = vec![[0.0, 0.0], [width, 0.0], [width, height], [0.0, height]];
+    for &(sign, limit) in [(1.0, from), (-1.0, -to)].iter() {
+        // How far a point is inside the edge.
+        let inside = |[x, y]: [f64; 2]| sign * (x * normal[0] + y * normal[1]) - limit;
+        let mut kept = Vec::new();
+        for (i, &a) in points.iter().enumerate() {
+            let b = points[(i + 1) % points.len()];
+            let (da, db) = (inside(a), inside(b));
+            if da >= 0.0 {
+                kept.push(a);
+            }
+            if (da >= 0.0) != (db >= 0.0) {
+                let t = da / (da - db);
+                kept.push([a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t]);
+            }
+        }
+        points = kept;
+    }
+    points
+}
 
-                self.find_bar(current_offset).unwrap_or(self.background_color)
+/// Stops that draw a gradient in SVG, which blends between them in
+/// sRGB.  There are more of them than the gradient has, so that the
+/// other interpolations come out close, and two where it jumps from
+/// one color to another, so that hard edges stay hard.
+fn svg_stops(gradient: &Gradient) -> Vec<(f32, image::Rgb<u8>)> {
+    let mut positions = (0..=20).map(|i| i as f32 / 20.0).collect::<Vec<_>>();
+    positions.extend(gradient.stops().iter().map(|stop| stop.position));
+    positions.sort_by(|a, b| a.partial_cmp(b).expect("positions are numbers"));
+    positions.dedup();
+    let mut stops = Vec::new();
+    for position in positions {
+        let (before, after) = (gradient.at(position - 1e-4), gradient.at(position));
+        if before != after {
+            stops.push((position, before));
+        }
+        stops.push((position, after));
+    }
+    stops
+}
+
+impl super::VectorGenerator for BarGenerator {
+    fn generate_svg(&self, _context: &Context) -> Result<Document> {
+        let (width, height) = (self.image_size.x() as f64, self.image_size.y() as f64);
+        let mut document = Document::new(
+            self.image_size.x(),
+            self.image_size.y(),
+            self.background_color,
+        );
+        let normal = self.normal();
+        let start = self.start(normal);
+        let length = (width * normal[0]).abs() + (height * normal[1]).abs();
+        let point = |t: f64| -> Point {
+            [
+                (normal[0] * (start + t)) as f32,
+                (normal[1] * (start + t)) as f32,
+            ]
+        };
+        let visible = self.visible();
+        // The image reaches from its corner, before the initial offset,
+        // to `length` past that.
+        let corner = -(self.initial_offset as f64);
+        for span in self.spans(&visible, corner, corner + length) {
+            let corners = clip(width, height, normal, start + span.start, start + span.end);
+            if corners.len() < 3 {
+                continue;
             }
-        })
+            let corners = corners
+                .into_iter()
+                .map(|[x, y]| [x as f32, y as f32])
+                .collect::<Vec<_>>();
+            match span.bar.gradient.as_ref() {
+                Some(gradient) => document.polygon_gradient(
+                    &corners,
+                    point(span.origin),
+                    point(span.origin + span.bar.size as f64),
+                    &svg_stops(gradient),
+                ),
+                None => document.polygon(&corners, span.bar.color),
+            }
+        }
+        Ok(document)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::Generator;
+    use crate::gradient::{Interpolation, Stop};
+
+    fn bars(size: [u32; 2], direction: [f64; 2]) -> BarGenerator {
+        BarGenerator {
+            image_size: Position::new(size[0], size[1]),
+            bars: vec![],
+            initial_offset: 0,
+            direction,
+            repeat: false,
+            antialias: false,
+            background_color: crate::hex(0x000000),
+        }
+    }
+
+    #[test]
+    fn test_antialias() {
+        let angle = 20f64.to_radians();
+        let render = |antialias: bool| {
+            let mut bars = BarGenerator {
+                antialias,
+                ..bars([32, 32], [angle.cos(), angle.sin()])
+            };
+            bars.push_bar(4, 12, crate::hex(0xffffff), None);
+            bars.generate(&Context::new(1)).unwrap()
+        };
+        let in_between =
+            |image: image::RgbImage| image.pixels().filter(|p| p[0] > 0 && p[0] < 0xff).count();
+        assert_eq!(in_between(render(false)), 0);
+        assert!(in_between(render(true)) > 32);
+    }
+
+    #[test]
+    fn test_bars() {
+        let render = |direction: [f64; 2], repeat: bool| {
+            let mut bars = BarGenerator {
+                repeat,
+                ..bars([12, 10], direction)
+            };
+            bars.push_bar(2, 3, crate::hex(0xffffff), None);
+            bars.generate(&Context::new(1)).unwrap()
+        };
+        let rows = |image: &image::RgbImage| -> Vec<u32> {
+            (0..image.height())
+                .filter(|&y| image.get_pixel(0, y)[0] == 0xff)
+                .collect()
+        };
+        // Bars start a pixel past their offset.
+        assert_eq!(rows(&render([0.0, 1.0], false)), vec![3, 4]);
+        // Laid out upwards, they start from the bottom.
+        assert_eq!(rows(&render([0.0, -1.0], false)), vec![5, 6]);
+        assert_eq!(rows(&render([0.0, 1.0], true)), vec![3, 4, 8, 9]);
+
+        let mut bars = bars([12, 10], [0.0, 1.0]);
+        let stops = [0x000000, 0xffffff]
+            .iter()
+            .enumerate()
+            .map(|(i, &color)| Stop {
+                position: i as f32,
+                color: crate::hex(color),
+            })
+            .collect();
+        let gradient = Gradient::new(stops, Interpolation::Srgb).unwrap();
+        bars.push_bar(0, 10, crate::hex(0x000000), Some(gradient));
+        let image = bars.generate(&Context::new(1)).unwrap();
+        assert!(rows(&image).is_empty());
+        assert!(image.get_pixel(0, 9)[0] > image.get_pixel(0, 0)[0]);
     }
 }
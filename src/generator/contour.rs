@@ -0,0 +1,210 @@
+use noise::Seedable;
+
+use super::coverage::Coverage;
+use super::noise::{Animated, NoiseGenerator};
+use crate::context::Context;
+use crate::error::Result;
+
+/// Draws the noise of a [`NoiseGenerator`] as a topographic map:
+/// lines along the noise values that are multiples of an interval,
+/// traced with marching squares.
+pub struct ContourGenerator<F: noise::NoiseFn<[f64; 2]>> {
+    noise: NoiseGenerator<F>,
+    /// The difference in noise value between one line and the next,
+    /// with noise values going from `0.0` to `1.0`.
+    pub interval: f32,
+    /// The width of the lines, in pixels.
+    pub width: f32,
+    pub color: image::Rgba<u8>,
+    /// Makes every nth line an index line, drawn with the index
+    /// width and color, like the labelled lines of a map.
+    pub index_every: Option<u32>,
+    pub index_width: f32,
+    pub index_color: image::Rgba<u8>,
+    /// The colors of the bands between the lines, from the lowest to
+    /// the highest.  When there are fewer colors than bands, each
+    /// color covers several bands in a row.  Without any, the noise
+    /// is drawn under the lines as it would be without them.
+    pub bands: Vec<image::Rgb<u8>>,
+}
+
+impl<F: noise::NoiseFn<[f64; 2]>> ContourGenerator<F> {
+    /// Lines one pixel wide, in `color`, at every `interval`.
+    pub fn new(noise: NoiseGenerator<F>, interval: f32, color: image::Rgba<u8>) -> Self {
+        ContourGenerator {
+            noise,
+            interval,
+            width: 1.0,
+            color,
+            index_every: None,
+            index_width: 2.0,
+            index_color: color,
+            bands: Vec::new(),
+        }
+    }
+
+    /// The number of lines, which are at every multiple of the
+    /// interval between `0.0` and `1.0`, not counting either end.
+    fn levels(&self) -> u32 {
+        // Just under one, so that an interval that divides it exactly
+        // doesn't put a line at the very top.
+        ((1.0 - 1e-6) / self.interval) as u32
+    }
+
+    fn is_index(&self, level: u32) -> bool {
+        // `is_multiple_of` needs a newer compiler than we support.
+        matches!(self.index_every, Some(n) if level.checked_rem(n) == Some(0))
+    }
+}
+
+impl<F: noise::NoiseFn<[f64; 2]>> super::Scaled for ContourGenerator<F> {
+    fn scaled(self, factor: u32) -> Self {
+        ContourGenerator {
+            noise: self.noise.scaled(factor),
+            width: self.width * factor as f32,
+            index_width: self.index_width * factor as f32,
+            ..self
+        }
+    }
+}
+
+impl<F> super::Generator<image::Rgb<u8>> for ContourGenerator<F>
+where
+    F: noise::NoiseFn<[f64; 2]> + Seedable + Animated + Send + Sync,
+{
+    fn generate(&self, context: &Context) -> Result<image::RgbImage> {
+        let field = self.noise.field(context);
+        let (width, height) = field.dimensions();
+        let levels = self.levels();
+
+        let mut image = crate::rows::from_fn(width, height, |x, y| {
+            let value = field.get_pixel(x, y)[0];
+            if self.bands.is_empty() {
+                return self.noise.color(value);
+            }
+            let band = ((value / self.interval) as u32).min(levels);
+            let index = band as usize * self.bands.len() / (levels as usize + 1);
+            self.bands[index]
+        });
+
+        let mut lines = Coverage::new(width, height);
+        let mut index_lines = Coverage::new(width, height);
+        let value = |x: u32, y: u32| field.get_pixel(x, y)[0];
+        for y in 0..height.saturating_sub(1) {
+            for x in 0..width.saturating_sub(1) {
+                let corners = [
+                    value(x, y),
+                    value(x + 1, y),
+                    value(x + 1, y + 1),
+                    value(x, y + 1),
+                ];
+                let low = corners.iter().cloned().fold(f32::INFINITY, f32::min);
+                let high = corners.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+                // Every line that could run through the cell, with one
+                // to spare at each end for rounding.
+                let first = ((low / self.interval) as u32).max(1);
+                let last = ((high / self.interval) as u32 + 1).min(levels);
+                for level in first..=last {
+                    let (coverage, width) = match self.is_index(level) {
+                        true => (&mut index_lines, self.index_width),
+                        false => (&mut lines, self.width),
+                    };
+                    let threshold = level as f32 * self.interval;
+                    for (from, to) in cell_segments(corners, threshold) {
+                        let offset = |[px, py]: [f32; 2]| [px + x as f32, py + y as f32];
+                        coverage.stroke(offset(from), offset(to), width);
+                    }
+                }
+            }
+        }
+
+        lines.paint(&mut image, self.color);
+        index_lines.paint(&mut image, self.index_color);
+        Ok(image)
+    }
+}
+
+/// The segments of the line at `threshold` through a cell between
+/// four samples, in order clockwise from the top left, one unit
+/// apart.  The points are relative to the top left sample.
+fn cell_segments(corners: [f32; 4], threshold: f32) -> Vec<([f32; 2], [f32; 2])> {
+    const POSITIONS: [[f32; 2]; 4] = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]];
+    let above = corners.map(|v| v >= threshold);
+
+    // Where the line crosses the edge after each corner, if it does.
+    let crossing = |edge: usize| {
+        let (a, b) = (edge, (edge + 1) % 4);
+        if above[a] == above[b] {
+            return None;
+        }
+        let t = (threshold - corners[a]) / (corners[b] - corners[a]);
+        let ([ax, ay], [bx, by]) = (POSITIONS[a], POSITIONS[b]);
+        Some([ax + (bx - ax) * t, ay + (by - ay) * t])
+    };
+    let crossings = [crossing(0), crossing(1), crossing(2), crossing(3)];
+    // The segment that cuts a corner off from the rest of the cell
+    // runs between the edges on either side of it.
+    let cut = |corner: usize| {
+        let before = crossings[(corner + 3) % 4].expect("the edge before the corner is crossed");
+        let after = crossings[corner].expect("the edge after the corner is crossed");
+        (before, after)
+    };
+
+    let count = above.iter().filter(|&&a| a).count();
+    match count {
+        0 | 4 => Vec::new(),
+        // A single corner on its own side of the line.
+        1 => vec![cut(above.iter().position(|&a| a).unwrap())],
+        3 => vec![cut(above.iter().position(|&a| !a).unwrap())],
+        // Either two corners next to each other, with the line across
+        // the middle, or a saddle, with opposite corners on the same
+        // side.  A saddle is split by the value at its center; the
+        // two corners on the other side of it are cut off.
+        _ if above[0] != above[2] => {
+            let points = crossings.iter().flatten().cloned().collect::<Vec<_>>();
+            vec![(points[0], points[1])]
+        }
+        _ => {
+            let center = corners.iter().sum::<f32>() / 4.0 >= threshold;
+            match above[0] == center {
+                true => vec![cut(1), cut(3)],
+                false => vec![cut(0), cut(2)],
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::Generator;
+    use crate::position::Position;
+
+    #[test]
+    fn test_cell_segments() {
+        // The line at 0.5 crosses halfway along the top and left.
+        let segments = cell_segments([1.0, 0.0, 0.0, 0.0], 0.5);
+        assert_eq!(segments, vec![([0.0, 0.5], [0.5, 0.0])]);
+        // Straight across the middle, between two pairs of corners.
+        let segments = cell_segments([0.0, 0.0, 1.0, 1.0], 0.25);
+        assert_eq!(segments, vec![([1.0, 0.25], [0.0, 0.25])]);
+        // A saddle with a high center joins the high corners.
+        let segments = cell_segments([1.0, 0.0, 1.0, 0.2], 0.5);
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].0, [0.5, 0.0]);
+        assert!(cell_segments([0.2, 0.3, 0.4, 0.1], 0.5).is_empty());
+    }
+
+    #[test]
+    fn test_bands() {
+        let black = crate::hex(0x000000);
+        let noise = NoiseGenerator::new(Position::new(32, 32), black, black, 2.0, 1.0);
+        let mut contours = ContourGenerator::new(noise, 0.1, image::Rgba([0, 0, 0, 0xff]));
+        contours.bands = vec![crate::hex(0xffffff)];
+        let image = contours.generate(&Context::new(1)).unwrap();
+        // The bands stand in for the colors, and the lines are drawn
+        // over them.
+        assert!(image.pixels().any(|&p| p == crate::hex(0xffffff)));
+        assert!(image.pixels().any(|&p| p[0] < 0x80));
+    }
+}
@@ -0,0 +1,135 @@
+//! Antialiased lines and edges, drawn over an image.
+
+/// How much of each pixel is covered by lines, from `0.0` to `1.0`.
+pub struct Coverage {
+    width: u32,
+    height: u32,
+    values: Vec<f32>,
+}
+
+impl Coverage {
+    pub fn new(width: u32, height: u32) -> Self {
+        Coverage {
+            width,
+            height,
+            values: vec![0.0; width as usize * height as usize],
+        }
+    }
+
+    /// Covers the pixels along a line from `from` to `to`, in
+    /// the coordinates of pixel centers, with round ends.  The edges
+    /// are antialiased by each pixel's distance from the line.
+    pub fn stroke(&mut self, from: [f32; 2], to: [f32; 2], width: f32) {
+        let radius = width.max(1.0) / 2.0;
+        // Lines thinner than a pixel are drawn a pixel wide, but
+        // fainter.
+        let strength = width.min(1.0);
+        let reach = radius + 1.0;
+        let left = (from[0].min(to[0]) - reach).floor().max(0.0) as u32;
+        let top = (from[1].min(to[1]) - reach).floor().max(0.0) as u32;
+        let right = ((from[0].max(to[0]) + reach).ceil() as u32).min(self.width);
+        let bottom = ((from[1].max(to[1]) + reach).ceil() as u32).min(self.height);
+
+        let (dx, dy) = (to[0] - from[0], to[1] - from[1]);
+        let length = dx * dx + dy * dy;
+        for y in top..bottom {
+            for x in left..right {
+                let (px, py) = (x as f32 - from[0], y as f32 - from[1]);
+                let t = match length > 0.0 {
+                    true => ((px * dx + py * dy) / length).clamp(0.0, 1.0),
+                    false => 0.0,
+                };
+                let distance = (px - t * dx).hypot(py - t * dy);
+                let covered = (radius + 0.5 - distance).clamp(0.0, 1.0) * strength;
+                let value = &mut self.values[(y * self.width + x) as usize];
+                // Where segments meet, they overlap; taking the most
+                // that any of them covers keeps the joins even.
+                *value = value.max(covered);
+            }
+        }
+    }
+
+    pub fn paint(&self, image: &mut image::RgbImage, color: image::Rgba<u8>) {
+        let line = image::Rgb([color[0], color[1], color[2]]);
+        let alpha = color[3] as f32 / 255.0;
+        for (pixel, &covered) in image.pixels_mut().zip(self.values.iter()) {
+            if covered > 0.0 {
+                *pixel = imageproc::pixelops::interpolate(line, *pixel, covered * alpha);
+            }
+        }
+    }
+}
+
+/// How much of the pixel with its top left corner at `corner` lies
+/// between `from` and `to`, measured along `normal`.  This is exact for
+/// the straight edges of bands, like stripes and bars.
+pub fn band(normal: [f32; 2], corner: [f32; 2], from: f32, to: f32) -> f32 {
+    // The pixel's corner that's the least far along the normal.
+    let low =
+        corner[0] * normal[0] + corner[1] * normal[1] + normal[0].min(0.0) + normal[1].min(0.0);
+    let (a, b) = (normal[0].abs(), normal[1].abs());
+    (below(to - low, a, b) - below(from - low, a, b)).max(0.0)
+}
+
+/// How much of a pixel is less than `t` along a normal whose
+/// components are `a` and `b`, from its least far corner.  Across the
+/// pixel, the distance along the normal is the sum of two even spreads,
+/// `a` and `b` wide.
+fn below(t: f32, a: f32, b: f32) -> f32 {
+    let (a, b) = (a.min(b), a.max(b));
+    if t <= 0.0 {
+        0.0
+    } else if t >= a + b {
+        1.0
+    } else if a <= f32::EPSILON {
+        t / b
+    } else if t < a {
+        t * t / (2.0 * a * b)
+    } else if t < b {
+        (t - a / 2.0) / b
+    } else {
+        1.0 - (a + b - t) * (a + b - t) / (2.0 * a * b)
+    }
+}
+
+/// Mixes colors that each cover part of a pixel over a background.
+/// The parts mustn't overlap, so that where two meet they share the
+/// pixel out between them, rather than letting the background show
+/// through.
+pub fn mix<I>(background: image::Rgb<u8>, parts: I) -> image::Rgb<u8>
+where
+    I: IntoIterator<Item = (f32, image::Rgb<u8>)>,
+{
+    let mut sum = [0.0; 3];
+    let mut covered = 0.0;
+    for (part, color) in parts {
+        for (sum, &channel) in sum.iter_mut().zip(color.0.iter()) {
+            *sum += part * channel as f32;
+        }
+        covered += part;
+    }
+    let rest = (1.0 - covered).max(0.0);
+    let channel = |i: usize| (sum[i] + rest * background[i] as f32).round().min(255.0) as u8;
+    image::Rgb([channel(0), channel(1), channel(2)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_band() {
+        // Straight across, a band covers whole rows of pixels and
+        // parts of the ones at its edges.
+        assert_eq!(band([0.0, 1.0], [3.0, 2.0], 2.0, 5.0), 1.0);
+        assert_eq!(band([0.0, 1.0], [3.0, 2.0], 2.5, 5.0), 0.5);
+        assert_eq!(band([0.0, 1.0], [3.0, 6.0], 2.0, 5.0), 0.0);
+        // A diagonal edge through the middle of a pixel covers half.
+        assert!((band([1.0, 1.0], [0.0, 0.0], 1.0, 9.0) - 0.5).abs() < 1e-6);
+        assert!((band([-1.0, 1.0], [0.0, 0.0], 0.0, 9.0) - 0.5).abs() < 1e-6);
+        assert!((band([1.0, 0.5], [0.0, 0.0], 0.0, 0.25) - 1.0 / 16.0).abs() < 1e-6);
+
+        let (black, white) = (crate::hex(0x000000), crate::hex(0xffffff));
+        assert_eq!(mix(black, vec![(0.5, white), (0.5, white)]), white);
+    }
+}
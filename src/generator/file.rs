@@ -1,3 +1,6 @@
+use crate::context::Context;
+use crate::error::{Error, Result};
+
 pub struct FileGenerator {
     path: std::path::PathBuf,
 }
@@ -11,7 +14,14 @@ impl FileGenerator {
 }
 
 impl super::Generator<image::Rgb<u8>> for FileGenerator {
-    fn generate(&self) -> image::ImageBuffer<image::Rgb<u8>, Vec<u8>> {
-        image::open(&self.path).expect("invalid image").to_rgb()
+    fn generate(&self, _context: &Context) -> Result<image::ImageBuffer<image::Rgb<u8>, Vec<u8>>> {
+        let image = image::open(&self.path).map_err(|e| {
+            Error::parameter(
+                "file",
+                "path",
+                format!("could not load {}: {}", self.path.display(), e),
+            )
+        })?;
+        Ok(image.to_rgb())
     }
 }
@@ -0,0 +1,381 @@
+use noise::{MultiFractal, NoiseFn, Seedable};
+use serde::{Deserialize, Serialize};
+
+use super::{Animated, Perlin};
+
+/// The kinds of noise a [`Source`] can be.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceKind {
+    Perlin,
+    OpenSimplex,
+    /// Distance to the closest of a set of scattered points, which
+    /// makes cells.
+    Worley,
+    /// Random values at whole coordinates, blended between.
+    Value,
+    /// Fractal Perlin noise, folded into rounded, cloudy lumps.
+    Billow,
+    /// Fractal Perlin noise, folded into sharp ridges, like
+    /// mountains or marble veins.
+    RidgedMulti,
+    /// Plain fractal Perlin noise.
+    Fbm,
+    /// Fractal Perlin noise that is smooth in its valleys and rough
+    /// on its peaks.
+    HybridMulti,
+}
+
+impl SourceKind {
+    /// Whether the noise is built on Perlin noise.  The `noise`
+    /// crate's 4D Perlin noise has seams wherever a coordinate
+    /// crosses a whole number, so these can't move in animations.
+    pub fn is_perlin(self) -> bool {
+        !matches!(
+            self,
+            SourceKind::OpenSimplex | SourceKind::Worley | SourceKind::Value
+        )
+    }
+
+    /// The number of octaves that the noise has by default: one for
+    /// the plain noise functions, and `noise`'s own default for the
+    /// fractal ones.
+    pub fn default_octaves(self) -> usize {
+        match self {
+            SourceKind::Perlin
+            | SourceKind::OpenSimplex
+            | SourceKind::Worley
+            | SourceKind::Value => 1,
+            _ => noise::Fbm::DEFAULT_OCTAVE_COUNT,
+        }
+    }
+}
+
+/// A noise function picked at runtime, like from a pipeline.
+///
+/// The fractal kinds come straight from `noise`.  The plain kinds
+/// are turned into fractals by [`Octaves`], and are exactly the plain
+/// noise function when they have a single octave.
+#[derive(Clone)]
+pub enum Source {
+    Perlin(Octaves<Perlin>),
+    OpenSimplex(Octaves<noise::OpenSimplex>),
+    Worley(Octaves<noise::Worley>),
+    Value(Octaves<noise::Value>),
+    Billow(noise::Billow),
+    RidgedMulti(noise::RidgedMulti),
+    Fbm(noise::Fbm),
+    HybridMulti(noise::HybridMulti),
+}
+
+impl Source {
+    /// The noise, with its default octaves, a persistence of `0.5`, a
+    /// lacunarity of `2.0`, and a frequency of `1.0`.
+    pub fn new(kind: SourceKind) -> Self {
+        let source = match kind {
+            SourceKind::Perlin => Source::Perlin(Octaves::new(Perlin::new())),
+            SourceKind::OpenSimplex => Source::OpenSimplex(Octaves::new(noise::OpenSimplex::new())),
+            SourceKind::Worley => {
+                Source::Worley(Octaves::new(noise::Worley::new().enable_range(true)))
+            }
+            SourceKind::Value => Source::Value(Octaves::new(noise::Value::new())),
+            SourceKind::Billow => Source::Billow(noise::Billow::new()),
+            SourceKind::RidgedMulti => Source::RidgedMulti(noise::RidgedMulti::new()),
+            SourceKind::Fbm => Source::Fbm(noise::Fbm::new()),
+            SourceKind::HybridMulti => Source::HybridMulti(noise::HybridMulti::new()),
+        };
+        source
+            .set_octaves(kind.default_octaves())
+            .set_persistence(0.5)
+            .set_lacunarity(2.0)
+    }
+
+    /// Worley noise normally shades each cell by the distance to its
+    /// point.  This fills each cell with a single value instead, for
+    /// flat Voronoi cells.  Other kinds of noise are left alone.
+    pub fn with_flat_cells(self) -> Self {
+        match self {
+            Source::Worley(octaves) => {
+                Source::Worley(octaves.map(|worley| worley.enable_range(false)))
+            }
+            other => other,
+        }
+    }
+
+    fn sample<const N: usize>(&self, point: [f64; N]) -> f64
+    where
+        Octaves<Perlin>: NoiseFn<[f64; N]>,
+        Octaves<noise::OpenSimplex>: NoiseFn<[f64; N]>,
+        Octaves<noise::Worley>: NoiseFn<[f64; N]>,
+        Octaves<noise::Value>: NoiseFn<[f64; N]>,
+        noise::Billow: NoiseFn<[f64; N]>,
+        noise::RidgedMulti: NoiseFn<[f64; N]>,
+        noise::Fbm: NoiseFn<[f64; N]>,
+        noise::HybridMulti: NoiseFn<[f64; N]>,
+    {
+        match self {
+            Source::Perlin(f) => f.get(point),
+            Source::OpenSimplex(f) => f.get(point),
+            Source::Worley(f) => f.get(point),
+            Source::Value(f) => f.get(point),
+            Source::Billow(f) => f.get(point),
+            Source::RidgedMulti(f) => f.get(point),
+            Source::Fbm(f) => f.get(point),
+            Source::HybridMulti(f) => f.get(point),
+        }
+    }
+}
+
+macro_rules! each {
+    ($source:expr, $f:ident => $body:expr) => {
+        match $source {
+            Source::Perlin($f) => Source::Perlin($body),
+            Source::OpenSimplex($f) => Source::OpenSimplex($body),
+            Source::Worley($f) => Source::Worley($body),
+            Source::Value($f) => Source::Value($body),
+            Source::Billow($f) => Source::Billow($body),
+            Source::RidgedMulti($f) => Source::RidgedMulti($body),
+            Source::Fbm($f) => Source::Fbm($body),
+            Source::HybridMulti($f) => Source::HybridMulti($body),
+        }
+    };
+}
+
+impl NoiseFn<[f64; 2]> for Source {
+    fn get(&self, point: [f64; 2]) -> f64 {
+        self.sample(point)
+    }
+}
+
+impl NoiseFn<[f64; 4]> for Source {
+    fn get(&self, point: [f64; 4]) -> f64 {
+        self.sample(point)
+    }
+}
+
+impl Seedable for Source {
+    fn set_seed(self, seed: u32) -> Self {
+        each!(self, f => f.set_seed(seed))
+    }
+
+    fn seed(&self) -> u32 {
+        match self {
+            Source::Perlin(f) => f.seed(),
+            Source::OpenSimplex(f) => f.seed(),
+            Source::Worley(f) => f.seed(),
+            Source::Value(f) => f.seed(),
+            Source::Billow(f) => f.seed(),
+            Source::RidgedMulti(f) => f.seed(),
+            Source::Fbm(f) => f.seed(),
+            Source::HybridMulti(f) => f.seed(),
+        }
+    }
+}
+
+impl MultiFractal for Source {
+    fn set_octaves(self, octaves: usize) -> Self {
+        each!(self, f => f.set_octaves(octaves))
+    }
+
+    fn set_frequency(self, frequency: f64) -> Self {
+        each!(self, f => f.set_frequency(frequency))
+    }
+
+    fn set_lacunarity(self, lacunarity: f64) -> Self {
+        each!(self, f => f.set_lacunarity(lacunarity))
+    }
+
+    fn set_persistence(self, persistence: f64) -> Self {
+        each!(self, f => f.set_persistence(persistence))
+    }
+}
+
+impl Animated for Source {}
+
+/// Fractal noise made from any noise function: several copies of it,
+/// each at a higher frequency and a lower amplitude than the last,
+/// added together.  The sum is scaled back into the range of a single
+/// copy.
+#[derive(Clone)]
+pub struct Octaves<F> {
+    sources: Vec<F>,
+    seed: u32,
+    frequency: f64,
+    lacunarity: f64,
+    persistence: f64,
+}
+
+impl<F: Seedable + Clone> Octaves<F> {
+    /// The noise function on its own, as a single octave.
+    pub fn new(f: F) -> Self {
+        Octaves {
+            seed: f.seed(),
+            sources: vec![f],
+            frequency: 1.0,
+            lacunarity: 2.0,
+            persistence: 0.5,
+        }
+    }
+
+    fn map<G: FnMut(F) -> F>(self, f: G) -> Self {
+        Octaves {
+            sources: self.sources.into_iter().map(f).collect(),
+            ..self
+        }
+    }
+}
+
+impl<F, const N: usize> NoiseFn<[f64; N]> for Octaves<F>
+where
+    F: NoiseFn<[f64; N]>,
+{
+    fn get(&self, point: [f64; N]) -> f64 {
+        let (mut frequency, mut amplitude) = (self.frequency, 1.0);
+        let (mut sum, mut total) = (0.0, 0.0);
+        for source in self.sources.iter() {
+            let mut scaled = point;
+            for v in scaled.iter_mut() {
+                *v *= frequency;
+            }
+            sum += source.get(scaled) * amplitude;
+            total += amplitude;
+            frequency *= self.lacunarity;
+            amplitude *= self.persistence;
+        }
+        sum / total
+    }
+}
+
+impl<F: Seedable + Clone> Seedable for Octaves<F> {
+    /// Each octave gets its own seed, counting up from `seed`, so
+    /// that they don't line up with each other.
+    fn set_seed(self, seed: u32) -> Self {
+        let sources = self
+            .sources
+            .iter()
+            .enumerate()
+            .map(|(i, source)| source.clone().set_seed(seed.wrapping_add(i as u32)))
+            .collect();
+        Octaves {
+            sources,
+            seed,
+            ..self
+        }
+    }
+
+    fn seed(&self) -> u32 {
+        self.seed
+    }
+}
+
+impl<F: Seedable + Clone> MultiFractal for Octaves<F> {
+    fn set_octaves(self, octaves: usize) -> Self {
+        let octaves = octaves.clamp(1, noise::Fbm::MAX_OCTAVES);
+        let mut sources = self.sources;
+        sources.resize(octaves, sources[0].clone());
+        Octaves { sources, ..self }.set_seed(self.seed)
+    }
+
+    fn set_frequency(self, frequency: f64) -> Self {
+        Octaves { frequency, ..self }
+    }
+
+    fn set_lacunarity(self, lacunarity: f64) -> Self {
+        Octaves { lacunarity, ..self }
+    }
+
+    fn set_persistence(self, persistence: f64) -> Self {
+        Octaves {
+            persistence,
+            ..self
+        }
+    }
+}
+
+/// Domain warping: samples a noise function at points that are
+/// pushed around by a second copy of it, which swirls the noise into
+/// marbled shapes.
+#[derive(Clone)]
+pub struct Warp<F> {
+    f: F,
+    warp: F,
+    strength: f64,
+    frequency: f64,
+}
+
+impl<F: Clone> Warp<F> {
+    /// Warps `f` by up to `strength`, in the units of the noise's own
+    /// coordinates.
+    pub fn new(f: F, strength: f64) -> Self {
+        Warp {
+            warp: f.clone(),
+            f,
+            strength,
+            frequency: 1.0,
+        }
+    }
+
+    /// Sets how much finer the warping is than the noise itself.
+    pub fn with_frequency(self, frequency: f64) -> Self {
+        Warp { frequency, ..self }
+    }
+}
+
+impl<F: NoiseFn<[f64; 2]>> NoiseFn<[f64; 2]> for Warp<F> {
+    fn get(&self, [x, y]: [f64; 2]) -> f64 {
+        let at = |dx: f64, dy: f64| {
+            self.warp
+                .get([(x + dx) * self.frequency, (y + dy) * self.frequency])
+        };
+        // The second sample is taken far from the first, so that the
+        // two directions are pushed independently.
+        let (dx, dy) = (at(0.0, 0.0), at(5.2, 1.3));
+        self.f.get([x + dx * self.strength, y + dy * self.strength])
+    }
+}
+
+impl<F: Seedable> Seedable for Warp<F> {
+    fn set_seed(self, seed: u32) -> Self {
+        Warp {
+            f: self.f.set_seed(seed),
+            // Any seed other than the noise's own would do.
+            warp: self.warp.set_seed(seed.wrapping_add(0x9e37_79b9)),
+            ..self
+        }
+    }
+
+    fn seed(&self) -> u32 {
+        self.f.seed()
+    }
+}
+
+impl<F: Animated> Animated for Warp<F> {
+    fn at(&self, time: f64) -> Self {
+        Warp {
+            f: self.f.at(time),
+            warp: self.warp.at(time),
+            ..self.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_single_octave_is_plain_noise() {
+        let perlin = Perlin::new().set_seed(7);
+        let source = Source::new(SourceKind::Perlin).set_seed(7);
+        for point in &[[0.25, 0.5], [3.7, -1.2], [10.1, 4.4]] {
+            assert_eq!(source.get(*point), perlin.get(*point));
+        }
+
+        // More octaves add detail, but keep the noise in range.
+        let detailed = Source::new(SourceKind::Value).set_octaves(5).set_seed(7);
+        for i in 0..100 {
+            let v = detailed.get([i as f64 * 0.37, i as f64 * 0.11]);
+            assert!((-1.0..=1.0).contains(&v));
+        }
+    }
+}
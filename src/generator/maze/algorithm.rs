@@ -0,0 +1,537 @@
+//! The algorithms that carve a maze out of a graph of closed cells.
+//! Each one leaves a different texture behind.
+//!
+//! Wherever an algorithm picks a direction to carve in or to walk in,
+//! the pick is weighted by the maze's direction weights.  Walls, which
+//! can be crossed either way, are weighted by both directions.
+
+use super::grid::Graph;
+use super::Direction;
+use rand::distributions::WeightedError;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Algorithm {
+    /// A depth first search that backs up at dead ends, which makes
+    /// long, winding corridors with few branches.
+    #[default]
+    Backtracker,
+    /// Joins cells along randomly ordered walls, which makes lots of
+    /// short dead ends.
+    Kruskal,
+    /// Grows the maze out from a single cell, one random wall on its
+    /// edge at a time, which makes many short branches around a
+    /// center.
+    Prim,
+    /// Loop erased random walks.  Every possible maze is equally
+    /// likely, when the direction weights are all the same.
+    Wilson,
+    /// Builds the maze one row at a time.
+    Eller,
+    /// A random walk that carves into every cell it visits for the
+    /// first time.  Also unbiased, but slow on large grids.
+    AldousBroder,
+    /// Carves each cell either north or east, which leaves a
+    /// diagonal grain and open corridors along the top and right.
+    BinaryTree,
+    /// Carves runs of cells to the east, each with a single opening
+    /// to the north, which leaves a vertical grain and an open
+    /// corridor along the top.
+    Sidewinder,
+    /// Grows the maze from a list of cells, carving from either the
+    /// newest cell in the list or a random one.  Always picking the
+    /// newest is the backtracker, and always picking a random one is
+    /// close to Prim.
+    GrowingTree,
+}
+
+impl Algorithm {
+    /// Whether the algorithm only works on a square grid, since it
+    /// goes through the cells by rows and columns.
+    pub fn is_square_only(self) -> bool {
+        matches!(
+            self,
+            Algorithm::Eller | Algorithm::BinaryTree | Algorithm::Sidewinder
+        )
+    }
+}
+
+/// Picks one of `options`, weighted by their directions.  If there's
+/// only one, it's picked even if its weight is 0, since it's the only
+/// way for the maze to stay connected.
+fn choose<R: Rng, F: Fn(Direction) -> u32>(
+    options: &[(usize, usize, Direction)],
+    weight: F,
+    rng: &mut R,
+) -> Result<(usize, usize, Direction), WeightedError> {
+    match options {
+        [only] => Ok(*only),
+        _ => options.choose_weighted(rng, |o| weight(o.2)).copied(),
+    }
+}
+
+/// Weights each wall by both directions across it, for random walks.
+/// A walk weighted by direction alone drifts, and can take ages to
+/// reach the cells that it drifts away from; this one doesn't, and
+/// still crosses the heavier walls more often.
+fn symmetric<F: Fn(Direction) -> u32>(weight: F) -> impl Fn(Direction) -> u32 {
+    move |d| weight(d) + weight(d.reverse())
+}
+
+/// A random key for a wall, so that sorting walls by their keys
+/// shuffles them with the odds of each coming first in proportion to
+/// its weight.  Walls weighted 0 come after all of the others.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Key {
+    unwanted: bool,
+    value: f64,
+}
+
+impl Key {
+    fn new<R: Rng>(weight: u32, rng: &mut R) -> Self {
+        let uniform: f64 = rng.gen();
+        match weight {
+            0 => Key {
+                unwanted: true,
+                value: uniform,
+            },
+            // An exponentially distributed race, which the heaviest
+            // weights tend to win.
+            _ => Key {
+                unwanted: false,
+                value: -(1.0 - uniform).ln() / weight as f64,
+            },
+        }
+    }
+}
+
+impl Eq for Key {}
+
+impl Ord for Key {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.unwanted
+            .cmp(&other.unwanted)
+            .then(self.value.total_cmp(&other.value))
+    }
+}
+
+impl PartialOrd for Key {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Carves a maze out of a graph of closed cells.  `newest` is how
+/// often the growing tree algorithm carves from the newest cell, from
+/// `0.0` to `1.0`; the other algorithms ignore it.  The algorithms
+/// that are square only expect a graph made by [`Graph::square`].
+pub fn carve<F: Fn(Direction) -> u32, R: Rng>(
+    algorithm: Algorithm,
+    newest: f64,
+    weight: F,
+    graph: &mut Graph,
+    rng: &mut R,
+) -> Result<(), WeightedError> {
+    if graph.is_empty() {
+        return Ok(());
+    }
+    let square = || Square::new(graph.row(0).len(), graph.rows());
+    match algorithm {
+        Algorithm::Backtracker => backtracker(graph, weight, rng)?,
+        Algorithm::Kruskal => kruskal(graph, weight, rng),
+        Algorithm::Prim => prim(graph, weight, rng),
+        Algorithm::Wilson => wilson(graph, weight, rng)?,
+        Algorithm::Eller => eller(square(), graph, weight, rng)?,
+        Algorithm::AldousBroder => aldous_broder(graph, weight, rng)?,
+        Algorithm::BinaryTree => binary_tree(square(), graph, weight, rng)?,
+        Algorithm::Sidewinder => sidewinder(square(), graph, weight, rng)?,
+        Algorithm::GrowingTree => growing_tree(graph, newest, weight, rng)?,
+    }
+    Ok(())
+}
+
+/// The walls out of a cell that lead to other cells, as the wall and
+/// the direction it faces.
+fn options(graph: &Graph, cell: usize) -> Vec<(usize, usize, Direction)> {
+    graph
+        .neighbors(cell)
+        .map(|(side, neighbor)| (side, neighbor, graph.cell(cell).sides[side].facing))
+        .collect()
+}
+
+fn backtracker<F: Fn(Direction) -> u32, R: Rng>(
+    graph: &mut Graph,
+    weight: F,
+    rng: &mut R,
+) -> Result<(), WeightedError> {
+    let mut stack = vec![0];
+    while let Some(&cell) = stack.last() {
+        graph.visit(cell);
+        let possible = options(graph, cell)
+            .into_iter()
+            .filter(|&(_, neighbor, _)| !graph.visited(neighbor))
+            .collect::<Vec<_>>();
+
+        if possible.is_empty() {
+            stack.pop();
+            continue;
+        }
+
+        let &(side, _, _) = possible.choose_weighted(rng, |o| weight(o.2))?;
+        stack.push(graph.carve(cell, side));
+    }
+    Ok(())
+}
+
+fn kruskal<F: Fn(Direction) -> u32, R: Rng>(graph: &mut Graph, weight: F, rng: &mut R) {
+    // Every wall, from the cell that comes first.  A wall can be
+    // crossed either way, so it's weighted by both directions.
+    let across = symmetric(weight);
+    let mut walls = Vec::new();
+    for cell in 0..graph.len() {
+        for (side, neighbor, facing) in options(graph, cell) {
+            if neighbor > cell {
+                walls.push((Key::new(across(facing), rng), cell, side, neighbor));
+            }
+        }
+    }
+    walls.sort_by_key(|wall| wall.0);
+
+    // The set that each cell is in, as a tree of cells pointing
+    // towards the one that names the set.
+    let mut parents = (0..graph.len()).collect::<Vec<_>>();
+    fn root(parents: &mut [usize], mut cell: usize) -> usize {
+        while parents[cell] != cell {
+            parents[cell] = parents[parents[cell]];
+            cell = parents[cell];
+        }
+        cell
+    }
+    for (_, cell, side, neighbor) in walls {
+        let (a, b) = (root(&mut parents, cell), root(&mut parents, neighbor));
+        if a != b {
+            parents[b] = a;
+            graph.carve(cell, side);
+        }
+    }
+}
+
+fn prim<F: Fn(Direction) -> u32, R: Rng>(graph: &mut Graph, weight: F, rng: &mut R) {
+    // The walls on the edge of the maze, in a random order that's
+    // weighted by the direction they would be carved in.  Since the
+    // keys are exponentially distributed, taking the lowest is the
+    // same as picking one of them by weight.
+    let mut edge = BinaryHeap::new();
+    let start = graph.random_cell(rng);
+    graph.visit(start);
+    let mut add = |graph: &Graph, cell: usize, edge: &mut BinaryHeap<_>| {
+        for (side, neighbor, facing) in options(graph, cell) {
+            if !graph.visited(neighbor) {
+                let key = Key::new(weight(facing), rng);
+                edge.push(std::cmp::Reverse((key, cell, side, neighbor)));
+            }
+        }
+    };
+    add(graph, start, &mut edge);
+
+    while let Some(std::cmp::Reverse((_, from, side, to))) = edge.pop() {
+        if graph.visited(to) {
+            continue;
+        }
+        let to = graph.carve(from, side);
+        add(graph, to, &mut edge);
+    }
+}
+
+fn wilson<F: Fn(Direction) -> u32, R: Rng>(
+    graph: &mut Graph,
+    weight: F,
+    rng: &mut R,
+) -> Result<(), WeightedError> {
+    let across = symmetric(weight);
+    let start = graph.random_cell(rng);
+    graph.visit(start);
+    // The wall that the walk last left each cell through.  Following
+    // these from the start of a walk skips any loops in it.
+    let mut exits = vec![None; graph.len()];
+    let mut cells = (0..graph.len()).collect::<Vec<_>>();
+    cells.shuffle(rng);
+
+    for cell in cells {
+        if graph.visited(cell) {
+            continue;
+        }
+        let mut at = cell;
+        while !graph.visited(at) {
+            let (side, next, _) = choose(&options(graph, at), &across, rng)?;
+            exits[at] = Some((side, next));
+            at = next;
+        }
+        // Carves along the walk, until it joins the maze.
+        let mut at = cell;
+        loop {
+            let (side, next) = exits[at].expect("every cell on the walk was left");
+            let joined = graph.visited(next);
+            at = graph.carve(at, side);
+            if joined {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn aldous_broder<F: Fn(Direction) -> u32, R: Rng>(
+    graph: &mut Graph,
+    weight: F,
+    rng: &mut R,
+) -> Result<(), WeightedError> {
+    let across = symmetric(weight);
+    let mut at = graph.random_cell(rng);
+    graph.visit(at);
+    let mut remaining = graph.len() - 1;
+    while remaining > 0 {
+        let (side, next, _) = choose(&options(graph, at), &across, rng)?;
+        if graph.visited(next) {
+            at = next;
+        } else {
+            at = graph.carve(at, side);
+            remaining -= 1;
+        }
+    }
+    Ok(())
+}
+
+/// The rows and columns of a graph made by [`Graph::square`].
+#[derive(Debug, Copy, Clone)]
+struct Square {
+    width: usize,
+    height: usize,
+}
+
+impl Square {
+    fn new(width: usize, height: usize) -> Self {
+        Square { width, height }
+    }
+
+    fn cell(self, x: usize, y: usize) -> usize {
+        y * self.width + x
+    }
+
+    /// The walls of a cell on the given sides that lead to other
+    /// cells.  Square cells have their walls in the same order as
+    /// [`Direction::all`].
+    fn options(self, x: usize, y: usize, sides: &[Direction]) -> Vec<(usize, usize, Direction)> {
+        sides
+            .iter()
+            .flat_map(|d| d.vector(x, y, self.width, self.height))
+            .map(|v| {
+                let side = Direction::all()
+                    .iter()
+                    .position(|&d| d == v.direction())
+                    .expect("every direction is a side");
+                (side, self.cell(v.x(), v.y()), v.direction())
+            })
+            .collect()
+    }
+}
+
+fn binary_tree<F: Fn(Direction) -> u32, R: Rng>(
+    square: Square,
+    graph: &mut Graph,
+    weight: F,
+    rng: &mut R,
+) -> Result<(), WeightedError> {
+    for y in 0..square.height {
+        for x in 0..square.width {
+            let options = square.options(x, y, &[Direction::North, Direction::East]);
+            if options.is_empty() {
+                continue;
+            }
+            let (side, _, _) = choose(&options, &weight, rng)?;
+            graph.carve(square.cell(x, y), side);
+        }
+    }
+    Ok(())
+}
+
+fn sidewinder<F: Fn(Direction) -> u32, R: Rng>(
+    square: Square,
+    graph: &mut Graph,
+    weight: F,
+    rng: &mut R,
+) -> Result<(), WeightedError> {
+    let [north, east] = [0, 1];
+    for y in 0..square.height {
+        let mut start = 0;
+        for x in 0..square.width {
+            let options = square.options(x, y, &[Direction::North, Direction::East]);
+            if options.is_empty() {
+                continue;
+            }
+            match choose(&options, &weight, rng)?.2 {
+                Direction::East => {
+                    graph.carve(square.cell(x, y), east);
+                }
+                _ => {
+                    // Closes the run with an opening from any of its
+                    // cells.
+                    let from = rng.gen_range(start, x + 1);
+                    graph.carve(square.cell(from, y), north);
+                    start = x + 1;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn eller<F: Fn(Direction) -> u32, R: Rng>(
+    square: Square,
+    graph: &mut Graph,
+    weight: F,
+    rng: &mut R,
+) -> Result<(), WeightedError> {
+    let [east, south] = [1, 2];
+    // Summed as `u64`s, so that large weights can't overflow; `join`
+    // and `drop` are then always between 0 and 1.
+    let across = weight(Direction::East) as u64 + weight(Direction::West) as u64;
+    let down = weight(Direction::North) as u64 + weight(Direction::South) as u64;
+    if across + down == 0 {
+        return Err(WeightedError::AllWeightsZero);
+    }
+    let join = across as f64 / (across + down) as f64;
+    let drop = down as f64 / (across + down) as f64;
+
+    // The set each cell in the row is in; cells only share a set if
+    // they're connected by the rows carved so far.
+    let mut sets: Vec<Option<usize>> = vec![None; square.width];
+    let mut next_set = 0;
+    for y in 0..square.height {
+        for set in sets.iter_mut().filter(|s| s.is_none()) {
+            *set = Some(next_set);
+            next_set += 1;
+        }
+        let last = y + 1 == square.height;
+        for x in 0..square.width.saturating_sub(1) {
+            let (a, b) = (sets[x], sets[x + 1]);
+            // The last row joins everything that's left over.
+            if a != b && (last || rng.gen_bool(join)) {
+                graph.carve(square.cell(x, y), east);
+                for set in sets.iter_mut().filter(|s| **s == b) {
+                    *set = a;
+                }
+            }
+        }
+        if last {
+            break;
+        }
+
+        let mut below = vec![None; square.width];
+        let mut members = sets.iter().cloned().enumerate().collect::<Vec<_>>();
+        members.sort_by_key(|&(_, set)| set);
+        for group in members.chunk_by(|a, b| a.1 == b.1) {
+            let mut dropped = false;
+            for &(x, set) in group {
+                if rng.gen_bool(drop) {
+                    graph.carve(square.cell(x, y), south);
+                    below[x] = set;
+                    dropped = true;
+                }
+            }
+            // Every set needs a way down, or it would be cut off.
+            if !dropped {
+                let &(x, set) = group.choose(rng).expect("groups aren't empty");
+                graph.carve(square.cell(x, y), south);
+                below[x] = set;
+            }
+        }
+        sets = below;
+    }
+    Ok(())
+}
+
+fn growing_tree<F: Fn(Direction) -> u32, R: Rng>(
+    graph: &mut Graph,
+    newest: f64,
+    weight: F,
+    rng: &mut R,
+) -> Result<(), WeightedError> {
+    let start = graph.random_cell(rng);
+    graph.visit(start);
+    let mut active = vec![start];
+    while !active.is_empty() {
+        let index = match rng.gen_bool(newest) {
+            true => active.len() - 1,
+            false => rng.gen_range(0, active.len()),
+        };
+        let cell = active[index];
+        let possible = options(graph, cell)
+            .into_iter()
+            .filter(|&(_, neighbor, _)| !graph.visited(neighbor))
+            .collect::<Vec<_>>();
+        if possible.is_empty() {
+            active.remove(index);
+            continue;
+        }
+        let &(side, _, _) = possible.choose_weighted(rng, |o| weight(o.2))?;
+        active.push(graph.carve(cell, side));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    /// Whether every cell can be reached from every other one without
+    /// crossing a wall, and there's only one way to do it.
+    fn is_perfect(graph: &Graph) -> bool {
+        let mut seen = vec![false; graph.len()];
+        let mut stack = vec![0];
+        let mut passages = 0;
+        while let Some(cell) = stack.pop() {
+            if seen[cell] {
+                continue;
+            }
+            seen[cell] = true;
+            for next in graph.passages(cell) {
+                passages += 1;
+                stack.push(next);
+            }
+        }
+        // Each passage was counted from both ends.
+        seen.iter().all(|&s| s) && passages / 2 == graph.len() - 1
+    }
+
+    #[test]
+    fn test_algorithms_make_perfect_mazes() {
+        let algorithms = [
+            Algorithm::Backtracker,
+            Algorithm::Kruskal,
+            Algorithm::Prim,
+            Algorithm::Wilson,
+            Algorithm::Eller,
+            Algorithm::AldousBroder,
+            Algorithm::BinaryTree,
+            Algorithm::Sidewinder,
+            Algorithm::GrowingTree,
+        ];
+        let weight = |d| match d {
+            Direction::East => 30,
+            _ => 10,
+        };
+        for &algorithm in algorithms.iter() {
+            let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+            let mut graph = Graph::square(13, 9);
+            carve(algorithm, 0.5, weight, &mut graph, &mut rng).unwrap();
+            assert!(is_perfect(&graph), "{:?}", algorithm);
+        }
+    }
+}
@@ -0,0 +1,89 @@
+//! Reshaping a carved maze by its dead ends.
+
+use super::grid::Graph;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+fn dead_ends(graph: &Graph) -> Vec<usize> {
+    (0..graph.len())
+        .filter(|&cell| !graph.cell(cell).removed && graph.cell(cell).open_sides() == 1)
+        .collect()
+}
+
+/// Knocks down a wall in `amount` of the dead ends, from `0.0` to
+/// `1.0`, which adds loops to the maze.  Where it can, the wall that's
+/// knocked down leads into another dead end, so that one wall gets rid
+/// of two of them.
+pub fn braid<R: Rng>(graph: &mut Graph, amount: f64, rng: &mut R) {
+    let mut cells = dead_ends(graph);
+    cells.shuffle(rng);
+    for cell in cells {
+        // An earlier wall might have already opened this one up.
+        if graph.cell(cell).open_sides() != 1 || !rng.gen_bool(amount) {
+            continue;
+        }
+        let closed = graph
+            .neighbors(cell)
+            .filter(|&(side, neighbor)| {
+                !graph.cell(cell).sides[side].open && !graph.cell(neighbor).removed
+            })
+            .collect::<Vec<_>>();
+        let into_dead_end = closed
+            .iter()
+            .filter(|&&(_, neighbor)| graph.cell(neighbor).open_sides() == 1)
+            .cloned()
+            .collect::<Vec<_>>();
+        let options = match into_dead_end.is_empty() {
+            true => closed,
+            false => into_dead_end,
+        };
+        if let Some(&(side, _)) = options.choose(rng) {
+            graph.open(cell, side);
+        }
+    }
+}
+
+/// Removes `amount` of the cells, from `0.0` to `1.0`, by cutting off
+/// dead ends until enough are gone.  Each dead end that's cut off can
+/// leave a new one behind, so the maze shrinks back from its ends.
+/// The cells in `keep` are never removed; since a path between them
+/// has no dead ends, it's never cut either.
+pub fn cull<R: Rng>(graph: &mut Graph, amount: f64, keep: &[usize], rng: &mut R) {
+    let mut remaining = (amount * graph.len() as f64).round() as usize;
+    while remaining > 0 {
+        let mut cells = dead_ends(graph);
+        cells.retain(|cell| !keep.contains(cell));
+        if cells.is_empty() {
+            break;
+        }
+        cells.shuffle(rng);
+        for &cell in cells.iter().take(remaining) {
+            graph.remove(cell);
+        }
+        remaining = remaining.saturating_sub(cells.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::maze::{algorithm, solve, Algorithm};
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_braid_and_cull() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(5);
+        let mut graph = Graph::square(12, 8);
+        algorithm::carve(Algorithm::Backtracker, 0.5, |_| 1, &mut graph, &mut rng).unwrap();
+        braid(&mut graph, 1.0, &mut rng);
+        assert!(dead_ends(&graph).is_empty());
+
+        let mut graph = Graph::square(12, 8);
+        algorithm::carve(Algorithm::Kruskal, 0.5, |_| 1, &mut graph, &mut rng).unwrap();
+        let keep = [0, 95];
+        cull(&mut graph, 0.5, &keep, &mut rng);
+        let removed = graph.cells().iter().filter(|c| c.removed).count();
+        assert_eq!(removed, 48);
+        assert!(solve::solve(&graph, keep[0], keep[1]).is_some());
+    }
+}
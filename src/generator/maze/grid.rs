@@ -0,0 +1,231 @@
+//! The cells of a maze, whatever their shape, and the walls between
+//! them.
+
+use super::{Cell, Direction};
+use rand::Rng;
+use std::ops::Range;
+
+/// A wall of a cell.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Side {
+    /// The cell on the other side of the wall, or `None` for a wall on
+    /// the edge of the maze.
+    pub neighbor: Option<usize>,
+    /// The compass direction that the wall faces most, which is what
+    /// the direction weights and openings go by.
+    pub facing: Direction,
+    pub open: bool,
+}
+
+/// A cell of a maze.
+#[derive(Debug, Clone, Default)]
+pub struct Node {
+    /// The walls of the cell, in order around it.
+    pub sides: Vec<Side>,
+    visit: bool,
+    /// Whether the cell was taken out of the maze, and left as open
+    /// space.
+    pub removed: bool,
+}
+
+impl Node {
+    fn new(sides: Vec<Side>) -> Self {
+        Node {
+            sides,
+            ..Node::default()
+        }
+    }
+
+    /// The number of open walls, including any out of the maze.
+    pub fn open_sides(&self) -> usize {
+        self.sides.iter().filter(|side| side.open).count()
+    }
+}
+
+/// The cells of a maze, numbered row by row.  Rows can have different
+/// numbers of cells, like the rings of a circular maze.
+#[derive(Debug, Clone)]
+pub struct Graph {
+    cells: Vec<Node>,
+    /// The number of the first cell in each row, and then the number
+    /// of cells.
+    starts: Vec<usize>,
+}
+
+impl Graph {
+    /// A graph of rows of cells, given the walls of each cell in each
+    /// row.
+    pub fn new(rows: Vec<Vec<Vec<Side>>>) -> Self {
+        let mut starts = vec![0];
+        let mut cells = Vec::new();
+        for row in rows {
+            cells.extend(row.into_iter().map(Node::new));
+            starts.push(cells.len());
+        }
+        Graph { cells, starts }
+    }
+
+    /// A rectangular grid, with each cell's walls in the order north,
+    /// east, south, west.
+    pub fn square(width: usize, height: usize) -> Self {
+        let rows = (0..height)
+            .map(|y| {
+                (0..width)
+                    .map(|x| {
+                        Direction::all()
+                            .iter()
+                            .map(|&d| Side {
+                                neighbor: d
+                                    .vector(x, y, width, height)
+                                    .map(|v| v.y() * width + v.x()),
+                                facing: d,
+                                open: false,
+                            })
+                            .collect()
+                    })
+                    .collect()
+            })
+            .collect();
+        Graph::new(rows)
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn cell(&self, cell: usize) -> &Node {
+        &self.cells[cell]
+    }
+
+    pub fn cells(&self) -> &[Node] {
+        &self.cells
+    }
+
+    pub fn rows(&self) -> usize {
+        self.starts.len() - 1
+    }
+
+    /// The numbers of the cells in a row.
+    pub fn row(&self, row: usize) -> Range<usize> {
+        self.starts[row]..self.starts[row + 1]
+    }
+
+    /// The cells on the other side of each wall of a cell, along with
+    /// the walls.
+    pub fn neighbors(&self, cell: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.cells[cell]
+            .sides
+            .iter()
+            .enumerate()
+            .flat_map(|(side, s)| s.neighbor.map(|n| (side, n)))
+    }
+
+    /// The cells next to a cell that there's a passage to.  Openings
+    /// out of the maze are skipped.
+    pub fn passages(&self, cell: usize) -> impl Iterator<Item = usize> + '_ {
+        self.cells[cell]
+            .sides
+            .iter()
+            .filter(|side| side.open)
+            .flat_map(|side| side.neighbor)
+    }
+
+    pub fn visited(&self, cell: usize) -> bool {
+        self.cells[cell].visit
+    }
+
+    pub fn visit(&mut self, cell: usize) {
+        self.cells[cell].visit = true;
+    }
+
+    /// The wall of `cell` that it shares with `neighbor`.
+    fn side_towards(&self, cell: usize, neighbor: usize) -> usize {
+        self.cells[cell]
+            .sides
+            .iter()
+            .position(|side| side.neighbor == Some(neighbor))
+            .expect("neighbors share a wall")
+    }
+
+    /// Opens a wall of a cell, from both sides.
+    pub fn open(&mut self, cell: usize, side: usize) {
+        self.cells[cell].sides[side].open = true;
+        if let Some(neighbor) = self.cells[cell].sides[side].neighbor {
+            let back = self.side_towards(neighbor, cell);
+            self.cells[neighbor].sides[back].open = true;
+        }
+    }
+
+    /// Opens the wall between a cell and the one on its `side`, and
+    /// marks both as part of the maze.  Returns the other cell.
+    pub fn carve(&mut self, cell: usize, side: usize) -> usize {
+        let neighbor = self.cells[cell].sides[side]
+            .neighbor
+            .expect("mazes are only carved between cells in the grid");
+        self.open(cell, side);
+        self.cells[cell].visit = true;
+        self.cells[neighbor].visit = true;
+        neighbor
+    }
+
+    /// Takes a cell out of the maze, closing all of its walls.
+    pub fn remove(&mut self, cell: usize) {
+        for side in 0..self.cells[cell].sides.len() {
+            let wall = &mut self.cells[cell].sides[side];
+            wall.open = false;
+            if let Some(neighbor) = wall.neighbor {
+                let back = self.side_towards(neighbor, cell);
+                self.cells[neighbor].sides[back].open = false;
+            }
+        }
+        self.cells[cell].removed = true;
+    }
+
+    pub fn random_cell<R: Rng>(&self, rng: &mut R) -> usize {
+        rng.gen_range(0, self.cells.len())
+    }
+
+    /// The cells of a graph made by [`Graph::square`], as rows of
+    /// cells with their walls by direction.
+    pub fn to_square(&self) -> Vec<Vec<Cell>> {
+        (0..self.rows())
+            .map(|row| {
+                self.row(row)
+                    .map(|cell| {
+                        let node = &self.cells[cell];
+                        let mut cell = Cell {
+                            removed: node.removed,
+                            ..Cell::default()
+                        };
+                        for side in node.sides.iter().filter(|side| side.open) {
+                            side.facing.mark(&mut cell);
+                        }
+                        cell
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_square() {
+        let mut graph = Graph::square(3, 2);
+        assert_eq!(graph.len(), 6);
+        assert_eq!(graph.row(1), 3..6);
+        let neighbors = graph.neighbors(4).map(|(_, n)| n).collect::<Vec<_>>();
+        assert_eq!(neighbors, vec![1, 5, 3]);
+        assert_eq!(graph.carve(4, 0), 1);
+        assert_eq!(graph.passages(1).collect::<Vec<_>>(), vec![4]);
+        let cells = graph.to_square();
+        assert!(cells[1][1].north && cells[0][1].south && !cells[0][1].north);
+    }
+}
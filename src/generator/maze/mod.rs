@@ -0,0 +1,1195 @@
+mod algorithm;
+mod dead_ends;
+pub mod grid;
+pub mod shape;
+pub mod solve;
+
+pub use self::algorithm::Algorithm;
+pub use self::shape::Shape;
+
+use self::grid::Graph;
+use self::shape::{Point, Tiling};
+use super::coverage::{self, Coverage};
+use crate::context::Context;
+use crate::error::{Error, Result};
+use crate::gradient::Gradient;
+use crate::position::{Position, Region};
+use crate::svg::Document;
+use rand::distributions::WeightedError;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub struct MazeGenerator {
+    /// The size of the box, in pixels.  This will be colored in the
+    /// block color when picked.
+    pub cell_size: Position,
+    pub wall_size: Position,
+    /// The size of the image, in pixels.
+    pub image_size: Position,
+    /// The padding on all sides of the image.  The `x` component is
+    /// for both the left and right, and the `y` component is for the
+    /// top and bottom.
+    pub padding: Position,
+
+    pub background_color: image::Rgb<u8>,
+    pub block_color: image::Rgb<u8>,
+
+    pub direction_weights: HashMap<Direction, u32>,
+    pub default_weight: u32,
+    pub algorithm: Algorithm,
+    /// How often the growing tree algorithm carves from the newest
+    /// cell instead of a random one, from `0.0` to `1.0`.
+    pub newest: f64,
+    /// The shape of the cells.  Only square mazes can be carved by
+    /// the algorithms that go by rows and columns.
+    pub shape: Shape,
+
+    pub entrance: Opening,
+    pub exit: Opening,
+    /// How many of the dead ends have a wall knocked down, which adds
+    /// loops to the maze, from `0.0` to `1.0`.
+    pub braid: f64,
+    /// How many of the cells are removed, from `0.0` to `1.0`.  Cells
+    /// are removed by cutting back dead ends, which leaves open space
+    /// around the rest of the maze.
+    pub sparseness: f64,
+    /// Draws the shortest path from the entrance to the exit.
+    pub solution: Option<Solution>,
+    /// Colors the open space of the maze by how far each cell is from
+    /// the entrance, from the entrance at `0.0` to the furthest cell
+    /// at `1.0`.
+    pub heatmap: Option<Gradient>,
+    /// Smooths the edges of the solution line, and of the cells of
+    /// mazes that aren't square.  The walls of square mazes always
+    /// line up with the pixels, and those of other shapes are always
+    /// smooth.
+    pub antialias: bool,
+}
+
+/// A cell that the maze is entered or left through.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Opening {
+    /// The column and row of the cell, counted from the top left.
+    /// Negative numbers count back from the right and bottom, so
+    /// `[-1, -1]` is the bottom right cell.  In a theta maze the rows
+    /// are rings, so `[0, 0]` is the cell in the middle.
+    pub cell: [i64; 2],
+    /// The outer wall of the cell that is opened.  Defaults to the
+    /// first of the north, south, west and east walls that's on the
+    /// edge of the maze.  A cell in the middle of the maze has no
+    /// outer walls, and isn't opened.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub side: Option<Direction>,
+}
+
+impl Opening {
+    pub fn new(cell: [i64; 2], side: Option<Direction>) -> Self {
+        Opening { cell, side }
+    }
+
+    /// The cell and the wall of it that's opened.
+    fn resolve(self, graph: &Graph, field: &'static str) -> Result<(usize, Option<usize>)> {
+        let index = |i: i64, count: usize| {
+            let count = count as i64;
+            match i {
+                _ if i >= count || i < -count => None,
+                _ if i < 0 => Some((count + i) as usize),
+                _ => Some(i as usize),
+            }
+        };
+        let rows = graph.rows();
+        let cell = index(self.cell[1], rows).and_then(|row| {
+            let cells = graph.row(row);
+            index(self.cell[0], cells.len()).map(|column| cells.start + column)
+        });
+        let cell = match cell {
+            Some(cell) => cell,
+            None => {
+                let uniform = (0..rows).all(|row| graph.row(row).len() == graph.row(0).len());
+                let reason = match uniform {
+                    true => format!(
+                        "[{}, {}] is not a cell in a maze of {} by {} cells",
+                        self.cell[0],
+                        self.cell[1],
+                        graph.row(0).len(),
+                        rows
+                    ),
+                    false => format!(
+                        "[{}, {}] is not a cell in a maze of {} rows, with {} cells in the last",
+                        self.cell[0],
+                        self.cell[1],
+                        rows,
+                        graph.row(rows - 1).len()
+                    ),
+                };
+                return Err(Error::parameter("maze", field, reason));
+            }
+        };
+        let outer = |d: Direction| {
+            graph
+                .cell(cell)
+                .sides
+                .iter()
+                .position(|side| side.neighbor.is_none() && side.facing == d)
+        };
+        let side = match self.side {
+            Some(side) => match outer(side) {
+                Some(side) => Some(side),
+                None => {
+                    return Err(Error::parameter(
+                        "maze",
+                        field,
+                        format!(
+                            "the {:?} wall of the cell is not on the edge of the maze",
+                            side
+                        ),
+                    ))
+                }
+            },
+            None => [
+                Direction::North,
+                Direction::South,
+                Direction::West,
+                Direction::East,
+            ]
+            .iter()
+            .find_map(|&d| outer(d)),
+        };
+        Ok((cell, side))
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Solution {
+    pub color: image::Rgb<u8>,
+    pub style: SolutionStyle,
+    /// The width of the line, in pixels.
+    pub width: f32,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SolutionStyle {
+    /// A line through the middle of the cells on the path.
+    #[default]
+    Line,
+    /// Fills in the cells on the path, and the openings between them.
+    Cells,
+}
+
+/// A cell, and the wall of it that's opened, if any.
+type Door = (usize, Option<usize>);
+
+impl super::Generator<image::Rgb<u8>> for MazeGenerator {
+    fn generate(&self, context: &Context) -> Result<image::RgbImage> {
+        self.check_shape()?;
+        match self.shape {
+            Shape::Square => self.generate_square(context),
+            shape => self.generate_tiled(shape, context),
+        }
+    }
+}
+
+impl MazeGenerator {
+    fn check_shape(&self) -> Result<()> {
+        if self.shape != Shape::Square && self.algorithm.is_square_only() {
+            return Err(Error::parameter(
+                "maze",
+                "algorithm",
+                format!("{:?} only works on square mazes", self.algorithm),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Carves a maze out of a graph of closed cells, opens up its
+    /// entrance and exit, and then braids and culls it.  Returns the
+    /// entrance and the exit.
+    fn carve<R: Rng>(&self, graph: &mut Graph, rng: &mut R) -> Result<[Door; 2]> {
+        let amounts = [
+            ("newest", self.newest),
+            ("braid", self.braid),
+            ("sparseness", self.sparseness),
+        ];
+        for &(field, amount) in amounts.iter() {
+            if !(0.0..=1.0).contains(&amount) {
+                return Err(Error::parameter(
+                    "maze",
+                    field,
+                    format!("must be between 0.0 and 1.0, not {}", amount),
+                ));
+            }
+        }
+
+        generate_maze(
+            self.algorithm,
+            self.newest,
+            |d| {
+                self.direction_weights
+                    .get(&d)
+                    .cloned()
+                    .unwrap_or(self.default_weight)
+            },
+            graph,
+            rng,
+        )
+        .map_err(|_| {
+            Error::parameter(
+                "maze",
+                "direction_weights",
+                "at least one direction out of every cell must have a weight above 0",
+            )
+        })?;
+
+        let entrance = self.entrance.resolve(graph, "entrance")?;
+        let exit = self.exit.resolve(graph, "exit")?;
+        for &(cell, side) in [entrance, exit].iter() {
+            if let Some(side) = side {
+                graph.open(cell, side);
+            }
+        }
+        if self.braid > 0.0 {
+            dead_ends::braid(graph, self.braid, rng);
+        }
+        if self.sparseness > 0.0 {
+            dead_ends::cull(graph, self.sparseness, &[entrance.0, exit.0], rng);
+        }
+        Ok([entrance, exit])
+    }
+
+    /// Carves a square maze and works out how to color it.
+    fn square(&self, context: &Context) -> Result<SquareMaze> {
+        let block_size = self.cell_size + self.wall_size;
+        if block_size.is_empty() {
+            return Err(Error::parameter(
+                "maze",
+                "cell_size",
+                "cells and walls together must be at least 1 pixel in each direction",
+            ));
+        }
+        let available = self
+            .image_size
+            .checked_sub(self.padding * 2 + self.wall_size)
+            .ok_or_else(|| {
+                Error::parameter(
+                    "maze",
+                    "padding",
+                    format!(
+                        "{} on each side does not leave room for a maze in an image of {}",
+                        self.padding, self.image_size
+                    ),
+                )
+            })?;
+        let center_adjustment: Position = available % block_size;
+        let inner_region = Region::new(
+            self.padding + center_adjustment / 2,
+            self.image_size - self.padding - center_adjustment / 2,
+        )
+        .expect("the padding was checked to fit in the image");
+
+        let blocks_count: Position = inner_region.dimensions() / block_size;
+        if blocks_count.is_empty() {
+            return Err(Error::parameter(
+                "maze",
+                "cell_size",
+                format!(
+                    "cells of {} with walls of {} do not fit in an image of {}",
+                    self.cell_size, self.wall_size, self.image_size
+                ),
+            ));
+        }
+
+        let (columns, rows) = (blocks_count.x() as usize, blocks_count.y() as usize);
+        let mut graph = Graph::square(columns, rows);
+        let [entrance, exit] = self.carve(&mut graph, &mut context.rng())?;
+        let grid = graph.to_square();
+        let at = |cell: usize| (cell % columns, cell / columns);
+        let door = |(cell, side): Door| (at(cell), side.map(|s| graph.cell(cell).sides[s].facing));
+
+        let layout = Layout {
+            origin: self.padding + center_adjustment / 2,
+            block_size,
+            wall_size: self.wall_size,
+            region: inner_region,
+        };
+        let heatmap = self.heatmap.as_ref().map(|_| {
+            let distances = solve::distances(&graph, entrance.0);
+            let furthest = distances.iter().flatten().max().cloned();
+            (distances, furthest.unwrap_or(0).max(1) as f32)
+        });
+        let path = match self.solution {
+            Some(_) => solve::solve(&graph, entrance.0, exit.0).unwrap_or_default(),
+            None => Vec::new(),
+        };
+        let on_path = {
+            let mut on_path = vec![vec![false; columns]; rows];
+            for (x, y) in path.iter().map(|&cell| at(cell)) {
+                on_path[y][x] = true;
+            }
+            on_path
+        };
+
+        let mut line = Vec::new();
+        if let Some(solution) = self.solution.as_ref() {
+            if solution.style == SolutionStyle::Line {
+                line = path
+                    .iter()
+                    .map(|&cell| layout.center(at(cell)))
+                    .collect::<Vec<_>>();
+                // The line runs out through the openings, to the edge
+                // of the maze.
+                if let (Some(first), (cell, Some(side))) = (line.first().cloned(), door(entrance)) {
+                    line.insert(0, layout.outside(cell, side, first));
+                }
+                if let (Some(last), (cell, Some(side))) = (line.last().cloned(), door(exit)) {
+                    line.push(layout.outside(cell, side, last));
+                }
+            }
+        }
+        Ok(SquareMaze {
+            grid,
+            layout,
+            heatmap,
+            on_path,
+            line,
+        })
+    }
+
+    /// The color of a part of a square maze.
+    fn color(&self, maze: &SquareMaze, part: Part) -> image::Rgb<u8> {
+        if let Some(solution) = self.solution.as_ref() {
+            if solution.style == SolutionStyle::Cells && part.is_on(&maze.on_path) {
+                return solution.color;
+            }
+        }
+        match (part, self.heatmap.as_ref().zip(maze.heatmap.as_ref())) {
+            (Part::Outside, _) => self.background_color,
+            (Part::Wall, _) => self.block_color,
+            (_, None) => self.background_color,
+            (part, Some((gradient, (distances, furthest)))) => {
+                let (columns, rows) = (maze.grid[0].len(), maze.grid.len());
+                let distance = part
+                    .cells(columns, rows)
+                    .iter()
+                    .flat_map(|&(x, y)| distances[y * columns + x])
+                    .min()
+                    .unwrap_or(0);
+                gradient.at(distance as f32 / furthest)
+            }
+        }
+    }
+
+    fn generate_square(&self, context: &Context) -> Result<image::RgbImage> {
+        let maze = self.square(context)?;
+        let (width, height) = (self.image_size.x(), self.image_size.y());
+        let mut image = crate::rows::from_fn(width, height, |x, y| {
+            self.color(&maze, maze.layout.part(&maze.grid, Position::new(x, y)))
+        });
+        if let Some(solution) = self.solution.as_ref() {
+            if self.antialias {
+                // The line is laid out by the corners of pixels, and
+                // strokes go by their middles.
+                let mut line = Coverage::new(width, height);
+                for pair in maze.line.windows(2) {
+                    let [from, to] = [pair[0], pair[1]].map(|[x, y]| [x - 0.5, y - 0.5]);
+                    line.stroke(from, to, solution.width);
+                }
+                line.paint(&mut image, opaque(solution.color));
+            } else {
+                for pair in maze.line.windows(2) {
+                    draw_line(&mut image, pair[0], pair[1], solution.width, solution.color);
+                }
+            }
+        }
+        Ok(image)
+    }
+
+    /// Carves a maze of any other shape, and works out what to fill
+    /// and what to draw.
+    fn tiled(&self, shape: Shape, context: &Context) -> Result<TiledMaze> {
+        let wall = self.wall_size.x().min(self.wall_size.y()) as f32;
+        let block = self.cell_size + self.wall_size;
+        // The walls around the edge reach out past the cells by half
+        // of their width.
+        let margin = [
+            self.padding.x() as f32 + wall / 2.0,
+            self.padding.y() as f32 + wall / 2.0,
+        ];
+        let space = [
+            self.image_size.x() as f32 - margin[0] * 2.0,
+            self.image_size.y() as f32 - margin[1] * 2.0,
+        ];
+        let block = [block.x() as f32, block.y() as f32];
+        let mut tiling = Tiling::new(shape, block, margin, space).ok_or_else(|| {
+            Error::parameter(
+                "maze",
+                "cell_size",
+                format!(
+                    "cells of {} with walls of {} do not fit in an image of {}",
+                    self.cell_size, self.wall_size, self.image_size
+                ),
+            )
+        })?;
+        let [entrance, exit] = self.carve(&mut tiling.graph, &mut context.rng())?;
+        let graph = &tiling.graph;
+        let path = match self.solution {
+            Some(_) => solve::solve(graph, entrance.0, exit.0).unwrap_or_default(),
+            None => Vec::new(),
+        };
+
+        // The color of each cell, if it's any different from the
+        // background.
+        let mut fills = vec![None; graph.len()];
+        if let Some(gradient) = self.heatmap.as_ref() {
+            let distances = solve::distances(graph, entrance.0);
+            let furthest = distances
+                .iter()
+                .flatten()
+                .max()
+                .cloned()
+                .unwrap_or(0)
+                .max(1) as f32;
+            for (fill, distance) in fills.iter_mut().zip(distances) {
+                *fill = distance.map(|d| gradient.at(d as f32 / furthest));
+            }
+        }
+        if let Some(solution) = self.solution {
+            if solution.style == SolutionStyle::Cells {
+                for &cell in path.iter() {
+                    fills[cell] = Some(solution.color);
+                }
+            }
+        }
+
+        // Each wall is drawn once, from the first of the cells on
+        // either side of it, unless both of them were removed.
+        let mut walls = Vec::new();
+        for (cell, node) in graph.cells().iter().enumerate() {
+            for (side, line) in node.sides.iter().zip(tiling.walls[cell].iter()) {
+                let drawn = match side.neighbor {
+                    _ if side.open => false,
+                    None => !node.removed,
+                    Some(next) => next > cell && !(node.removed && graph.cell(next).removed),
+                };
+                if drawn && wall > 0.0 {
+                    walls.push(line.clone());
+                }
+            }
+        }
+
+        let mut line = Vec::new();
+        if let Some(solution) = self.solution {
+            if solution.style == SolutionStyle::Line {
+                line = path
+                    .iter()
+                    .map(|&cell| tiling.centers[cell])
+                    .collect::<Vec<_>>();
+                // The line runs out through the openings, past the
+                // walls.
+                if let (false, (cell, Some(side))) = (line.is_empty(), entrance) {
+                    line.insert(0, tiling.outside(cell, side, wall));
+                }
+                if let (false, (cell, Some(side))) = (line.is_empty(), exit) {
+                    line.push(tiling.outside(cell, side, wall));
+                }
+            }
+        }
+        Ok(TiledMaze {
+            fills: fills
+                .into_iter()
+                .enumerate()
+                .flat_map(|(cell, fill)| fill.map(|color| (tiling.outline(cell), color)))
+                .collect(),
+            walls,
+            wall,
+            line,
+        })
+    }
+
+    fn generate_tiled(&self, shape: Shape, context: &Context) -> Result<image::RgbImage> {
+        let maze = self.tiled(shape, context)?;
+        let (width, height) = (self.image_size.x(), self.image_size.y());
+        let mut image = image::RgbImage::from_pixel(width, height, self.background_color);
+        if self.antialias {
+            fill_smooth(&mut image, &maze.fills, self.background_color);
+        } else {
+            for (outline, color) in maze.fills.iter() {
+                fill_polygon(&mut image, outline, *color);
+            }
+        }
+
+        let mut walls = Coverage::new(width, height);
+        for line in maze.walls.iter() {
+            for pair in line.windows(2) {
+                walls.stroke(pair[0], pair[1], maze.wall);
+            }
+        }
+        walls.paint(&mut image, opaque(self.block_color));
+
+        if let Some(solution) = self.solution {
+            let mut line = Coverage::new(width, height);
+            for pair in maze.line.windows(2) {
+                line.stroke(pair[0], pair[1], solution.width);
+            }
+            line.paint(&mut image, opaque(solution.color));
+        }
+        Ok(image)
+    }
+}
+
+impl super::Scaled for MazeGenerator {
+    fn scaled(self, factor: u32) -> Self {
+        MazeGenerator {
+            cell_size: self.cell_size * factor,
+            wall_size: self.wall_size * factor,
+            image_size: self.image_size * factor,
+            padding: self.padding * factor,
+            solution: self.solution.map(|solution| Solution {
+                width: solution.width * factor as f32,
+                ..solution
+            }),
+            ..self
+        }
+    }
+}
+
+impl super::VectorGenerator for MazeGenerator {
+    fn generate_svg(&self, context: &Context) -> Result<Document> {
+        self.check_shape()?;
+        let (width, height) = (self.image_size.x(), self.image_size.y());
+        let mut document = Document::new(width, height, self.background_color);
+        match self.shape {
+            Shape::Square => {
+                let maze = self.square(context)?;
+                // Every pixel in each of the posts, walls and open
+                // spaces of a block is the same part of the maze, so
+                // the blocks are drawn a piece at a time.
+                for (left, right) in maze.layout.pieces(0, width) {
+                    for (top, bottom) in maze.layout.pieces(1, height) {
+                        let part = maze.layout.part(&maze.grid, Position::new(left, top));
+                        let color = self.color(&maze, part);
+                        if color != self.background_color {
+                            document.rect(
+                                [left as f32, top as f32],
+                                [(right - left) as f32, (bottom - top) as f32],
+                                color,
+                            );
+                        }
+                    }
+                }
+                match self.solution.as_ref() {
+                    Some(solution) if self.antialias => {
+                        document.polyline(&maze.line, solution.width, solution.color);
+                    }
+                    // Without antialiasing, the line is drawn the same
+                    // as in the image: a rectangle per segment, with
+                    // square ends.
+                    Some(solution) => {
+                        let half = solution.width / 2.0;
+                        for pair in maze.line.windows(2) {
+                            let (from, to) = (pair[0], pair[1]);
+                            let low = [from[0].min(to[0]) - half, from[1].min(to[1]) - half];
+                            let high = [from[0].max(to[0]) + half, from[1].max(to[1]) + half];
+                            let low = low.map(|v| v.round().max(0.0));
+                            document.rect(
+                                low,
+                                [high[0].round() - low[0], high[1].round() - low[1]],
+                                solution.color,
+                            );
+                        }
+                    }
+                    None => {}
+                }
+            }
+            shape => {
+                let maze = self.tiled(shape, context)?;
+                // The tiling is laid out around the middles of the
+                // pixels, which are half a pixel in from their corners.
+                document.group([0.5, 0.5], |document| {
+                    for (outline, color) in maze.fills.iter() {
+                        document.polygon(outline, *color);
+                    }
+                    for line in maze.walls.iter() {
+                        document.polyline(line, maze.wall, self.block_color);
+                    }
+                    if let Some(solution) = self.solution {
+                        document.polyline(&maze.line, solution.width, solution.color);
+                    }
+                });
+            }
+        }
+        Ok(document)
+    }
+}
+
+/// A square maze, carved and laid out.
+struct SquareMaze {
+    grid: Vec<Vec<Cell>>,
+    layout: Layout,
+    /// How far each cell is from the entrance, and the furthest that
+    /// any cell is, when there's a heatmap.
+    heatmap: Option<(Vec<Option<u32>>, f32)>,
+    on_path: Vec<Vec<bool>>,
+    /// The points that the solution is drawn through, when it's drawn
+    /// as a line.
+    line: Vec<[f32; 2]>,
+}
+
+/// A maze of any other shape, carved and laid out.
+struct TiledMaze {
+    /// The outlines of the cells that aren't the background color, and
+    /// their colors.
+    fills: Vec<(Vec<Point>, image::Rgb<u8>)>,
+    /// The walls that are drawn, and how wide they are.
+    walls: Vec<Vec<Point>>,
+    wall: f32,
+    /// The points that the solution is drawn through, when it's drawn
+    /// as a line.
+    line: Vec<Point>,
+}
+
+fn opaque(color: image::Rgb<u8>) -> image::Rgba<u8> {
+    image::Rgba([color[0], color[1], color[2], 0xff])
+}
+
+/// Fills in the pixels whose middles are inside a polygon.
+fn fill_polygon(image: &mut image::RgbImage, polygon: &[Point], color: image::Rgb<u8>) {
+    let (width, height) = (image.width() as f32, image.height() as f32);
+    let (mut low, mut high) = ([f32::INFINITY; 2], [f32::NEG_INFINITY; 2]);
+    for point in polygon {
+        for axis in 0..2 {
+            low[axis] = low[axis].min(point[axis]);
+            high[axis] = high[axis].max(point[axis]);
+        }
+    }
+    let (left, top) = (
+        low[0].floor().max(0.0) as u32,
+        low[1].floor().max(0.0) as u32,
+    );
+    let (right, bottom) = (
+        high[0].ceil().min(width - 1.0),
+        high[1].ceil().min(height - 1.0),
+    );
+    if right < 0.0 || bottom < 0.0 {
+        return;
+    }
+    for y in top..=bottom as u32 {
+        for x in left..=right as u32 {
+            if shape::contains(polygon, [x as f32, y as f32]) {
+                image.put_pixel(x, y, color);
+            }
+        }
+    }
+}
+
+/// Fills in polygons that don't overlap over a background, by how
+/// much of each pixel they cover.  Pixels are sampled on a grid, which
+/// is plenty for edges that are mostly hidden under walls.
+fn fill_smooth(
+    image: &mut image::RgbImage,
+    fills: &[(Vec<Point>, image::Rgb<u8>)],
+    background: image::Rgb<u8>,
+) {
+    const SAMPLES: u32 = 4;
+    let (width, height) = (image.width(), image.height());
+    // The parts of the pixels on the edges of the polygons, which are
+    // mixed together once every polygon has been sampled.
+    let mut edges = HashMap::new();
+    for (polygon, color) in fills.iter() {
+        let (mut low, mut high) = ([f32::INFINITY; 2], [f32::NEG_INFINITY; 2]);
+        for point in polygon {
+            for axis in 0..2 {
+                low[axis] = low[axis].min(point[axis]);
+                high[axis] = high[axis].max(point[axis]);
+            }
+        }
+        let left = (low[0] - 0.5).floor().max(0.0) as u32;
+        let top = (low[1] - 0.5).floor().max(0.0) as u32;
+        let right = ((high[0] + 1.5).max(0.0) as u32).min(width);
+        let bottom = ((high[1] + 1.5).max(0.0) as u32).min(height);
+        for y in top..bottom {
+            for x in left..right {
+                let inside = (0..SAMPLES * SAMPLES)
+                    .filter(|i| {
+                        let step = |i: u32| (i as f32 + 0.5) / SAMPLES as f32 - 0.5;
+                        let point = [x as f32 + step(i % SAMPLES), y as f32 + step(i / SAMPLES)];
+                        shape::contains(polygon, point)
+                    })
+                    .count();
+                match inside as u32 {
+                    0 => {}
+                    n if n == SAMPLES * SAMPLES => image.put_pixel(x, y, *color),
+                    n => {
+                        let covered = n as f32 / (SAMPLES * SAMPLES) as f32;
+                        edges
+                            .entry((x, y))
+                            .or_insert_with(Vec::new)
+                            .push((covered, *color));
+                    }
+                }
+            }
+        }
+    }
+    for ((x, y), parts) in edges {
+        image.put_pixel(x, y, coverage::mix(background, parts));
+    }
+}
+
+/// Where the cells of a maze are in the image.
+struct Layout {
+    /// The top left corner of the first cell's walls.
+    origin: Position,
+    block_size: Position,
+    wall_size: Position,
+    region: Region,
+}
+
+/// The parts of a maze that a pixel can be in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Part {
+    /// The padding around the maze.
+    Outside,
+    Wall,
+    /// The open space in a cell.
+    Cell(usize, usize),
+    /// An opening in the wall on the `Direction` side of a cell, either
+    /// to the next cell or out of the maze.
+    Gap(usize, usize, Direction),
+    /// The post at the top left corner of a cell, when every wall
+    /// that meets there is open.
+    Post(usize, usize),
+}
+
+impl Layout {
+    fn part(&self, grid: &[Vec<Cell>], pos: Position) -> Part {
+        if !self.region.contains(pos) {
+            return Part::Outside;
+        }
+        let block: Position = (pos - self.origin) / self.block_size;
+        let inside: Position = (pos - self.origin) % self.block_size;
+        let (x, y) = (block.x() as usize, block.y() as usize);
+        let in_wall = (
+            inside.x() < self.wall_size.x(),
+            inside.y() < self.wall_size.y(),
+        );
+        let (columns, rows) = (grid[0].len(), grid.len());
+
+        // Cells that were removed leave open space behind, along with
+        // any walls and posts that were only next to removed cells.
+        let part = Self::carved_part(grid, (x, y), in_wall);
+        let xs = match in_wall.0 && x > 0 {
+            true => x - 1..x + 1,
+            false => x..x + 1,
+        };
+        let ys = match in_wall.1 && y > 0 {
+            true => y - 1..y + 1,
+            false => y..y + 1,
+        };
+        let removed = ys
+            .filter(|&y| y < rows)
+            .flat_map(|y| xs.clone().filter(|&x| x < columns).map(move |x| (x, y)))
+            .all(|(x, y)| grid[y][x].removed);
+        match part {
+            Part::Cell(..) | Part::Wall | Part::Post(..) if removed => Part::Outside,
+            part => part,
+        }
+    }
+
+    /// The part of a carved maze that a pixel is in, without taking
+    /// removed cells into account.
+    fn carved_part(grid: &[Vec<Cell>], (x, y): (usize, usize), in_wall: (bool, bool)) -> Part {
+        let (columns, rows) = (grid[0].len(), grid.len());
+
+        // The walls along the right and bottom edges of the maze,
+        // which are past the last cells.  Their openings are as tall
+        // or wide as the whole block, posts included.
+        if x >= columns || y >= rows {
+            return match (x < columns, y < rows) {
+                (false, true) if grid[y][columns - 1].east => {
+                    Part::Gap(columns - 1, y, Direction::East)
+                }
+                (true, false) if grid[rows - 1][x].south => {
+                    Part::Gap(x, rows - 1, Direction::South)
+                }
+                _ => Part::Wall,
+            };
+        }
+
+        let cell = grid[y][x];
+        match in_wall {
+            (false, false) => Part::Cell(x, y),
+            (false, true) if cell.north => Part::Gap(x, y, Direction::North),
+            (true, false) if cell.west => Part::Gap(x, y, Direction::West),
+            (true, true)
+                if x > 0
+                    && y > 0
+                    && cell.north
+                    && cell.west
+                    && grid[y - 1][x].west
+                    && grid[y][x - 1].north =>
+            {
+                Part::Post(x, y)
+            }
+            _ => Part::Wall,
+        }
+    }
+
+    /// The spans of pixels along an axis of an image `size` pixels
+    /// long, `0` for across and `1` for down, that are each all in the
+    /// same walls or open spaces.
+    fn pieces(&self, axis: usize, size: u32) -> Vec<(u32, u32)> {
+        let along = |p: Position| [p.x(), p.y()][axis];
+        let (start, end) = (
+            along(self.region.top_left()),
+            along(self.region.bottom_right()),
+        );
+        let (block, wall) = (along(self.block_size), along(self.wall_size));
+        let mut edges = vec![0];
+        let mut edge = start;
+        while edge < end {
+            edges.push(edge);
+            edges.push((edge + wall).min(end));
+            edge += block;
+        }
+        edges.push(end);
+        edges.push(size);
+        edges
+            .windows(2)
+            .map(|pair| (pair[0], pair[1]))
+            .filter(|(from, to)| from < to)
+            .collect()
+    }
+
+    /// The middle of a cell's open space, in pixels.
+    fn center(&self, (x, y): (usize, usize)) -> [f32; 2] {
+        let cell = self.block_size - self.wall_size;
+        [
+            self.origin.x() as f32
+                + (x as u32 * self.block_size.x() + self.wall_size.x()) as f32
+                + cell.x() as f32 / 2.0,
+            self.origin.y() as f32
+                + (y as u32 * self.block_size.y() + self.wall_size.y()) as f32
+                + cell.y() as f32 / 2.0,
+        ]
+    }
+
+    /// The point just past the wall on the `side` of a cell whose
+    /// center is at `center`.
+    fn outside(&self, (x, y): (usize, usize), side: Direction, center: [f32; 2]) -> [f32; 2] {
+        let left = self.origin.x() as f32 + (x as u32 * self.block_size.x()) as f32;
+        let top = self.origin.y() as f32 + (y as u32 * self.block_size.y()) as f32;
+        match side {
+            Direction::North => [center[0], top],
+            Direction::West => [left, center[1]],
+            Direction::South => [
+                center[0],
+                top + (self.block_size.y() + self.wall_size.y()) as f32,
+            ],
+            Direction::East => [
+                left + (self.block_size.x() + self.wall_size.x()) as f32,
+                center[1],
+            ],
+        }
+    }
+}
+
+impl Part {
+    /// The cells that the part belongs to, for coloring it.
+    fn cells(self, columns: usize, rows: usize) -> Vec<(usize, usize)> {
+        match self {
+            Part::Outside | Part::Wall => Vec::new(),
+            Part::Cell(x, y) | Part::Post(x, y) => vec![(x, y)],
+            Part::Gap(x, y, side) => {
+                let mut cells = vec![(x, y)];
+                cells.extend(side.vector(x, y, columns, rows).map(|v| (v.x(), v.y())));
+                cells
+            }
+        }
+    }
+
+    /// Whether the part is on a path: either a cell on it, or an
+    /// opening that the path goes through.
+    fn is_on(self, path: &[Vec<bool>]) -> bool {
+        let (columns, rows) = (path[0].len(), path.len());
+        match self {
+            // A path through a gap covers the cells on both sides of
+            // it; a gap out of the maze only has the one.
+            Part::Cell(..) | Part::Gap(..) => {
+                self.cells(columns, rows).iter().all(|&(x, y)| path[y][x])
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Fills a line between two points that are in a row or a column,
+/// with square ends that reach past each point by half of the width.
+fn draw_line(
+    image: &mut image::RgbImage,
+    from: [f32; 2],
+    to: [f32; 2],
+    width: f32,
+    color: image::Rgb<u8>,
+) {
+    let half = width / 2.0;
+    let range = |a: f32, b: f32, limit: u32| {
+        let start = (a.min(b) - half).round().max(0.0) as u32;
+        let end = ((a.max(b) + half).round().max(0.0) as u32).min(limit);
+        start..end
+    };
+    for y in range(from[1], to[1], image.height()) {
+        for x in range(from[0], to[0], image.width()) {
+            image.put_pixel(x, y, color);
+        }
+    }
+}
+
+/// Carves a maze out of a graph of closed cells with the given
+/// algorithm.  Every wall on the edge of the maze is left closed.
+pub fn generate_maze<F: Fn(Direction) -> u32, R: Rng>(
+    algorithm: Algorithm,
+    newest: f64,
+    weight: F,
+    graph: &mut Graph,
+    rng: &mut R,
+) -> Result<(), WeightedError> {
+    algorithm::carve(algorithm, newest, weight, graph, rng)
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Cell {
+    north: bool,
+    east: bool,
+    south: bool,
+    west: bool,
+    /// Whether the cell was taken out of the maze, and left as open
+    /// space.
+    removed: bool,
+}
+
+impl Cell {
+    /// Whether the wall on the `direction` side of the cell is open.
+    pub fn is_open(&self, direction: Direction) -> bool {
+        match direction {
+            Direction::North => self.north,
+            Direction::East => self.east,
+            Direction::South => self.south,
+            Direction::West => self.west,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    North,
+    East,
+    South,
+    West,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+struct DirectionVector(Direction, usize, usize);
+
+impl DirectionVector {
+    fn direction(self) -> Direction {
+        self.0
+    }
+    fn x(self) -> usize {
+        self.1
+    }
+    fn y(self) -> usize {
+        self.2
+    }
+}
+
+impl Direction {
+    fn vector(self, x: usize, y: usize, width: usize, height: usize) -> Option<DirectionVector> {
+        match self {
+            Direction::North if y > 0 => Some(DirectionVector(self, x, y - 1)),
+            Direction::East if x + 1 < width => Some(DirectionVector(self, x + 1, y)),
+            Direction::South if y + 1 < height => Some(DirectionVector(self, x, y + 1)),
+            Direction::West if x > 0 => Some(DirectionVector(self, x - 1, y)),
+            _ => None,
+        }
+    }
+
+    fn reverse(self) -> Direction {
+        match self {
+            Direction::North => Direction::South,
+            Direction::East => Direction::West,
+            Direction::South => Direction::North,
+            Direction::West => Direction::East,
+        }
+    }
+
+    fn mark(self, cell: &mut Cell) {
+        match self {
+            Direction::North => cell.north = true,
+            Direction::East => cell.east = true,
+            Direction::South => cell.south = true,
+            Direction::West => cell.west = true,
+        }
+    }
+
+    fn all() -> &'static [Direction] {
+        &[
+            Direction::North,
+            Direction::East,
+            Direction::South,
+            Direction::West,
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::{Generator, VectorGenerator};
+    use crate::gradient::{Interpolation, Stop};
+
+    fn maze() -> MazeGenerator {
+        MazeGenerator {
+            cell_size: Position::new(2, 2),
+            wall_size: Position::new(1, 1),
+            image_size: Position::new(25, 19),
+            padding: Position::new(0, 0),
+            background_color: crate::hex(0x000000),
+            block_color: crate::hex(0xffffff),
+            direction_weights: HashMap::new(),
+            default_weight: 1,
+            algorithm: Algorithm::GrowingTree,
+            newest: 0.5,
+            shape: Shape::Square,
+            entrance: Opening::new([0, 0], None),
+            exit: Opening::new([-1, -1], None),
+            braid: 0.0,
+            sparseness: 0.0,
+            solution: None,
+            heatmap: None,
+            antialias: false,
+        }
+    }
+
+    #[test]
+    fn test_amounts() {
+        let context = Context::new(1);
+        assert!(maze().generate(&context).is_ok());
+        let heavy = MazeGenerator {
+            algorithm: Algorithm::Eller,
+            default_weight: u32::MAX,
+            ..maze()
+        };
+        assert!(heavy.generate(&context).is_ok());
+        let mazes = vec![
+            MazeGenerator {
+                newest: 1.5,
+                ..maze()
+            },
+            MazeGenerator {
+                braid: -0.5,
+                ..maze()
+            },
+            MazeGenerator {
+                sparseness: f64::NAN,
+                ..maze()
+            },
+        ];
+        for maze in mazes {
+            assert!(maze.generate(&context).is_err());
+        }
+    }
+
+    #[test]
+    fn test_svg() {
+        let document = maze().generate_svg(&Context::new(1)).unwrap();
+        assert_eq!((document.width(), document.height()), (25, 19));
+        assert!(document.to_string().contains(r##"fill="#ffffff""##));
+    }
+
+    #[test]
+    fn test_square_solution() {
+        // Cells of 2 pixels with walls of 1 make an 8 by 6 maze, where
+        // cell `(x, y)` starts at pixel `(3x + 1, 3y + 1)`.
+        let (columns, rows) = (8, 6);
+        let context = Context::new(3);
+        let red = crate::hex(0xff0000);
+        let image = MazeGenerator {
+            solution: Some(Solution {
+                color: red,
+                style: SolutionStyle::Cells,
+                width: 1.0,
+            }),
+            ..maze()
+        }
+        .generate(&context)
+        .unwrap();
+
+        // The entrance is cut in the top wall, and the exit in the
+        // bottom one, where openings take the post before them too;
+        // the rest of the outer wall is whole.
+        let (width, height) = image.dimensions();
+        for (x, y, &pixel) in image.enumerate_pixels() {
+            let outer = x == 0 || y == 0 || x == width - 1 || y == height - 1;
+            let entrance = y == 0 && (1..3).contains(&x);
+            let exit = y == height - 1 && (width - 4..width - 1).contains(&x);
+            let opening = entrance || exit;
+            if outer {
+                assert_eq!(pixel == crate::hex(0xffffff), !opening, "({}, {})", x, y);
+            }
+        }
+
+        // Walks the path from the entrance, through the openings
+        // between the cells on it.
+        let cell = |(x, y): (u32, u32)| *image.get_pixel(3 * x + 1, 3 * y + 1);
+        let mut path = vec![(0, 0)];
+        loop {
+            let (x, y) = *path.last().unwrap();
+            let next = [(1, 0, 3, 1), (-1, 0, 0, 1), (0, 1, 1, 3), (0, -1, 1, 0)]
+                .iter()
+                .filter_map(|&(dx, dy, gx, gy)| {
+                    let next = ((x as i32 + dx) as u32, (y as i32 + dy) as u32);
+                    let inside = next.0 < columns && next.1 < rows;
+                    let open = inside && *image.get_pixel(3 * x + gx, 3 * y + gy) == red;
+                    Some(next).filter(|next| open && !path.contains(next))
+                })
+                .next();
+            match next {
+                Some(next) => path.push(next),
+                None => break,
+            }
+        }
+        assert_eq!(*path.last().unwrap(), (columns - 1, rows - 1));
+        let on_path = (0..rows)
+            .flat_map(|y| (0..columns).map(move |x| (x, y)))
+            .filter(|&c| cell(c) == red)
+            .count();
+        assert_eq!(on_path, path.len());
+
+        // The heatmap gets lighter further along the path.
+        let stops = vec![
+            Stop {
+                position: 0.0,
+                color: crate::hex(0x000000),
+            },
+            Stop {
+                position: 1.0,
+                color: crate::hex(0xffffff),
+            },
+        ];
+        let heatmap = MazeGenerator {
+            heatmap: Some(Gradient::new(stops, Interpolation::Srgb).unwrap()),
+            ..maze()
+        }
+        .generate(&context)
+        .unwrap();
+        let heat = path
+            .iter()
+            .map(|&(x, y)| heatmap.get_pixel(3 * x + 1, 3 * y + 1)[0])
+            .collect::<Vec<_>>();
+        assert_eq!(heat[0], 0);
+        assert!(heat.windows(2).all(|pair| pair[0] < pair[1]), "{:?}", heat);
+    }
+}
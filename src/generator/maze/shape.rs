@@ -0,0 +1,422 @@
+//! Mazes with cells that aren't square, and where their walls are in
+//! the image.
+
+use super::grid::{Graph, Side};
+use super::Direction;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+/// The shape of the cells in a maze.  The cell and wall sizes work
+/// the same way for each: cells are spaced by the cell size plus the
+/// wall size, and the walls are drawn down the middle between them.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Shape {
+    /// A grid of rectangles.
+    #[default]
+    Square,
+    /// A honeycomb of hexagons, pointing up, with every other row
+    /// shifted half a cell to the right.  Each cell is as wide and as
+    /// tall as its box, so a box that's about 1.15 times as tall as
+    /// it's wide makes regular hexagons.
+    Hex,
+    /// Rows of triangles that point up and down in turn, starting
+    /// with up in the top left.  A box that's about 0.87 times as
+    /// tall as it's wide makes equilateral triangles.
+    Triangle,
+    /// Rings of cells around a single cell in the middle of the image.
+    /// The height of the box is the width of each ring, and the
+    /// width is about how wide the cells are around it; rings are
+    /// split into more cells as they get bigger.  Rows of cells are
+    /// rings, counted out from the middle, and their cells go
+    /// clockwise from the top.
+    Theta,
+}
+
+/// A point in the image, in pixels.
+pub type Point = [f32; 2];
+
+/// The cells of a maze of some shape, and where they are.
+pub struct Tiling {
+    pub graph: Graph,
+    /// The walls of each cell, in the same order as in the graph, as
+    /// lines down the middle of them.
+    pub walls: Vec<Vec<Vec<Point>>>,
+    /// The middle of each cell.
+    pub centers: Vec<Point>,
+}
+
+/// A wall that's still being laid out, as a line and the column and
+/// row of the cell on the other side of it.
+type Wall = (Vec<Point>, Option<(usize, usize)>);
+
+/// A cell that's still being laid out: its middle, and its walls.
+struct Outline {
+    center: Point,
+    walls: Vec<Wall>,
+}
+
+impl Tiling {
+    /// Fits as many cells as will fit in a box of `size` at `origin`,
+    /// spaced `block` pixels apart, in the middle of the box.  Returns
+    /// `None` if not even one fits, or for square mazes, which are
+    /// laid out on their own.
+    pub fn new(shape: Shape, block: Point, origin: Point, size: Point) -> Option<Tiling> {
+        if block[0] <= 0.0 || block[1] <= 0.0 {
+            return None;
+        }
+        let rows = match shape {
+            Shape::Square => return None,
+            Shape::Hex => hex(block, size),
+            Shape::Triangle => triangle(block, size),
+            Shape::Theta => theta(block, size),
+        }?;
+
+        // Centers the cells in the box.
+        let points = || {
+            rows.iter()
+                .flatten()
+                .flat_map(|o| o.walls.iter().flat_map(|w| &w.0))
+        };
+        let (mut low, mut high) = ([f32::INFINITY; 2], [f32::NEG_INFINITY; 2]);
+        for point in points() {
+            for axis in 0..2 {
+                low[axis] = low[axis].min(point[axis]);
+                high[axis] = high[axis].max(point[axis]);
+            }
+        }
+        let shift = [0, 1]
+            .map(|axis| origin[axis] + (size[axis] - (high[axis] - low[axis])) / 2.0 - low[axis]);
+        let moved = |[x, y]: Point| [x + shift[0], y + shift[1]];
+
+        let starts = rows
+            .iter()
+            .scan(0, |start, row| {
+                let first = *start;
+                *start += row.len();
+                Some(first)
+            })
+            .collect::<Vec<_>>();
+        let index = |(column, row): (usize, usize)| starts[row] + column;
+        let mut sides = Vec::new();
+        let mut walls = Vec::new();
+        let mut centers = Vec::new();
+        for row in rows.iter() {
+            let mut row_sides = Vec::new();
+            for outline in row.iter() {
+                row_sides.push(
+                    outline
+                        .walls
+                        .iter()
+                        .map(|(line, neighbor)| Side {
+                            neighbor: neighbor.map(index),
+                            facing: facing(outline.center, line),
+                            open: false,
+                        })
+                        .collect(),
+                );
+                walls.push(
+                    outline
+                        .walls
+                        .iter()
+                        .map(|(line, _)| line.iter().cloned().map(moved).collect())
+                        .collect(),
+                );
+                centers.push(moved(outline.center));
+            }
+            sides.push(row_sides);
+        }
+        Some(Tiling {
+            graph: Graph::new(sides),
+            walls,
+            centers,
+        })
+    }
+
+    /// The outline of a cell, all the way around.
+    pub fn outline(&self, cell: usize) -> Vec<Point> {
+        self.walls[cell].iter().flatten().cloned().collect()
+    }
+
+    /// The point just past the middle of a wall of a cell, on the
+    /// far side from the cell, for a line out through an opening.
+    pub fn outside(&self, cell: usize, side: usize, wall_width: f32) -> Point {
+        let [x, y] = middle(&self.walls[cell][side]);
+        let center = self.centers[cell];
+        let (dx, dy) = (x - center[0], y - center[1]);
+        let length = dx.hypot(dy).max(f32::EPSILON);
+        let past = wall_width / 2.0;
+        [x + dx / length * past, y + dy / length * past]
+    }
+}
+
+/// The point halfway between the ends of a line.
+fn middle(line: &[Point]) -> Point {
+    let (first, last) = (line[0], line[line.len() - 1]);
+    [(first[0] + last[0]) / 2.0, (first[1] + last[1]) / 2.0]
+}
+
+/// The compass direction that a wall faces most, seen from the middle
+/// of its cell.  A wall that faces exactly between two of them counts
+/// as north or south.
+fn facing(center: Point, wall: &[Point]) -> Direction {
+    let [x, y] = middle(wall);
+    let (dx, dy) = (x - center[0], y - center[1]);
+    match (dx.abs() > dy.abs(), dx > 0.0, dy > 0.0) {
+        (true, true, _) => Direction::East,
+        (true, false, _) => Direction::West,
+        (false, _, true) => Direction::South,
+        (false, _, false) => Direction::North,
+    }
+}
+
+/// The cell that's `offset` columns and rows away from another, if
+/// it's in a grid of `size` columns and rows.
+fn neighbor(
+    column: usize,
+    row: usize,
+    offset: (isize, isize),
+    size: (usize, usize),
+) -> Option<(usize, usize)> {
+    let column = column as isize + offset.0;
+    let row = row as isize + offset.1;
+    match (0..size.0 as isize).contains(&column) && (0..size.1 as isize).contains(&row) {
+        true => Some((column as usize, row as usize)),
+        false => None,
+    }
+}
+
+/// The walls between the corners of a polygon, in order, along with
+/// the cell on the other side of each.
+fn polygon(corners: &[Point], neighbors: &[Option<(usize, usize)>]) -> Outline {
+    let count = corners.len() as f32;
+    let center = [
+        corners.iter().map(|c| c[0]).sum::<f32>() / count,
+        corners.iter().map(|c| c[1]).sum::<f32>() / count,
+    ];
+    let walls = (0..corners.len())
+        .map(|i| {
+            let line = vec![corners[i], corners[(i + 1) % corners.len()]];
+            (line, neighbors[i])
+        })
+        .collect();
+    Outline { center, walls }
+}
+
+fn hex([width, height]: Point, [space_x, space_y]: Point) -> Option<Vec<Vec<Outline>>> {
+    // Each row overlaps the one above it by a quarter of a cell.
+    let pitch = height * 0.75;
+    if space_y < height {
+        return None;
+    }
+    let rows = ((space_y - height) / pitch) as usize + 1;
+    let shifted = if rows > 1 { width / 2.0 } else { 0.0 };
+    let columns = ((space_x - shifted) / width).max(0.0) as usize;
+    if columns == 0 {
+        return None;
+    }
+
+    // The cells around each cell, from the top right and clockwise,
+    // for rows that aren't shifted and for rows that are.
+    const EVEN: [(isize, isize); 6] = [(0, -1), (1, 0), (0, 1), (-1, 1), (-1, 0), (-1, -1)];
+    const ODD: [(isize, isize); 6] = [(1, -1), (1, 0), (1, 1), (0, 1), (-1, 0), (0, -1)];
+    let grid = (0..rows)
+        .map(|row| {
+            (0..columns)
+                .map(|column| {
+                    let odd = row % 2 == 1;
+                    let x =
+                        column as f32 * width + width / 2.0 + if odd { width / 2.0 } else { 0.0 };
+                    let y = row as f32 * pitch + height / 2.0;
+                    let (w, h) = (width / 2.0, height / 2.0);
+                    let corners = [
+                        [x, y - h],
+                        [x + w, y - h / 2.0],
+                        [x + w, y + h / 2.0],
+                        [x, y + h],
+                        [x - w, y + h / 2.0],
+                        [x - w, y - h / 2.0],
+                    ];
+                    let offsets = if odd { ODD } else { EVEN };
+                    let neighbors = offsets.map(|o| neighbor(column, row, o, (columns, rows)));
+                    polygon(&corners, &neighbors)
+                })
+                .collect()
+        })
+        .collect();
+    Some(grid)
+}
+
+fn triangle([width, height]: Point, [space_x, space_y]: Point) -> Option<Vec<Vec<Outline>>> {
+    // Each triangle overlaps the ones next to it by half of its width.
+    let columns = ((space_x * 2.0 / width) as usize).saturating_sub(1);
+    let rows = (space_y / height) as usize;
+    if columns == 0 || rows == 0 {
+        return None;
+    }
+    let grid = (0..rows)
+        .map(|row| {
+            (0..columns)
+                .map(|column| {
+                    let left = column as f32 * width / 2.0;
+                    let (top, bottom) = (row as f32 * height, (row + 1) as f32 * height);
+                    let near = |offset| neighbor(column, row, offset, (columns, rows));
+                    match (column + row) % 2 == 0 {
+                        // Pointing up, from the bottom left corner.
+                        true => polygon(
+                            &[
+                                [left, bottom],
+                                [left + width / 2.0, top],
+                                [left + width, bottom],
+                            ],
+                            &[near((-1, 0)), near((1, 0)), near((0, 1))],
+                        ),
+                        // Pointing down, from the top left corner.
+                        false => polygon(
+                            &[
+                                [left, top],
+                                [left + width, top],
+                                [left + width / 2.0, bottom],
+                            ],
+                            &[near((0, -1)), near((1, 0)), near((-1, 0))],
+                        ),
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    Some(grid)
+}
+
+fn theta([width, ring]: Point, [space_x, space_y]: Point) -> Option<Vec<Vec<Outline>>> {
+    let rings = (space_x.min(space_y) / 2.0 / ring) as usize;
+    if rings == 0 {
+        return None;
+    }
+    // The number of cells in each ring.  Each ring splits the cells of
+    // the one inside it into as many as keeps them about `width` wide,
+    // so that every cell has one cell inside it.  The first ring has at
+    // least three cells, so that each cell has different cells on
+    // either side of it.
+    let mut counts = vec![1];
+    for r in 1..rings {
+        let around = 2.0 * PI * r as f32 * ring;
+        let split = ((around / counts[r - 1] as f32 / width).round() as usize).max(1);
+        let count = counts[r - 1] * split;
+        counts.push(if r == 1 { count.max(3) } else { count });
+    }
+
+    // Points around the middle of the maze, with angles going
+    // clockwise from the top.
+    let point = |radius: f32, angle: f32| [radius * angle.sin(), -radius * angle.cos()];
+    let arc = |radius: f32, from: f32, to: f32| {
+        let steps = ((to - from).abs() * radius / 3.0).ceil().max(1.0) as usize;
+        (0..=steps)
+            .map(|i| point(radius, from + (to - from) * i as f32 / steps as f32))
+            .collect::<Vec<_>>()
+    };
+    let angle = |cell: usize, r: usize| cell as f32 * 2.0 * PI / counts[r] as f32;
+    // The walls along the outside of a cell: one for each cell in the
+    // next ring out, or just the one on the last ring.
+    let outer = |cell: usize, r: usize| match r + 1 < rings {
+        true => {
+            let split = counts[r + 1] / counts[r];
+            (cell * split..(cell + 1) * split)
+                .map(|next| {
+                    let line = arc(
+                        (r + 1) as f32 * ring,
+                        angle(next, r + 1),
+                        angle(next + 1, r + 1),
+                    );
+                    (line, Some((next, r + 1)))
+                })
+                .collect::<Vec<_>>()
+        }
+        false => vec![(
+            arc((r + 1) as f32 * ring, angle(cell, r), angle(cell + 1, r)),
+            None,
+        )],
+    };
+
+    let mut grid = vec![vec![Outline {
+        center: [0.0, 0.0],
+        walls: outer(0, 0),
+    }]];
+    for r in 1..rings {
+        let (inside, outside) = (r as f32 * ring, (r + 1) as f32 * ring);
+        let count = counts[r];
+        let split = count / counts[r - 1];
+        grid.push(
+            (0..count)
+                .map(|cell| {
+                    let (from, to) = (angle(cell, r), angle(cell + 1, r));
+                    let mut walls = vec![
+                        (arc(inside, to, from), Some((cell / split, r - 1))),
+                        (
+                            vec![point(inside, from), point(outside, from)],
+                            Some(((cell + count - 1) % count, r)),
+                        ),
+                    ];
+                    walls.extend(outer(cell, r));
+                    walls.push((
+                        vec![point(outside, to), point(inside, to)],
+                        Some(((cell + 1) % count, r)),
+                    ));
+                    Outline {
+                        center: point((inside + outside) / 2.0, (from + to) / 2.0),
+                        walls,
+                    }
+                })
+                .collect(),
+        );
+    }
+    Some(grid)
+}
+
+/// Whether a point is inside a polygon, by counting how many of its
+/// edges a line out from the point to the right crosses.
+pub fn contains(polygon: &[Point], [x, y]: Point) -> bool {
+    let mut inside = false;
+    for (i, &[ax, ay]) in polygon.iter().enumerate() {
+        let [bx, by] = polygon[(i + 1) % polygon.len()];
+        if (ay > y) != (by > y) && x < ax + (y - ay) / (by - ay) * (bx - ax) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tilings() {
+        let hex = Tiling::new(Shape::Hex, [20.0, 23.0], [0.0, 0.0], [100.0, 100.0]).unwrap();
+        // Five rows of four, since every other row is shifted.
+        assert_eq!((hex.graph.rows(), hex.graph.row(0).len()), (5, 4));
+        // A cell in the middle has all six neighbors.
+        assert_eq!(hex.graph.neighbors(5).count(), 6);
+
+        let triangle =
+            Tiling::new(Shape::Triangle, [20.0, 17.0], [0.0, 0.0], [100.0, 100.0]).unwrap();
+        assert_eq!((triangle.graph.rows(), triangle.graph.row(0).len()), (5, 9));
+        assert_eq!(triangle.graph.cell(0).sides[2].facing, Direction::South);
+
+        let theta = Tiling::new(Shape::Theta, [10.0, 10.0], [0.0, 0.0], [100.0, 100.0]).unwrap();
+        assert_eq!(theta.graph.rows(), 5);
+        assert_eq!(theta.graph.row(1).len(), 6);
+        // Every wall is shared with the cell on the other side of it.
+        for cell in 0..theta.graph.len() {
+            for (_, neighbor) in theta.graph.neighbors(cell) {
+                assert!(theta.graph.neighbors(neighbor).any(|(_, n)| n == cell));
+            }
+        }
+        // The cells are in the middle of the box.
+        let [x, y] = theta.centers[0];
+        assert!((x - 50.0).abs() < 0.5 && (y - 50.0).abs() < 0.5);
+
+        assert!(contains(&hex.outline(0), hex.centers[0]));
+        assert!(!contains(&hex.outline(0), hex.centers[1]));
+    }
+}
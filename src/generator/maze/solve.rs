@@ -0,0 +1,68 @@
+//! Finding paths through a carved maze.
+
+use super::grid::Graph;
+use std::collections::VecDeque;
+
+/// A breadth first search out from `from`, which finds the number of
+/// steps to every cell and the cell that each one is first reached
+/// from.  Cells that can't be reached are left as `None`.
+fn search(graph: &Graph, from: usize) -> (Vec<Option<u32>>, Vec<Option<usize>>) {
+    let mut distances = vec![None; graph.len()];
+    let mut parents = vec![None; graph.len()];
+    distances[from] = Some(0);
+    let mut queue = VecDeque::new();
+    queue.push_back(from);
+    while let Some(at) = queue.pop_front() {
+        let distance = distances[at].expect("queued cells have a distance");
+        for next in graph.passages(at) {
+            if distances[next].is_none() {
+                distances[next] = Some(distance + 1);
+                parents[next] = Some(at);
+                queue.push_back(next);
+            }
+        }
+    }
+    (distances, parents)
+}
+
+/// The number of steps from `from` to every cell.  Every step costs
+/// the same, so this is what Dijkstra's algorithm would find.
+pub fn distances(graph: &Graph, from: usize) -> Vec<Option<u32>> {
+    search(graph, from).0
+}
+
+/// The shortest path from `from` to `to`, including both, or `None`
+/// if there isn't one.
+pub fn solve(graph: &Graph, from: usize, to: usize) -> Option<Vec<usize>> {
+    let (distances, parents) = search(graph, from);
+    distances[to]?;
+    let mut path = vec![to];
+    while let Some(parent) = parents[path[path.len() - 1]] {
+        path.push(parent);
+    }
+    path.reverse();
+    Some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::maze::{algorithm, Algorithm};
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_solve() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+        let mut graph = Graph::square(9, 7);
+        algorithm::carve(Algorithm::Kruskal, 0.5, |_| 1, &mut graph, &mut rng).unwrap();
+        let path = solve(&graph, 0, 62).unwrap();
+        assert_eq!(path[0], 0);
+        assert_eq!(path[path.len() - 1], 62);
+        // Each step is through an open wall to the next cell.
+        for step in path.windows(2) {
+            assert!(graph.passages(step[0]).any(|next| next == step[1]));
+        }
+        let distances = distances(&graph, 0);
+        assert_eq!(distances[62], Some(path.len() as u32 - 1));
+    }
+}
@@ -1,17 +1,331 @@
+mod contour;
+mod coverage;
 mod file;
+pub mod fractal;
 pub mod maze;
 mod noise;
 mod random;
 mod bars;
 pub mod stripe;
+mod supersample;
 
+pub use self::contour::ContourGenerator;
 pub use self::file::FileGenerator;
 pub use self::maze::MazeGenerator;
-pub use self::noise::NoiseGenerator;
+pub use self::noise::{Animated, DownSample, NoiseGenerator, Perlin};
 pub use self::random::RandomGenerator;
 pub use self::bars::BarGenerator;
 pub use self::stripe::StripeGenerator;
+pub use self::supersample::{Resample, Scaled, Supersample};
 
-pub trait Generator<P: image::Pixel> {
-    fn generate(&self) -> image::ImageBuffer<P, Vec<P::Subpixel>>;
+use crate::context::Context;
+use crate::error::Result;
+use crate::registry::{Descriptor, Kind, Parameter};
+use crate::svg::Document;
+
+pub trait Generator<P: image::Pixel>: Send + Sync {
+    /// Generates the image.  Any randomness should come from the
+    /// given context, so that the image can be reproduced.
+    fn generate(&self, context: &Context) -> Result<image::ImageBuffer<P, Vec<P::Subpixel>>>;
+}
+
+/// A generator that draws shapes, which can be written out as an SVG
+/// document instead of an image.
+pub trait VectorGenerator: Send + Sync {
+    /// Draws the same image as [`Generator::generate`] would for the
+    /// same context, as shapes.
+    fn generate_svg(&self, context: &Context) -> Result<Document>;
 }
+
+const SIZE: &str = "[u32, u32]";
+/// Pairs of pixels, or of percentages of the shorter side of the
+/// image, like `["2%", "2%"]`.
+const LENGTHS: &str = "[length, length]";
+/// Pixels, or a percentage of the shorter side of the image, like
+/// `"2%"`.
+const LENGTH: &str = "length";
+const COLOR: &str = "color";
+
+/// Every generator that can be named in a pipeline.
+pub static GENERATORS: &[Descriptor] = &[
+    Descriptor {
+        name: "noise",
+        kind: Kind::Generator,
+        summary: "Noise, blended between two colors or through a gradient.",
+        parameters: &[
+            Parameter::required("image_size", SIZE, "The size of the image, in pixels."),
+            Parameter::optional(
+                "background_color",
+                COLOR,
+                "none",
+                "The color of the highest noise values; needed without a gradient or bands.",
+            ),
+            Parameter::optional(
+                "block_color",
+                COLOR,
+                "none",
+                "The color of the lowest noise values; needed without a gradient or bands.",
+            ),
+            Parameter::optional(
+                "gradient",
+                "{ stops, interpolation, hard }",
+                "none",
+                "Colors for the noise values from lowest to highest, in srgb, linear or oklab.",
+            ),
+            Parameter::required(
+                "image_scale",
+                "f64 | [f64, f64]",
+                "How much noise fits across the width of the image.",
+            ),
+            Parameter::optional(
+                "value_scale",
+                "f64",
+                "1.0",
+                "A multiplier on the noise value before it is clamped.",
+            ),
+            Parameter::optional(
+                "motion",
+                "f64",
+                "0.0",
+                "How far the noise moves over an animation; 0.0 keeps it still.",
+            ),
+            Parameter::optional(
+                "source",
+                "{ type, octaves, persistence, lacunarity, frequency, flat }",
+                "none",
+                "The noise function, like perlin, worley, billow or ridged_multi.",
+            ),
+            Parameter::optional(
+                "warp",
+                "{ strength, frequency }",
+                "none",
+                "Pushes the noise around by a second copy of itself, for marbled shapes.",
+            ),
+            Parameter::optional(
+                "contours",
+                "{ interval, width, color, index_every, index_width, index_color, bands }",
+                "none",
+                "Draws the noise as contour lines, optionally over bands of color.",
+            ),
+        ],
+    },
+    Descriptor {
+        name: "maze",
+        kind: Kind::Generator,
+        summary: "A maze, drawn as walls on a grid of cells.",
+        parameters: &[
+            Parameter::required(
+                "cell_size",
+                LENGTHS,
+                "The size of the open space in each cell.",
+            ),
+            Parameter::required(
+                "wall_size",
+                LENGTHS,
+                "The thickness of the walls between cells.",
+            ),
+            Parameter::required("image_size", SIZE, "The size of the image, in pixels."),
+            Parameter::optional(
+                "padding",
+                LENGTHS,
+                "[0, 0]",
+                "The space left around the maze on each side.",
+            ),
+            Parameter::required(
+                "background_color",
+                COLOR,
+                "The color of the paths and the padding.",
+            ),
+            Parameter::required("block_color", COLOR, "The color of the walls."),
+            Parameter::optional(
+                "direction_weights",
+                "{ north|east|south|west = u32 }",
+                "{}",
+                "How likely the maze is to carve in each direction.",
+            ),
+            Parameter::optional(
+                "default_weight",
+                "u32",
+                "10",
+                "The weight of directions missing from direction_weights.",
+            ),
+            Parameter::optional(
+                "algorithm",
+                "backtracker | kruskal | prim | wilson | eller | aldous_broder | binary_tree | sidewinder | growing_tree",
+                "\"backtracker\"",
+                "How the maze is carved, which changes its texture.",
+            ),
+            Parameter::optional(
+                "newest",
+                "f64",
+                "0.5",
+                "How often growing_tree carves from the newest cell instead of a random one.",
+            ),
+            Parameter::optional(
+                "shape",
+                "square | hex | triangle | theta",
+                "\"square\"",
+                "The shape of the cells; theta mazes are rings around the middle of the image.",
+            ),
+            Parameter::optional(
+                "braid",
+                "f64",
+                "0.0",
+                "How many dead ends have a wall knocked down, adding loops.",
+            ),
+            Parameter::optional(
+                "sparseness",
+                "f64",
+                "0.0",
+                "How many cells are cut away from the dead ends, leaving open space.",
+            ),
+            Parameter::optional(
+                "entrance",
+                "{ cell = [i64, i64], side }",
+                "{ cell = [0, 0], side = \"north\" }",
+                "The cell the maze is entered through; negative cells count from the end.",
+            ),
+            Parameter::optional(
+                "exit",
+                "{ cell = [i64, i64], side }",
+                "{ cell = [-1, -1], side = \"south\" }",
+                "The cell the maze is left through.",
+            ),
+            Parameter::optional(
+                "solution",
+                "{ color, style = line | cells, width }",
+                "none",
+                "Draws the shortest path from the entrance to the exit.",
+            ),
+            Parameter::optional(
+                "heatmap",
+                "{ stops, interpolation, hard }",
+                "none",
+                "Colors the paths by their distance from the entrance.",
+            ),
+            Parameter::optional(
+                "antialias",
+                "bool",
+                "false",
+                "Smooths the solution line, and the cells of mazes that aren't square.",
+            ),
+        ],
+    },
+    Descriptor {
+        name: "stripe",
+        kind: Kind::Generator,
+        summary: "Horizontal stripes, optionally sloped across the image.",
+        parameters: &[
+            Parameter::required("image_size", SIZE, "The size of the image, in pixels."),
+            Parameter {
+                name: "stripes",
+                kind: "[{ width, color, padding_bottom }]",
+                default: None,
+                summary: "The stripes, from top to bottom, with widths and paddings as lengths.",
+            },
+            Parameter::optional(
+                "stripe_shift",
+                "f64",
+                "0.0",
+                "How far the stripes drop from the left to the right edge.",
+            ),
+            Parameter::optional(
+                "top_offset",
+                LENGTH,
+                "0",
+                "The distance from the top of the image to the first stripe.",
+            ),
+            Parameter::required("background_color", COLOR, "The color between the stripes."),
+            Parameter::optional(
+                "error_color",
+                COLOR,
+                "\"#ff0000\"",
+                "The color used where no stripe could be found.",
+            ),
+            Parameter::optional(
+                "antialias",
+                "bool",
+                "false",
+                "Smooths the edges of the stripes.",
+            ),
+        ],
+    },
+    Descriptor {
+        name: "bars",
+        kind: Kind::Generator,
+        summary: "Bars laid out one after another, at any angle.",
+        parameters: &[
+            Parameter::required("image_size", SIZE, "The size of the image, in pixels."),
+            Parameter {
+                name: "bars",
+                kind: "[{ gap, size, color, gradient }]",
+                default: None,
+                summary: "The bars, as lengths; gap is the space after the previous bar.",
+            },
+            Parameter::optional(
+                "initial_offset",
+                LENGTH,
+                "0",
+                "The distance from the edge of the image to the first bar.",
+            ),
+            Parameter::optional(
+                "angle",
+                "f64",
+                "0.0",
+                "How far the bars are turned, in degrees; horizontal ones counterclockwise.",
+            ),
+            Parameter::optional(
+                "vertical",
+                "bool",
+                "false",
+                "Whether the bars run down the image instead of across it.",
+            ),
+            Parameter::optional(
+                "repeat",
+                "bool",
+                "false",
+                "Repeats the bars over the whole image.",
+            ),
+            Parameter::required("background_color", COLOR, "The color between the bars."),
+            Parameter::optional(
+                "antialias",
+                "bool",
+                "false",
+                "Smooths the edges of the bars.",
+            ),
+        ],
+    },
+    Descriptor {
+        name: "random",
+        kind: Kind::Generator,
+        summary: "A grid of blocks, each randomly filled in.",
+        parameters: &[
+            Parameter::required("block_size", LENGTHS, "The size of each block."),
+            Parameter::required("image_size", SIZE, "The size of the image, in pixels."),
+            Parameter::optional(
+                "padding",
+                LENGTHS,
+                "[0, 0]",
+                "The space left around the blocks on each side.",
+            ),
+            Parameter::required("block_color", COLOR, "The color of a filled in block."),
+            Parameter::required(
+                "background_color",
+                COLOR,
+                "The color of an empty block and the padding.",
+            ),
+            Parameter::optional(
+                "distribution",
+                "f64",
+                "0.5",
+                "The chance that any block is filled in, from 0.0 to 1.0.",
+            ),
+        ],
+    },
+    Descriptor {
+        name: "file",
+        kind: Kind::Generator,
+        summary: "An existing image, loaded from disk.",
+        parameters: &[Parameter::required("path", "path", "The image to load.")],
+    },
+];
@@ -1,6 +1,50 @@
+use noise::{MultiFractal, Seedable};
 use rand::Rng;
 
-use crate::position::{Position, Region};
+use crate::context::Context;
+use crate::error::Result;
+use crate::gradient::Gradient;
+use crate::position::Position;
+
+/// Perlin noise.
+///
+/// `noise` exports two different `Perlin`s through glob re-exports,
+/// with no other path to either, so this reaches the one its fractals
+/// are built on through a single octave of [`noise::Fbm`], which is
+/// exactly the plain noise.
+#[derive(Debug, Clone)]
+pub struct Perlin(noise::Fbm);
+
+impl Perlin {
+    pub fn new() -> Self {
+        Perlin(noise::Fbm::new().set_octaves(1).set_frequency(1.0))
+    }
+}
+
+impl Default for Perlin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> noise::NoiseFn<[f64; N]> for Perlin
+where
+    noise::Fbm: noise::NoiseFn<[f64; N]>,
+{
+    fn get(&self, point: [f64; N]) -> f64 {
+        self.0.get(point)
+    }
+}
+
+impl Seedable for Perlin {
+    fn set_seed(self, seed: u32) -> Self {
+        Perlin(self.0.set_seed(seed))
+    }
+
+    fn seed(&self) -> u32 {
+        self.0.seed()
+    }
+}
 
 pub struct NoiseGenerator<F: noise::NoiseFn<[f64; 2]>> {
     f: F,
@@ -9,21 +53,90 @@ pub struct NoiseGenerator<F: noise::NoiseFn<[f64; 2]>> {
     pub background_color: image::Rgb<u8>,
     pub block_color: image::Rgb<u8>,
     pub image_size: Position,
+    /// Maps noise values to colors, from the lowest at `0.0` to the
+    /// highest at `1.0`, instead of blending between the background
+    /// and block colors.
+    pub gradient: Option<Gradient>,
+}
+
+/// A noise function that can change over the course of an
+/// animation.  Functions that don't move can use the default, which
+/// is the same function at every point in time.
+pub trait Animated: Clone {
+    /// The function at `time`, from `0.0` to `1.0`.  The function at
+    /// `1.0` should be the same as it is at `0.0`, so that animations
+    /// loop.
+    fn at(&self, _time: f64) -> Self {
+        self.clone()
+    }
+}
+
+impl Animated for Perlin {}
+
+/// Samples a 4D noise function on the plane at the given `z` and `w`.
+///
+/// In an animation, the plane moves along a circle around `(z, w)`,
+/// which brings it back to where it started at the end.
+#[derive(Clone)]
+pub struct DownSample<F: noise::NoiseFn<[f64; 4]>> {
+    f: F,
+    center: [f64; 2],
+    radius: f64,
+    time: f64,
 }
 
-pub struct DownSample<F: noise::NoiseFn<[f64; 4]>>(F, f64, f64);
+impl<F: noise::NoiseFn<[f64; 4]>> DownSample<F> {
+    pub fn new(f: F, z: f64, w: f64) -> Self {
+        DownSample {
+            f,
+            center: [z, w],
+            radius: 0.0,
+            time: 0.0,
+        }
+    }
+
+    /// Sets the radius of the circle that the plane moves along.  The
+    /// bigger it is, the more the noise changes from frame to frame.
+    pub fn with_motion(self, radius: f64) -> Self {
+        DownSample { radius, ..self }
+    }
+}
 
 impl<F> noise::NoiseFn<[f64; 2]> for DownSample<F>
 where
     F: noise::NoiseFn<[f64; 4]>,
 {
     fn get(&self, v: [f64; 2]) -> f64 {
-        let v = [v[0], v[1], self.1, self.2];
-        (self.0).get(v)
+        let angle = self.time * std::f64::consts::TAU;
+        let z = self.center[0] + self.radius * angle.cos();
+        let w = self.center[1] + self.radius * angle.sin();
+        self.f.get([v[0], v[1], z, w])
     }
 }
 
-impl NoiseGenerator<noise::Perlin> {
+impl<F: noise::NoiseFn<[f64; 4]> + Seedable> Seedable for DownSample<F> {
+    fn set_seed(self, seed: u32) -> Self {
+        DownSample {
+            f: self.f.set_seed(seed),
+            ..self
+        }
+    }
+
+    fn seed(&self) -> u32 {
+        self.f.seed()
+    }
+}
+
+impl<F: noise::NoiseFn<[f64; 4]> + Clone> Animated for DownSample<F> {
+    fn at(&self, time: f64) -> Self {
+        DownSample {
+            time,
+            ..self.clone()
+        }
+    }
+}
+
+impl NoiseGenerator<Perlin> {
     pub fn new(
         image_size: Position,
         background_color: image::Rgb<u8>,
@@ -31,42 +144,94 @@ impl NoiseGenerator<noise::Perlin> {
         image_scale: f64,
         value_scale: f64,
     ) -> Self {
-        use noise::Seedable;
-        use rand::distributions::Open01;
-        let mut rng = rand::thread_rng();
-        let seed: u32 = rng.gen();
-        let f = noise::Perlin::new();
-        let f = f.set_seed(seed);
-
+        let f = Perlin::new();
 
         NoiseGenerator {
-            f,//: DownSample(f, dbg!(rng.sample(Open01)), dbg!(rng.sample(Open01))),
+            f,
             image_scale: [image_scale, image_scale],
             background_color,
             block_color,
             image_size,
             value_scale,
+            gradient: None,
         }
     }
 }
 
-impl<F> super::Generator<image::Rgb<u8>> for NoiseGenerator<F>
+impl<F: noise::NoiseFn<[f64; 2]>> NoiseGenerator<F> {
+    pub fn function(&self) -> &F {
+        &self.f
+    }
+
+    /// Replaces the noise function, which is Perlin noise by
+    /// default.  The function is reseeded from the context every
+    /// time an image is generated.
+    pub fn with_function<G: noise::NoiseFn<[f64; 2]>>(self, f: G) -> NoiseGenerator<G> {
+        NoiseGenerator {
+            f,
+            image_scale: self.image_scale,
+            value_scale: self.value_scale,
+            background_color: self.background_color,
+            block_color: self.block_color,
+            image_size: self.image_size,
+            gradient: self.gradient,
+        }
+    }
+}
+
+impl<F> NoiseGenerator<F>
 where
-    F: noise::NoiseFn<[f64; 2]>,
+    F: noise::NoiseFn<[f64; 2]> + Seedable + Animated + Send + Sync,
 {
-    fn generate(&self) -> image::RgbImage {
-        image::ImageBuffer::from_fn(self.image_size.y(), self.image_size.x(), |x, y| {
+    /// The noise value of every pixel, scaled and clamped to `0.0`
+    /// through `1.0`.
+    pub fn field(&self, context: &Context) -> image::ImageBuffer<image::Luma<f32>, Vec<f32>> {
+        let f = self.f.at(context.time()).set_seed(context.rng().gen());
+        let (width, height) = (self.image_size.x(), self.image_size.y());
+        crate::rows::from_fn(width, height, |x, y| {
             let point = [
                 ((x as f64) / (self.image_size.x() as f64)) * self.image_scale[0],
                 ((y as f64) / (self.image_size.x() as f64)) * self.image_scale[1],
             ];
-            // let value = (self.f.get(point) * self.value_scale).clamp(0.0, 1.0);
-            let value = self.f.get(point);
+            let value = f.get(point);
             let value = (value + 1.0) / 2.0;
             let value = value * self.value_scale;
             let value = value.clamp(0.0, 1.0);
-
-            imageproc::pixelops::interpolate(self.background_color, self.block_color, value as f32)
+            image::Luma([value as f32])
         })
     }
+
+    /// The color of a noise value from the field.
+    pub fn color(&self, value: f32) -> image::Rgb<u8> {
+        match self.gradient.as_ref() {
+            Some(gradient) => gradient.at(value),
+            None => {
+                imageproc::pixelops::interpolate(self.background_color, self.block_color, value)
+            }
+        }
+    }
+}
+
+/// The noise is sampled by how far across the image each pixel is,
+/// so only the size of the image changes.
+impl<F: noise::NoiseFn<[f64; 2]>> super::Scaled for NoiseGenerator<F> {
+    fn scaled(self, factor: u32) -> Self {
+        NoiseGenerator {
+            image_size: self.image_size * factor,
+            ..self
+        }
+    }
+}
+
+impl<F> super::Generator<image::Rgb<u8>> for NoiseGenerator<F>
+where
+    F: noise::NoiseFn<[f64; 2]> + Seedable + Animated + Send + Sync,
+{
+    fn generate(&self, context: &Context) -> Result<image::RgbImage> {
+        let field = self.field(context);
+        let image = crate::rows::from_fn(field.width(), field.height(), |x, y| {
+            self.color(field.get_pixel(x, y)[0])
+        });
+        Ok(image)
+    }
 }
@@ -1,4 +1,7 @@
+use crate::context::Context;
+use crate::error::{Error, Result};
 use crate::position::{Position, Region};
+use crate::svg::Document;
 use rand::Rng;
 
 #[derive(Debug, Copy, Clone)]
@@ -33,48 +36,122 @@ pub struct RandomGenerator<P: image::Pixel + 'static> {
     pub distribution: f64,
 }
 
-impl<P: image::Pixel + 'static> super::Generator<P> for RandomGenerator<P> {
-    fn generate(&self) -> image::ImageBuffer<P, Vec<P::Subpixel>> {
-        let center_adjustment: Position = (self.image_size - self.padding * 2) % self.block_size;
-        let inner_region = Region::new(
-            self.padding + center_adjustment / 2,
-            self.image_size - self.padding - center_adjustment / 2,
-        )
-        .expect("top left should be greater than bottom right?");
+impl<P: image::Pixel + 'static> RandomGenerator<P> {
+    /// The top left corner of the blocks, and whether each block is
+    /// colored in, by column and then by row.
+    fn blocks(&self, context: &Context) -> Result<(Position, Vec<Vec<bool>>)> {
+        if self.block_size.is_empty() {
+            return Err(Error::parameter(
+                "random",
+                "block_size",
+                "blocks must be at least 1 pixel in each direction",
+            ));
+        }
+        let available = self
+            .image_size
+            .checked_sub(self.padding * 2)
+            .ok_or_else(|| {
+                Error::parameter(
+                    "random",
+                    "padding",
+                    format!(
+                        "{} on each side does not fit in an image of {}",
+                        self.padding, self.image_size
+                    ),
+                )
+            })?;
 
-        let blocks_count: Position = (self.image_size - self.padding * 2) / self.block_size;
+        let center_adjustment: Position = available % self.block_size;
+        let blocks_count: Position = available / self.block_size;
 
-        let rng = rand::thread_rng();
-        let dist = rand::distributions::Bernoulli::new(self.distribution)
-            .expect("distribution should be between 0.0 and 1.0");
+        let mut rng = context.rng();
+        let dist = rand::distributions::Bernoulli::new(self.distribution).map_err(|_| {
+            Error::parameter(
+                "random",
+                "distribution",
+                format!("must be between 0.0 and 1.0, not {}", self.distribution),
+            )
+        })?;
 
         let blocks = (0..blocks_count.x())
             .map(|_| {
-                rng.sample_iter(dist)
+                (&mut rng)
+                    .sample_iter(dist)
                     .take(blocks_count.y() as usize)
                     .collect::<Vec<bool>>()
             })
             .collect::<Vec<_>>();
+        Ok((self.padding + center_adjustment / 2, blocks))
+    }
+}
+
+impl<P> super::Generator<P> for RandomGenerator<P>
+where
+    P: image::Pixel + Send + Sync + 'static,
+    P::Subpixel: Send + Sync,
+{
+    fn generate(&self, context: &Context) -> Result<image::ImageBuffer<P, Vec<P::Subpixel>>> {
+        let (origin, blocks) = self.blocks(context)?;
+        let inner_region = Region::new(origin, self.image_size - origin)
+            .expect("the padding was checked to fit in the image");
 
         let index_of = |position: Position| {
-            let adjusted: Position = (position - self.padding) / self.block_size;
+            let adjusted: Position = (position - origin) / self.block_size;
             blocks
                 .get(adjusted.x() as usize)
                 .and_then(|v| v.get(adjusted.y() as usize))
                 .cloned()
         };
 
-        image::ImageBuffer::from_fn(self.image_size.x(), self.image_size.y(), |x, y| {
-            let pos = Position::new(x, y);
-            if !inner_region.contains(pos) {
-                return self.background_color;
-            }
+        Ok(crate::rows::from_fn(
+            self.image_size.x(),
+            self.image_size.y(),
+            |x, y| {
+                let pos = Position::new(x, y);
+                if !inner_region.contains(pos) {
+                    return self.background_color;
+                }
+
+                if index_of(pos).unwrap_or(false) {
+                    self.block_color
+                } else {
+                    self.background_color
+                }
+            },
+        ))
+    }
+}
+
+impl<P: image::Pixel + 'static> super::Scaled for RandomGenerator<P> {
+    fn scaled(self, factor: u32) -> Self {
+        RandomGenerator {
+            block_size: self.block_size * factor,
+            image_size: self.image_size * factor,
+            padding: self.padding * factor,
+            ..self
+        }
+    }
+}
 
-            if index_of(pos).unwrap() {
-                self.block_color
-            } else {
-                self.background_color
+impl super::VectorGenerator for RandomGenerator<image::Rgb<u8>> {
+    fn generate_svg(&self, context: &Context) -> Result<Document> {
+        let (origin, blocks) = self.blocks(context)?;
+        let mut document = Document::new(
+            self.image_size.x(),
+            self.image_size.y(),
+            self.background_color,
+        );
+        let size = [self.block_size.x() as f32, self.block_size.y() as f32];
+        for (x, column) in blocks.iter().enumerate() {
+            for (y, _) in column.iter().enumerate().filter(|(_, &on)| on) {
+                let corner = origin + self.block_size * Position::new(x as u32, y as u32);
+                document.rect(
+                    [corner.x() as f32, corner.y() as f32],
+                    size,
+                    self.block_color,
+                );
             }
-        })
+        }
+        Ok(document)
     }
 }
@@ -1,4 +1,8 @@
+use super::coverage;
+use crate::context::Context;
+use crate::error::Result;
 use crate::position::Position;
+use crate::svg::Document;
 
 pub struct StripeGenerator {
     pub image_size: Position,
@@ -8,6 +12,9 @@ pub struct StripeGenerator {
     pub background_color: image::Rgb<u8>,
 
     pub error_color: image::Rgb<u8>,
+    /// Whether the edges of the stripes are smoothed, by how much of
+    /// each pixel they cover.
+    pub antialias: bool,
 }
 
 pub struct Stripe {
@@ -26,8 +33,47 @@ impl Stripe {
     }
 }
 
+impl StripeGenerator {
+    /// How far the stripes rise from the left of the image to the
+    /// right.  Negative shifts don't move the stripes at all.
+    fn shift(&self) -> f32 {
+        self.stripe_shift.max(0.0) as f32
+    }
+
+    /// The top and bottom of each stripe at the left edge of the
+    /// image.
+    fn spans(&self) -> Vec<(f32, f32, image::Rgb<u8>)> {
+        let mut offset = self.top_offset;
+        let mut spans = Vec::new();
+        for stripe in self.stripes.iter() {
+            if stripe.width > 0 {
+                spans.push((offset as f32, (offset + stripe.width) as f32, stripe.color));
+            }
+            offset += stripe.width + stripe.padding_bottom;
+        }
+        spans
+    }
+
+    fn generate_smooth(&self) -> image::RgbImage {
+        let normal = [self.shift() / self.image_size.x() as f32, 1.0];
+        let spans = self.spans();
+        crate::rows::from_fn(self.image_size.x(), self.image_size.y(), |x, y| {
+            let corner = [x as f32, y as f32];
+            coverage::mix(
+                self.background_color,
+                spans.iter().map(|&(top, bottom, color)| {
+                    (coverage::band(normal, corner, top, bottom), color)
+                }),
+            )
+        })
+    }
+}
+
 impl super::Generator<image::Rgb<u8>> for StripeGenerator {
-    fn generate(&self) -> image::RgbImage {
+    fn generate(&self, _context: &Context) -> Result<image::RgbImage> {
+        if self.antialias {
+            return Ok(self.generate_smooth());
+        }
         let mut tree = std::collections::BTreeMap::new();
         tree.insert(0, self.background_color);
         let mut offset = self.top_offset;
@@ -37,9 +83,14 @@ impl super::Generator<image::Rgb<u8>> for StripeGenerator {
             offset += stripe.width + stripe.padding_bottom;
         }
 
-        let pick = |y| tree.range(0..=y).rev().next().map(|(_, p)| *p).unwrap();
+        let pick = |y| {
+            tree.range(0..=y)
+                .next_back()
+                .map(|(_, p)| *p)
+                .unwrap_or(self.error_color)
+        };
 
-        image::ImageBuffer::from_fn(self.image_size.x(), self.image_size.y(), |x, y| {
+        Ok(crate::rows::from_fn(self.image_size.x(), self.image_size.y(), |x, y| {
             let progress = x as f64 / (self.image_size.x() as f64);
             let modifier = self.stripe_shift * progress;
             let corrected_y = y + modifier as u32;
@@ -48,8 +99,53 @@ impl super::Generator<image::Rgb<u8>> for StripeGenerator {
             let current = pick(corrected_y);
             let next = pick(corrected_y + 1);
 
-            let actual = imageproc::pixelops::interpolate(current, next, 1.0 - until_next);
-            actual
-        })
+            imageproc::pixelops::interpolate(current, next, 1.0 - until_next)
+        }))
+    }
+}
+
+impl super::Scaled for StripeGenerator {
+    fn scaled(self, factor: u32) -> Self {
+        StripeGenerator {
+            image_size: self.image_size * factor,
+            stripes: self
+                .stripes
+                .into_iter()
+                .map(|stripe| {
+                    Stripe::new(
+                        stripe.width * factor,
+                        stripe.color,
+                        stripe.padding_bottom * factor,
+                    )
+                })
+                .collect(),
+            stripe_shift: self.stripe_shift * factor as f64,
+            top_offset: self.top_offset * factor,
+            ..self
+        }
+    }
+}
+
+impl super::VectorGenerator for StripeGenerator {
+    fn generate_svg(&self, _context: &Context) -> Result<Document> {
+        let width = self.image_size.x() as f32;
+        let mut document = Document::new(
+            self.image_size.x(),
+            self.image_size.y(),
+            self.background_color,
+        );
+        let shift = self.shift();
+        for (top, bottom, color) in self.spans() {
+            document.polygon(
+                &[
+                    [0.0, top],
+                    [width, top - shift],
+                    [width, bottom - shift],
+                    [0.0, bottom],
+                ],
+                color,
+            );
+        }
+        Ok(document)
     }
 }
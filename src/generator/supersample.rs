@@ -0,0 +1,227 @@
+//! Drawing a generator's image larger than it's needed, and scaling
+//! it back down, which smooths over the edges of anything that's
+//! drawn a whole pixel at a time.
+
+use super::Generator;
+use crate::context::Context;
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+/// A generator that can draw the same image at a larger size, for
+/// [`Supersample`].
+pub trait Scaled {
+    /// The same generator, drawing an image `factor` times as wide and
+    /// as tall, with everything in it grown to match.
+    fn scaled(self, factor: u32) -> Self;
+}
+
+/// How the pixels of the larger image are blended together when it's
+/// scaled back down.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Resample {
+    /// The average of the pixels that each pixel is made from.
+    #[default]
+    Box,
+    /// A three-lobed Lanczos filter, which is the sharpest, but can
+    /// ring slightly along hard edges.
+    Lanczos,
+    /// The Mitchell-Netravali filter, which is softer than Lanczos,
+    /// with hardly any ringing.
+    Mitchell,
+}
+
+impl Resample {
+    /// How far the filter reaches on either side of a pixel, in
+    /// pixels of the smaller image.
+    fn support(self) -> f32 {
+        match self {
+            Resample::Box => 0.5,
+            Resample::Lanczos => 3.0,
+            Resample::Mitchell => 2.0,
+        }
+    }
+
+    /// The weight of a pixel `t` pixels of the smaller image away.
+    fn weight(self, t: f32) -> f32 {
+        let t = t.abs();
+        match self {
+            Resample::Box => (t < 0.5) as u8 as f32,
+            Resample::Lanczos if t < 3.0 => sinc(t) * sinc(t / 3.0),
+            Resample::Lanczos => 0.0,
+            Resample::Mitchell => {
+                // B = C = 1/3, as Mitchell and Netravali recommend.
+                let (b, c) = (1.0 / 3.0, 1.0 / 3.0);
+                let value = if t < 1.0 {
+                    (12.0 - 9.0 * b - 6.0 * c) * t * t * t
+                        + (-18.0 + 12.0 * b + 6.0 * c) * t * t
+                        + (6.0 - 2.0 * b)
+                } else if t < 2.0 {
+                    (-b - 6.0 * c) * t * t * t
+                        + (6.0 * b + 30.0 * c) * t * t
+                        + (-12.0 * b - 48.0 * c) * t
+                        + (8.0 * b + 24.0 * c)
+                } else {
+                    0.0
+                };
+                value / 6.0
+            }
+        }
+    }
+
+    /// The pixels of a row of the larger image that each pixel of the
+    /// smaller one is made from: the first of them, and their weights,
+    /// which add up to one.
+    fn taps(self, length: u32, factor: u32) -> Vec<(u32, Vec<f32>)> {
+        let scale = factor as f32;
+        let large = length * factor;
+        (0..length)
+            .map(|i| {
+                let center = (i as f32 + 0.5) * scale;
+                let reach = self.support() * scale;
+                let first = (center - reach).floor().max(0.0) as u32;
+                let last = ((center + reach).ceil() as u32).min(large);
+                let mut weights = (first..last)
+                    .map(|j| self.weight((j as f32 + 0.5 - center) / scale))
+                    .collect::<Vec<_>>();
+                let total: f32 = weights.iter().sum();
+                if total != 0.0 {
+                    weights.iter_mut().for_each(|w| *w /= total);
+                }
+                (first, weights)
+            })
+            .collect()
+    }
+}
+
+fn sinc(x: f32) -> f32 {
+    if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// Wraps a generator to draw its image `factor` times as large and
+/// then scale it back down with a filter, which antialiases
+/// generators that color each pixel on its own.
+pub struct Supersample<G> {
+    generator: G,
+    factor: u32,
+    filter: Resample,
+}
+
+impl<G: Scaled> Supersample<G> {
+    /// Wraps a generator, which is grown by `factor` to draw the
+    /// larger image.  Sizes like its cells and padding are grown along
+    /// with it, so the image comes out looking the same, only
+    /// smoother.
+    pub fn new(generator: G, factor: u32, filter: Resample) -> Result<Self> {
+        if factor == 0 {
+            return Err(Error::parameter(
+                "supersample",
+                "factor",
+                "the image must be drawn at least at its own size",
+            ));
+        }
+        Ok(Supersample {
+            generator: generator.scaled(factor),
+            factor,
+            filter,
+        })
+    }
+}
+
+impl<G: Generator<image::Rgb<u8>>> Generator<image::Rgb<u8>> for Supersample<G> {
+    fn generate(&self, context: &Context) -> Result<image::RgbImage> {
+        let large = self.generator.generate(context)?;
+        if self.factor == 1 {
+            return Ok(large);
+        }
+        Ok(downsample(&large, self.factor, self.filter))
+    }
+}
+
+/// Scales an image down by a whole factor, a row and then a column at
+/// a time.
+fn downsample(image: &image::RgbImage, factor: u32, filter: Resample) -> image::RgbImage {
+    let (width, height) = (image.width() / factor, image.height() / factor);
+    let columns = filter.taps(width, factor);
+    let rows = filter.taps(height, factor);
+
+    // Across first, into a buffer that's still at the larger height.
+    let mut across = vec![[0.0f32; 3]; (width * image.height()) as usize];
+    for y in 0..image.height() {
+        for (x, (first, weights)) in columns.iter().enumerate() {
+            let sum = &mut across[(y * width) as usize + x];
+            for (i, weight) in weights.iter().enumerate() {
+                let pixel = image.get_pixel(first + i as u32, y);
+                for (sum, &channel) in sum.iter_mut().zip(pixel.0.iter()) {
+                    *sum += weight * channel as f32;
+                }
+            }
+        }
+    }
+
+    crate::rows::from_fn(width, height, |x, y| {
+        let (first, weights) = &rows[y as usize];
+        let mut sum = [0.0f32; 3];
+        for (i, weight) in weights.iter().enumerate() {
+            let pixel = across[((first + i as u32) * width + x) as usize];
+            for (sum, channel) in sum.iter_mut().zip(pixel.iter()) {
+                *sum += weight * channel;
+            }
+        }
+        image::Rgb(sum.map(|channel| channel.round().clamp(0.0, 255.0) as u8))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::RandomGenerator;
+    use crate::position::Position;
+
+    #[test]
+    fn test_supersample() {
+        let random = RandomGenerator {
+            block_size: Position::new(4, 4),
+            image_size: Position::new(30, 20),
+            padding: Position::new(3, 2),
+            block_color: crate::hex(0xffffff),
+            background_color: crate::hex(0x000000),
+            distribution: 0.5,
+        };
+        let context = Context::new(3);
+        let plain = random.generate(&context).unwrap();
+        // Blocks that line up with the pixels come out the same from
+        // the box filter.
+        let boxed = Supersample::new(random, 3, Resample::Box).unwrap();
+        assert_eq!(boxed.generate(&context).unwrap(), plain);
+        // The filters that reach past each pixel blur the edges.
+        let smooth = Supersample::new(random, 3, Resample::Mitchell)
+            .unwrap()
+            .generate(&context)
+            .unwrap();
+        assert_eq!(smooth.dimensions(), plain.dimensions());
+        assert_ne!(smooth, plain);
+
+        // The weights add up to one, so flat color stays the same.
+        let full = RandomGenerator {
+            distribution: 1.0,
+            image_size: Position::new(32, 20),
+            padding: Position::new(0, 0),
+            ..random
+        };
+        for &filter in [Resample::Lanczos, Resample::Mitchell].iter() {
+            let image = Supersample::new(full, 2, filter)
+                .unwrap()
+                .generate(&context)
+                .unwrap();
+            assert_eq!(image.dimensions(), (32, 20));
+            assert!(image.pixels().all(|&p| p == crate::hex(0xffffff)));
+        }
+        assert!(Supersample::new(random, 0, Resample::Box).is_err());
+    }
+}
@@ -0,0 +1,170 @@
+//! Gradients with any number of color stops, for mapping a value
+//! from `0.0` to `1.0` to a color.
+
+use crate::color;
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+
+/// The space a gradient blends between its stops in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Interpolation {
+    /// Straight sRGB, like a plain two color blend.  The middle of a
+    /// blend between two bright colors comes out dark and muddy.
+    Srgb,
+    /// Linear light, which blends the way light mixes.
+    Linear,
+    /// OKLab, which keeps the lightness and hue changing evenly.
+    Oklab,
+}
+
+impl Interpolation {
+    fn coordinates(self, rgb: image::Rgb<u8>) -> [f32; 3] {
+        match self {
+            Interpolation::Srgb => color::to_array(rgb),
+            Interpolation::Linear => color::linear(color::to_array(rgb)),
+            Interpolation::Oklab => color::oklab_from_linear(color::linear(color::to_array(rgb))),
+        }
+    }
+
+    fn color(self, v: [f32; 3]) -> image::Rgb<u8> {
+        match self {
+            Interpolation::Srgb => {
+                let channel = |v: f32| v.round().clamp(0.0, 255.0) as u8;
+                image::Rgb([channel(v[0]), channel(v[1]), channel(v[2])])
+            }
+            Interpolation::Linear => color::from_linear(v),
+            Interpolation::Oklab => color::from_linear(color::linear_from_oklab(v)),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Stop {
+    /// Where the stop is, from `0.0` to `1.0`.
+    pub position: f32,
+    pub color: image::Rgb<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Gradient {
+    stops: Vec<Stop>,
+    interpolation: Interpolation,
+    // The colors of the stops, in the interpolation space.
+    points: Vec<[f32; 3]>,
+    hard: bool,
+}
+
+impl Gradient {
+    /// A gradient through the given stops, which have to be in order.
+    /// Two stops at the same position make a hard edge between them.
+    pub fn new(stops: Vec<Stop>, interpolation: Interpolation) -> Result<Self> {
+        if stops.is_empty() {
+            return Err(Error::parameter(
+                "gradient",
+                "stops",
+                "must have at least one stop",
+            ));
+        }
+        if let Some(stop) = stops.iter().find(|s| !(0.0..=1.0).contains(&s.position)) {
+            return Err(Error::parameter(
+                "gradient",
+                "stops",
+                format!("positions must be from 0 to 1, not {}", stop.position),
+            ));
+        }
+        if stops.windows(2).any(|w| w[0].position > w[1].position) {
+            return Err(Error::parameter(
+                "gradient",
+                "stops",
+                "must be in order of their positions",
+            ));
+        }
+
+        Ok(Gradient {
+            points: stops
+                .iter()
+                .map(|s| interpolation.coordinates(s.color))
+                .collect(),
+            stops,
+            interpolation,
+            hard: false,
+        })
+    }
+
+    /// Makes every stop a hard edge, so that each color holds from
+    /// its stop up to the next one, in bands.
+    pub fn with_hard_stops(self) -> Self {
+        Gradient { hard: true, ..self }
+    }
+
+    pub fn stops(&self) -> &[Stop] {
+        &self.stops
+    }
+
+    /// The color at `t`, from `0.0` to `1.0`.  Before the first stop
+    /// and after the last, the gradient keeps their colors.
+    pub fn at(&self, t: f32) -> image::Rgb<u8> {
+        // The first stop after `t`.
+        let next = self.stops.partition_point(|s| s.position <= t);
+        if next == 0 {
+            return self.stops[0].color;
+        }
+        if next == self.stops.len() || self.hard {
+            return self.stops[next - 1].color;
+        }
+
+        let (from, to) = (&self.stops[next - 1], &self.stops[next]);
+        let weight = (t - from.position) / (to.position - from.position);
+        let (a, b) = (self.points[next - 1], self.points[next]);
+        self.interpolation.color([
+            a[0] + (b[0] - a[0]) * weight,
+            a[1] + (b[1] - a[1]) * weight,
+            a[2] + (b[2] - a[2]) * weight,
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stops() {
+        let stops = vec![
+            Stop {
+                position: 0.0,
+                color: crate::hex(0x000000),
+            },
+            Stop {
+                position: 0.5,
+                color: crate::hex(0xffffff),
+            },
+            Stop {
+                position: 0.5,
+                color: crate::hex(0xff0000),
+            },
+            Stop {
+                position: 1.0,
+                color: crate::hex(0x0000ff),
+            },
+        ];
+        let srgb = Gradient::new(stops.clone(), Interpolation::Srgb).unwrap();
+        assert_eq!(srgb.at(0.25), crate::hex(0x808080));
+        assert_eq!(srgb.at(0.5), crate::hex(0xff0000));
+        assert_eq!(srgb.at(2.0), crate::hex(0x0000ff));
+        // Half of the light is brighter than half of the value.
+        let linear = Gradient::new(stops.clone(), Interpolation::Linear).unwrap();
+        assert_eq!(linear.at(0.25), crate::hex(0xbcbcbc));
+
+        let hard = Gradient::new(stops.clone(), Interpolation::Oklab)
+            .unwrap()
+            .with_hard_stops();
+        assert_eq!(hard.at(0.45), crate::hex(0x000000));
+        assert_eq!(hard.at(0.75), crate::hex(0xff0000));
+
+        let mut backwards = stops;
+        backwards.reverse();
+        assert!(Gradient::new(backwards, Interpolation::Oklab).is_err());
+    }
+}
@@ -0,0 +1,217 @@
+//! Wallpapers that span several monitors.
+//!
+//! A layout describes where each monitor physically sits.  The
+//! pipeline is rendered once, to a canvas covering every monitor at
+//! a single DPI, and each monitor then gets its own slice of it.
+//! Since the slices are cut by physical position, the parts of the
+//! canvas hidden behind bezels are skipped over, and anything drawn
+//! across two monitors lines up, even when their DPIs differ.
+
+use crate::error::{Error, Result};
+use crate::position::Position;
+use serde::{Deserialize, Serialize};
+
+const MM_PER_INCH: f64 = 25.4;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Layout {
+    /// The DPI to render the canvas at.  Defaults to the highest DPI
+    /// of any of the monitors, so that none of them are upscaled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dpi: Option<f64>,
+    /// The physical distance between the screens of neighboring
+    /// monitors, in millimeters; this is usually the width of both
+    /// of their bezels.  Only used for monitors without a position.
+    #[serde(default)]
+    pub gap: f64,
+    pub monitors: Vec<Monitor>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Monitor {
+    pub name: String,
+    /// The resolution of the monitor, in pixels.
+    pub size: Position,
+    /// The physical pixel density of the monitor, in pixels per inch.
+    pub dpi: f64,
+    /// Where the top left corner of the screen sits, in millimeters,
+    /// from any point as long as it's the same for every monitor.
+    /// If this isn't given, the monitor is placed to the right of the
+    /// one before it, `gap` away, with their tops lined up.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<[f64; 2]>,
+}
+
+impl Monitor {
+    /// The physical size of the screen, in millimeters.
+    fn physical_size(&self) -> [f64; 2] {
+        [
+            self.size.x() as f64 / self.dpi * MM_PER_INCH,
+            self.size.y() as f64 / self.dpi * MM_PER_INCH,
+        ]
+    }
+}
+
+impl Layout {
+    /// The size of the canvas that covers every monitor.
+    pub fn canvas_size(&self) -> Result<Position> {
+        let placements = self.placements()?;
+        let scale = self.scale();
+        let [width, height] = extent(&placements);
+        Ok(Position::new(
+            (width * scale).ceil() as u32,
+            (height * scale).ceil() as u32,
+        ))
+    }
+
+    /// Cuts the image for the named monitor out of a canvas of the
+    /// size given by [`Layout::canvas_size`].  Monitors with a lower
+    /// DPI than the canvas are scaled down to their resolution.
+    pub fn slice(&self, canvas: &image::RgbImage, name: &str) -> Result<image::RgbImage> {
+        let placements = self.placements()?;
+        let (monitor, [x, y]) = self
+            .monitors
+            .iter()
+            .zip(placements.iter())
+            .find(|(monitor, _)| monitor.name == name)
+            .map(|(monitor, placement)| (monitor, placement.0))
+            .ok_or_else(|| {
+                Error::parameter(
+                    "layout",
+                    "monitors",
+                    format!("there is no monitor named {:?}", name),
+                )
+            })?;
+        let [left, top] = origin(&placements);
+        let scale = self.scale();
+        let [width, height] = monitor.physical_size();
+
+        let clamp = |v: f64, max: u32| (v.round().max(0.0) as u32).min(max);
+        let (canvas_width, canvas_height) = canvas.dimensions();
+        let x = clamp((x - left) * scale, canvas_width);
+        let y = clamp((y - top) * scale, canvas_height);
+        let width = clamp(width * scale, canvas_width - x).max(1);
+        let height = clamp(height * scale, canvas_height - y).max(1);
+
+        let slice = image::imageops::crop_imm(canvas, x, y, width, height).to_image();
+        if slice.dimensions() == (monitor.size.x(), monitor.size.y()) {
+            return Ok(slice);
+        }
+        Ok(image::imageops::resize(
+            &slice,
+            monitor.size.x(),
+            monitor.size.y(),
+            image::imageops::FilterType::Lanczos3,
+        ))
+    }
+
+    /// Pixels on the canvas per millimeter.
+    fn scale(&self) -> f64 {
+        let dpi = self.dpi.unwrap_or_else(|| {
+            self.monitors
+                .iter()
+                .map(|monitor| monitor.dpi)
+                .fold(0.0, f64::max)
+        });
+        dpi / MM_PER_INCH
+    }
+
+    /// The top left corner and size of every monitor, in
+    /// millimeters, in the same order as the monitors.
+    fn placements(&self) -> Result<Vec<([f64; 2], [f64; 2])>> {
+        if self.monitors.is_empty() {
+            return Err(Error::parameter(
+                "layout",
+                "monitors",
+                "must have at least one monitor",
+            ));
+        }
+        if let Some(dpi) = self.dpi.filter(|dpi| !is_positive(*dpi)) {
+            return Err(Error::parameter(
+                "layout",
+                "dpi",
+                format!("must be above 0, not {}", dpi),
+            ));
+        }
+        if let Some(monitor) = self.monitors.iter().find(|m| !is_positive(m.dpi)) {
+            return Err(Error::parameter(
+                "layout",
+                "monitors",
+                format!("the dpi of {:?} must be above 0", monitor.name),
+            ));
+        }
+
+        let mut placements: Vec<([f64; 2], [f64; 2])> = Vec::with_capacity(self.monitors.len());
+        for monitor in self.monitors.iter() {
+            let position = monitor.position.unwrap_or_else(|| match placements.last() {
+                Some(([x, y], [width, _])) => [x + width + self.gap, *y],
+                None => [0.0, 0.0],
+            });
+            placements.push((position, monitor.physical_size()));
+        }
+        Ok(placements)
+    }
+}
+
+// Also false for NaN, which `<= 0.0` isn't.
+fn is_positive(value: f64) -> bool {
+    value > 0.0
+}
+
+fn origin(placements: &[([f64; 2], [f64; 2])]) -> [f64; 2] {
+    placements
+        .iter()
+        .fold([f64::INFINITY; 2], |[x, y], ([px, py], _)| {
+            [x.min(*px), y.min(*py)]
+        })
+}
+
+fn extent(placements: &[([f64; 2], [f64; 2])]) -> [f64; 2] {
+    let [left, top] = origin(placements);
+    let [right, bottom] = placements.iter().fold(
+        [f64::NEG_INFINITY; 2],
+        |[x, y], ([px, py], [width, height])| [x.max(px + width), y.max(py + height)],
+    );
+    [right - left, bottom - top]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(name: &str, size: (u32, u32), dpi: f64) -> Monitor {
+        Monitor {
+            name: name.to_string(),
+            size: size.into(),
+            dpi,
+            position: None,
+        }
+    }
+
+    #[test]
+    fn test_slices_line_up() {
+        // Two monitors of the same physical height, one at twice the
+        // DPI of the other, an inch apart.
+        let layout = Layout {
+            dpi: None,
+            gap: 25.4,
+            monitors: vec![
+                monitor("left", (200, 100), 100.0),
+                monitor("right", (200, 200), 200.0),
+            ],
+        };
+        assert_eq!(layout.canvas_size().unwrap(), Position::new(800, 200));
+
+        let canvas = image::RgbImage::from_fn(800, 200, |x, _| {
+            image::Rgb([if x < 400 { 0 } else { 255 }, 0, 0])
+        });
+        let left = layout.slice(&canvas, "left").unwrap();
+        assert_eq!(left.dimensions(), (200, 100));
+        assert!(left.pixels().all(|p| p.0[0] == 0));
+        let right = layout.slice(&canvas, "right").unwrap();
+        assert_eq!(right.dimensions(), (200, 200));
+        assert!(right.pixels().all(|p| p.0[0] == 255));
+    }
+}
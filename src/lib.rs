@@ -0,0 +1,59 @@
+//! Wallpaper generation as a library.
+//!
+//! An image is made by a [`Generator`] and then passed through any
+//! number of [`Filter`]s.  A [`Pipeline`] chains the two together,
+//! either by hand or from a [`config::Config`] loaded from a TOML or
+//! JSON file:
+//!
+//! ```
+//! use bkgn::filter::BlurFilter;
+//! use bkgn::generator::NoiseGenerator;
+//! use bkgn::{Context, Pipeline, Position};
+//!
+//! let generator = NoiseGenerator::new(
+//!     Position::new(64, 64),
+//!     image::Rgb([0x21, 0x4f, 0x72]),
+//!     image::Rgb([0x7a, 0xdd, 0xaa]),
+//!     4.0,
+//!     1.0,
+//! );
+//! let pipeline = Pipeline::new(generator).with_filter(BlurFilter::new(1.0));
+//! let image = pipeline.render(&Context::new(42)).unwrap();
+//! assert_eq!(image.dimensions(), (64, 64));
+//! ```
+
+pub mod animation;
+pub mod color;
+pub mod config;
+pub mod context;
+pub mod error;
+pub mod filter;
+pub mod generator;
+pub mod gradient;
+pub mod layout;
+pub mod metadata;
+pub mod palette;
+pub mod pipeline;
+pub mod position;
+pub mod registry;
+pub mod rows;
+pub mod scheme;
+pub mod svg;
+
+pub use self::context::Context;
+pub use self::error::{Error, Result};
+pub use self::filter::Filter;
+pub use self::generator::Generator;
+pub use self::pipeline::Pipeline;
+pub use self::position::{Position, Region};
+
+fn rgb(r: u8, g: u8, b: u8) -> image::Rgb<u8> {
+    image::Rgb::from([r, g, b])
+}
+fn hex(hex: u32) -> image::Rgb<u8> {
+    rgb(
+        ((hex & 0xff0000) >> 16) as u8,
+        ((hex & 0x00ff00) >> 8) as u8,
+        (hex & 0x0000ff) as u8,
+    )
+}
@@ -1,155 +1,591 @@
-mod filter;
-mod generator;
-mod position;
+use bkgn::config::Config;
+use bkgn::scheme::{self, Scheme};
+use bkgn::{animation, metadata, pipeline, registry, Context, Pipeline, Position};
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
 
-use self::filter::Filter;
-use self::generator::Generator;
-use self::position::Position;
+/// Generates wallpapers from pipelines of generators and filters.
+#[derive(Debug, StructOpt)]
+enum Command {
+    /// Renders a pipeline to an image.
+    Generate {
+        /// The pipeline to render, as a TOML or JSON file.
+        #[structopt(parse(from_os_str))]
+        pipeline: PathBuf,
+        /// Where to write the image.  Defaults to the name of the
+        /// pipeline, with a `.png` extension, in the current
+        /// directory.
+        #[structopt(short, long, parse(from_os_str))]
+        output: Option<PathBuf>,
+        #[structopt(flatten)]
+        options: RenderOptions,
+        /// The master seed for the pipeline.  Overrides the seed in
+        /// the pipeline, if there is one; if neither is given, a
+        /// random seed is used.
+        #[structopt(long)]
+        seed: Option<u64>,
+    },
+    /// Renders the pipeline recorded in an image made by `generate`
+    /// again.
+    Reproduce {
+        /// The image to read the pipeline from.
+        #[structopt(parse(from_os_str))]
+        image: PathBuf,
+        /// Where to write the image.  Defaults to the name of the
+        /// original image, with `-reproduced` added.
+        #[structopt(short, long, parse(from_os_str))]
+        output: Option<PathBuf>,
+        #[structopt(flatten)]
+        options: RenderOptions,
+    },
+    /// Takes a terminal color scheme from an image: the 16 ANSI
+    /// colors, plus a background, a foreground and an accent.
+    Palette {
+        /// The image to take the scheme from.
+        #[structopt(parse(from_os_str))]
+        image: PathBuf,
+        /// Where to write the scheme, in the format given by the
+        /// extension: `.json`, `.Xresources` or `.sh`.  Can be given
+        /// more than once.  If it isn't given, the scheme is printed.
+        #[structopt(short, long = "output", parse(from_os_str))]
+        outputs: Vec<PathBuf>,
+        /// The format to print the scheme in: `json`, `xresources` or
+        /// `sh`.
+        #[structopt(short, long, default_value = "json")]
+        format: scheme::Format,
+        /// Whether the scheme has a dark or a light background: `auto`,
+        /// `dark` or `light`.
+        #[structopt(long, default_value = "auto")]
+        mode: scheme::Mode,
+        /// The least contrast ratio text may have against the
+        /// background, from 1 to 21.
+        #[structopt(long, default_value = "4.5")]
+        contrast: f32,
+    },
+    /// Lists every generator and filter that can be used in a
+    /// pipeline.
+    List,
+    /// Prints the parameters of a generator or filter.  Use
+    /// `generator.<name>` or `filter.<name>` if the name is used by
+    /// both.
+    Describe { name: String },
+}
+
+#[derive(Debug, StructOpt)]
+struct RenderOptions {
+    /// The format to write the image in, e.g. `png`, `jpg` or `svg`.
+    /// Defaults to the extension of the output path.  Only PNGs
+    /// record the pipeline that made them in a way that can be
+    /// reproduced.  SVGs can only be drawn by the maze, stripe, bars
+    /// and random generators, and skip any filters.
+    #[structopt(short, long, parse(try_from_str = parse_format))]
+    format: Option<Format>,
+    /// Overrides the size of the generated image, as
+    /// `WIDTHxHEIGHT`.  This renders a single image, even if the
+    /// pipeline has targets.
+    #[structopt(short, long, parse(try_from_str = parse_size))]
+    size: Option<Position>,
+    /// Only renders the target with this name.  Can be given more
+    /// than once.
+    #[structopt(short, long = "target")]
+    targets: Vec<String>,
+    /// Writes an animated pipeline as one image per frame, numbered
+    /// from 0000, instead of as a single GIF or PNG.
+    #[structopt(long)]
+    frames: bool,
+}
 
-fn rgb(r: u8, g: u8, b: u8) -> image::Rgb<u8> {
-    image::Rgb::from([r, g, b])
+/// The kind of file an image is written to.
+#[derive(Debug, Copy, Clone)]
+enum Format {
+    Image(image::ImageFormat),
+    /// An SVG document of the shapes that the generator draws.
+    Svg,
 }
-fn hex(hex: u32) -> image::Rgb<u8> {
-    rgb(
-        ((hex & 0xff0000) >> 16) as u8,
-        ((hex & 0x00ff00) >> 8) as u8,
-        (hex & 0x0000ff) as u8,
-    )
+
+impl Format {
+    fn from_path(path: &Path) -> Option<Self> {
+        match path.extension() {
+            Some(extension) if extension.eq_ignore_ascii_case("svg") => Some(Format::Svg),
+            _ => image::ImageFormat::from_path(path).ok().map(Format::Image),
+        }
+    }
 }
 
 fn main() {
-    // test_generate();
-    // let maze = self::generator::maze::generate_maze(|_| 1, 30, 10);
-    // self::generator::maze::print_grid(&maze);
-    phone_generate();
-}
-
-fn phone_generate() {
-    use self::generator::stripe::Stripe;
-    let barrel_scale = 60;
-
-    let width = 2960 / 5;
-
-    // let generator = self::generator::StripeGenerator {
-    //     image_size: Position::new(1440 + barrel_scale * 2, 2960 + barrel_scale * 2),
-    //     background_color: hex(0x111111),
-    //     stripes: vec![
-    //         Stripe::new(width, hex(0xff9900), width / 2),
-    //         Stripe::new(width, hex(0xff9900), width / 2),
-    //         Stripe::new(width, hex(0xff9900), width / 2),
-    //     ],
-    //     top_offset: (2960 / 3),
-    //     error_color: hex(0xff0000),
-    //     stripe_shift: 1200.0, // doesn't do anything anyway
-    // };
-
-    // let background_color = hex(0xf8f8f9);
-    // let block_color = hex(0xff9f00);
-
-    let background_color = hex(0x214f72);
-    let block_color = hex(0x7addaa);
-
-    let generator = self::generator::NoiseGenerator::new(
-        Position::new(1080, 1920),
-        background_color,
-        block_color,
-        4.0,
-        1.0,
-    );
-
-
-    let blur_filter = self::filter::BlurFilter::new(1.0);
-    let shift_filter = self::filter::MarchFilter::new(0.0125, 40, hex(0x333333));
-    let null_filter = self::filter::NullFilter::new(0.0000125, 3440.0 * 60.0, None);
-    let dither_filter = self::filter::DitherFilter::new(2, block_color, background_color);
-    let abberate_filter = self::filter::AbberateFilter::new(0, 4, -4);
-    let scan_filter = self::filter::ScanFilter::new(160 * 3, 0x10 / 2);
-    let noise_filter = self::filter::NoiseFilter::new(0.0325, 0x33, 0x99);
-    let vignette_filter = self::filter::VignetteFilter::new(-0.9, 0.1);
-    let barrel_filter = self::filter::BarrelFilter::new(barrel_scale as f32, hex(0x333333));
-    let crop_filter =
-        self::filter::CropFilter::new(barrel_scale, barrel_scale, barrel_scale, barrel_scale);
-
-    let mut image = time("generate", || generator.generate());
-    time("filter.blur", || blur_filter.filter(&mut image));
-    time("filter.dither", || dither_filter.filter(&mut image));
-    // time("filter.noise", || noise_filter.filter(&mut image));
-    // time("filter.shift", || shift_filter.filter(&mut image));
-    // time("filter.abberate", || abberate_filter.filter(&mut image));
-    // time("filter.null", || null_filter.filter(&mut image));
-    // time("filter.scan", || scan_filter.filter(&mut image));
-    // time("filter.barrel", || barrel_filter.filter(&mut image));
-    // time("filter.vignette", || vignette_filter.filter(&mut image));
-    // time("filter.crop", || crop_filter.filter(&mut image));
-    time("image.save", || {
-        image.save_with_format("test.png", image::ImageFormat::Png)
-    })
-    .expect("could not save image");
-}
-
-fn test_generate() {
-    let barrel_scale = 60;
-    let generator = self::generator::MazeGenerator {
-        cell_size: Position::new(14, 14),
-        wall_size: Position::new(6, 6),
-        image_size: Position::new(3440 + barrel_scale * 2, 1440 + barrel_scale * 2),
-        padding: Position::new(200 - 6 + barrel_scale, 150 - 6 + barrel_scale),
-        // cell_size: Position::new(40, 40),
-        // wall_size: Position::new(10, 10),
-        // image_size: Position::new(50 * 5 + 10, 50 * 5 + 10),
-        // padding: Position::new(0, 0),
-        block_color: hex(0xc0c0c0),
-        // block_color: hex(0xff3300),
-        background_color: hex(0x333333),
-        direction_weights: {
-            let mut weights = std::collections::HashMap::new();
-            weights.insert(self::generator::maze::Direction::West, 30);
-            weights.insert(self::generator::maze::Direction::East, 30);
-            weights
-        },
-        default_weight: 10,
+    match Command::from_args() {
+        Command::Generate {
+            pipeline,
+            output,
+            options,
+            seed,
+        } => {
+            let mut config = Config::load(&pipeline).unwrap_or_else(|e| fail(e));
+            config.seed = seed.or(config.seed);
+            let output = output.unwrap_or_else(|| {
+                let stem = pipeline.file_stem().unwrap_or_else(|| "bkgn".as_ref());
+                Path::new(stem).with_extension("png")
+            });
+
+            render(config, &output, &options, None);
+        }
+        Command::Reproduce {
+            image,
+            output,
+            options,
+        } => {
+            let recorded = metadata::read(&image).unwrap_or_else(|e| {
+                fail(format!("could not reproduce {}: {}", image.display(), e))
+            });
+            if recorded.is_foreign() {
+                eprintln!(
+                    "warning: {} was made by {}, not {}; it may not come out the same",
+                    image.display(),
+                    recorded.software.as_deref().unwrap_or("an unknown version"),
+                    metadata::software()
+                );
+            }
+            let output = output.unwrap_or_else(|| {
+                let stem = image.file_stem().unwrap_or_else(|| "bkgn".as_ref());
+                let mut name = stem.to_os_string();
+                name.push("-reproduced.png");
+                PathBuf::from(name)
+            });
+
+            render(
+                recorded.config,
+                &output,
+                &options,
+                recorded.target.as_deref(),
+            );
+        }
+        Command::Palette {
+            image,
+            outputs,
+            format,
+            mode,
+            contrast,
+        } => {
+            let scheme = image::open(&image)
+                .map_err(bkgn::Error::from)
+                .and_then(|loaded| Scheme::from_image(&loaded.to_rgb(), mode, contrast))
+                .unwrap_or_else(|e| fail(format!("could not read {}: {}", image.display(), e)));
+            if outputs.is_empty() {
+                print!("{}", scheme.export(format));
+            }
+            for path in outputs {
+                let format = scheme::Format::from_path(&path).unwrap_or_else(|| {
+                    fail(format!(
+                        "could not tell the format of {} from its extension",
+                        path.display()
+                    ))
+                });
+                save_scheme(&scheme, &path, format);
+            }
+        }
+        Command::List => list(),
+        Command::Describe { name } => describe(&name),
+    }
+}
+
+fn render(mut config: Config, output: &Path, options: &RenderOptions, only: Option<&str>) {
+    if let Some(size) = options.size {
+        config = config.with_size(size).unwrap_or_else(|e| fail(e));
+        config.targets.clear();
+        config.layout = None;
+    }
+
+    let format = options
+        .format
+        .or_else(|| Format::from_path(output))
+        .unwrap_or_else(|| fail("could not tell the image format from the output path"));
+    if let Some(export) = config.scheme.as_ref() {
+        export.formats().unwrap_or_else(|e| fail(e));
+    }
+
+    let context = config
+        .seed
+        .map(Context::new)
+        .unwrap_or_else(Context::random);
+    config.seed = Some(context.seed());
+
+    eprintln!("seed: {}", context.seed());
+    let format = match format {
+        Format::Image(format) => format,
+        Format::Svg => return render_svg(&config, &context, output, options),
+    };
+    let frames = match config.animation.as_ref() {
+        Some(animation) => {
+            let frames = animation.contexts(&context).unwrap_or_else(|e| fail(e));
+            frames.collect()
+        }
+        None => vec![context],
+    };
+
+    if let Some(layout) = config.layout.as_ref() {
+        if !config.targets.is_empty() {
+            fail("a pipeline can have targets or a layout, but not both");
+        }
+
+        let canvas_size = layout.canvas_size().unwrap_or_else(|e| fail(e));
+        eprintln!("canvas: {}", canvas_size);
+        let canvas_config = config.with_size(canvas_size).unwrap_or_else(|e| fail(e));
+        let pipeline = Pipeline::from_config(&canvas_config).unwrap_or_else(|e| fail(e));
+
+        let names = layout.monitors.iter().map(|monitor| &monitor.name[..]);
+        let mut outputs = select(names, options, only)
+            .into_iter()
+            .map(|name| {
+                let path = match only {
+                    Some(_) => output.to_path_buf(),
+                    None => target_path(output, name),
+                };
+                eprintln!("monitor: {}, to {}", name, path.display());
+                (
+                    name,
+                    Output::new(&config, Some(name), path, format, options),
+                )
+            })
+            .collect::<Vec<_>>();
+        for (i, frame) in frames.iter().enumerate() {
+            let canvas = render_image(&pipeline, frame);
+            for (name, output) in outputs.iter_mut() {
+                let slice = layout.slice(&canvas, name).unwrap_or_else(|e| fail(e));
+                output.write(&slice);
+                if i == 0 {
+                    // Named the same way as the image.
+                    let name = match only {
+                        Some(_) => None,
+                        None => Some(*name),
+                    };
+                    export_scheme(&config, &slice, name);
+                }
+            }
+        }
+        for (_, output) in outputs {
+            output.finish();
+        }
+        return;
+    }
+
+    if config.targets.is_empty() {
+        let output = Output::new(&config, None, output.to_path_buf(), format, options);
+        render_to(&config, &frames, output, None);
+        return;
+    }
+
+    let names = config.targets.iter().map(|target| &target.name[..]);
+    for name in select(names, options, None) {
+        let target = config.targets.iter().find(|t| t.name == name);
+        let target = target.expect("only names of targets are selected");
+        let path = target_path(output, name);
+        eprintln!(
+            "target: {} at {}, to {}",
+            target.name,
+            target.size,
+            path.display()
+        );
+        let config = config.for_target(target).unwrap_or_else(|e| fail(e));
+        let output = Output::new(&config, None, path, format, options);
+        render_to(&config, &frames, output, Some(name));
+    }
+}
+
+/// Draws the pipeline's generator as SVG documents, one for each of
+/// its targets if it has any.
+fn render_svg(config: &Config, context: &Context, output: &Path, options: &RenderOptions) {
+    if config.animation.is_some() {
+        fail("animations can't be saved as svg");
+    }
+    if config.layout.is_some() {
+        fail("layouts can't be saved as svg; use targets instead");
+    }
+    if !config.filters.is_empty() {
+        let names = config.filters.iter().map(|filter| filter.name());
+        eprintln!(
+            "warning: raster filters are unavailable for svg output; skipping the {} filters",
+            names.collect::<Vec<_>>().join(", ")
+        );
+    }
+    if config.scheme.is_some() {
+        eprintln!(
+            "warning: color schemes are taken from images; skipping the scheme for svg output"
+        );
+    }
+
+    if config.targets.is_empty() {
+        save_svg(config, context, output);
+        return;
+    }
+    let names = config.targets.iter().map(|target| &target.name[..]);
+    for name in select(names, options, None) {
+        let target = config.targets.iter().find(|t| t.name == name);
+        let target = target.expect("only names of targets are selected");
+        let path = target_path(output, name);
+        eprintln!(
+            "target: {} at {}, to {}",
+            target.name,
+            target.size,
+            path.display()
+        );
+        let config = config.for_target(target).unwrap_or_else(|e| fail(e));
+        save_svg(&config, context, &path);
+    }
+}
+
+fn save_svg(config: &Config, context: &Context, output: &Path) {
+    let document = time("generate", || pipeline::render_svg(config, context));
+    let document = document.unwrap_or_else(|e| fail(e));
+    let saved = time("image.save", || {
+        metadata::save_svg(document, config, output)
+    });
+    if let Err(e) = saved {
+        fail(format!("could not save {}: {}", output.display(), e));
+    }
+}
+
+/// The names of the targets or monitors to render.  This is all of
+/// them, unless some were picked with `--target`, or `only` is given.
+fn select<'a, I>(names: I, options: &'a RenderOptions, only: Option<&'a str>) -> Vec<&'a str>
+where
+    I: Iterator<Item = &'a str>,
+{
+    let names = names.collect::<Vec<_>>();
+    let picked = match only {
+        Some(name) => vec![name],
+        None => options.targets.iter().map(|name| &name[..]).collect(),
     };
-    // let mut generator = self::generator::BarGenerator {
-    //     image_size: Position::new(1920 + barrel_scale * 2, 1080 + barrel_scale * 2),
-    //     initial_offset: 850,
-    //     vertical: false,
-    //     background_color: hex(0x333333),
-    //     angle: 30f64.to_radians(),
-    //     bars: vec![]
-    // };
-    // let color = hex(0xffffff);
-    // generator.push_bar(0, 128, color);
-    // generator.push_bar(32, 128, color);
-    // generator.push_bar(32, 128, color);
-    // let generator = self::generator::FileGenerator::new("v.png");
-
-    let blur_filter = self::filter::BlurFilter::new(1.0);
-    let shift_filter = self::filter::MarchFilter::new(0.0125, 40, hex(0x333333));
-    let null_filter = self::filter::NullFilter::new(0.0000125, 3440.0 * 60.0, None);
-    let dither_filter = self::filter::DitherFilter::new(2, hex(0xc0c0c0), hex(0x333333));
-    let abberate_filter = self::filter::AbberateFilter::new(0, 4, -4);
-    let scan_filter = self::filter::ScanFilter::new(160 * 3, 0x10 / 2);
-    let noise_filter = self::filter::NoiseFilter::new(0.0325, 0x33, 0x99);
-    let vignette_filter = self::filter::VignetteFilter::new(-0.9, 0.1);
-    let barrel_filter = self::filter::BarrelFilter::new(barrel_scale as f32, hex(0x333333));
-    let crop_filter =
-        self::filter::CropFilter::new(barrel_scale, barrel_scale, barrel_scale, barrel_scale);
-
-    let mut image = time("generate", || generator.generate());
-    time("filter.blur", || blur_filter.filter(&mut image));
-    time("filter.dither", || dither_filter.filter(&mut image));
-    time("filter.noise", || noise_filter.filter(&mut image));
-    time("filter.shift", || shift_filter.filter(&mut image));
-    time("filter.abberate", || abberate_filter.filter(&mut image));
-    time("filter.null", || null_filter.filter(&mut image));
-    time("filter.scan", || scan_filter.filter(&mut image));
-    time("filter.barrel", || barrel_filter.filter(&mut image));
-    time("filter.vignette", || vignette_filter.filter(&mut image));
-    time("filter.crop", || crop_filter.filter(&mut image));
-    time("image.save", || {
-        image.save_with_format("test.png", image::ImageFormat::Png)
+    for name in picked.iter() {
+        if !names.contains(name) {
+            fail(format!(
+                "the pipeline has no target or monitor named {:?}",
+                name
+            ));
+        }
+    }
+
+    if picked.is_empty() {
+        names
+    } else {
+        picked
+    }
+}
+
+/// Renders every frame of the pipeline to a single output.  `name`
+/// is the name of the target being rendered, if there is one.
+fn render_to(config: &Config, frames: &[Context], mut output: Output, name: Option<&str>) {
+    let pipeline = Pipeline::from_config(config).unwrap_or_else(|e| fail(e));
+    for (i, frame) in frames.iter().enumerate() {
+        let image = render_image(&pipeline, frame);
+        output.write(&image);
+        if i == 0 {
+            export_scheme(config, &image, name);
+        }
+    }
+    output.finish();
+}
+
+/// Writes the color scheme the pipeline asks for, if any, taken from
+/// its image.  For targets and monitors, `name` is added to the end of
+/// the file names, the same as for the images.  Animations take their
+/// scheme from the first frame.
+fn export_scheme(config: &Config, image: &image::RgbImage, name: Option<&str>) {
+    let export = match config.scheme.as_ref() {
+        Some(export) => export,
+        None => return,
+    };
+    let scheme = time("scheme", || {
+        Scheme::from_image(image, export.mode, export.contrast)
     })
-    .expect("could not save image");
+    .unwrap_or_else(|e| fail(e));
+    for (path, format) in export.formats().unwrap_or_else(|e| fail(e)) {
+        let path = match name {
+            Some(name) => target_path(path, name),
+            None => path.to_path_buf(),
+        };
+        eprintln!("scheme: to {}", path.display());
+        save_scheme(&scheme, &path, format);
+    }
+}
+
+fn save_scheme(scheme: &Scheme, path: &Path, format: scheme::Format) {
+    if let Err(e) = scheme.save(path, format) {
+        fail(format!("could not save {}: {}", path.display(), e));
+    }
+}
+
+fn render_image(pipeline: &Pipeline, context: &Context) -> image::RgbImage {
+    let image = if context.frames() > 1 {
+        let name = format!("frame {}/{}", context.frame() + 1, context.frames());
+        time(&name, || pipeline.render(context))
+    } else {
+        pipeline.render_with(context, |name, elapsed| {
+            eprintln!("time[{:?}] ... {}ms", name, elapsed.as_millis())
+        })
+    };
+    image.unwrap_or_else(|e| fail(e))
+}
+
+/// Where the images for a single target or monitor are written.
+enum Output<'a> {
+    Still {
+        config: &'a Config,
+        target: Option<&'a str>,
+        path: PathBuf,
+        format: image::ImageFormat,
+    },
+    Animation(PathBuf, Box<animation::Writer>),
+}
+
+impl<'a> Output<'a> {
+    fn new(
+        config: &'a Config,
+        target: Option<&'a str>,
+        path: PathBuf,
+        format: image::ImageFormat,
+        options: &RenderOptions,
+    ) -> Self {
+        let animation = match config.animation.as_ref() {
+            Some(animation) => animation,
+            None => {
+                return Output::Still {
+                    config,
+                    target,
+                    path,
+                    format,
+                }
+            }
+        };
+
+        let encoding = match format {
+            _ if options.frames => animation::Encoding::Frames(format),
+            image::ImageFormat::Gif => animation::Encoding::Gif,
+            image::ImageFormat::Png => animation::Encoding::Apng,
+            _ => {
+                fail("animations can only be saved as a gif or png; use --frames for other formats")
+            }
+        };
+        let writer = animation::Writer::new(&path, encoding, animation, config, target)
+            .unwrap_or_else(|e| fail(e));
+        Output::Animation(path, Box::new(writer))
+    }
+
+    fn write(&mut self, image: &image::RgbImage) {
+        match self {
+            Output::Still {
+                config,
+                target,
+                path,
+                format,
+            } => save(image, config, *target, path, *format),
+            Output::Animation(path, writer) => {
+                if let Err(e) = writer.write_frame(image) {
+                    fail(format!("could not save {}: {}", path.display(), e));
+                }
+            }
+        }
+    }
+
+    fn finish(self) {
+        if let Output::Animation(path, writer) = self {
+            if let Err(e) = writer.finish() {
+                fail(format!("could not save {}: {}", path.display(), e));
+            }
+        }
+    }
+}
+
+fn save(
+    image: &image::RgbImage,
+    config: &Config,
+    target: Option<&str>,
+    output: &Path,
+    format: image::ImageFormat,
+) {
+    let saved = time("image.save", || match format {
+        image::ImageFormat::Png => metadata::save_png(image, config, target, output),
+        _ => image
+            .save_with_format(output, format)
+            .map_err(bkgn::Error::from),
+    });
+    if let Err(e) = saved {
+        fail(format!("could not save {}: {}", output.display(), e));
+    }
+}
+
+/// The path to write a target to, which is the output path with the
+/// name of the target added to the end of the file name.
+fn target_path(output: &Path, name: &str) -> PathBuf {
+    let stem = output.file_stem().unwrap_or_else(|| "bkgn".as_ref());
+    let mut file_name = stem.to_os_string();
+    file_name.push("-");
+    file_name.push(name);
+    if let Some(extension) = output.extension() {
+        file_name.push(".");
+        file_name.push(extension);
+    }
+    output.with_file_name(file_name)
+}
+
+fn list() {
+    for kind in &[registry::Kind::Generator, registry::Kind::Filter] {
+        println!("{}s:", kind);
+        for descriptor in registry::all().filter(|d| d.kind == *kind) {
+            println!("  {:<10} {}", descriptor.name, descriptor.summary);
+        }
+    }
+}
+
+fn describe(name: &str) {
+    let found = registry::find(name);
+    if found.is_empty() {
+        fail(format!(
+            "no generator or filter named {:?}; see `bkgn list`",
+            name
+        ));
+    }
+
+    for (i, descriptor) in found.into_iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!(
+            "{} {}: {}",
+            descriptor.kind, descriptor.name, descriptor.summary
+        );
+        for parameter in descriptor.parameters.iter() {
+            println!(
+                "  {:<18} {:<24} {}",
+                parameter.name,
+                parameter.kind,
+                parameter
+                    .default
+                    .map(|d| format!("(default: {})", d))
+                    .unwrap_or_else(|| "(required)".to_string())
+            );
+            println!("  {:<18} {}", "", parameter.summary);
+        }
+    }
+}
+
+fn parse_format(value: &str) -> Result<Format, String> {
+    // `from_path` is the only way to look a format up by its
+    // extension in this version of `image`.
+    Format::from_path(&Path::new("image").with_extension(value))
+        .ok_or_else(|| format!("unknown format {:?}", value))
+}
+
+fn parse_size(value: &str) -> Result<Position, String> {
+    let parse = |v: &str| v.trim().parse::<u32>().ok();
+    value
+        .split_once('x')
+        .and_then(|(w, h)| Some(Position::new(parse(w)?, parse(h)?)))
+        .ok_or_else(|| format!("expected a size like 1920x1080, got {:?}", value))
+}
+
+fn fail<D: std::fmt::Display>(message: D) -> ! {
+    eprintln!("error: {}", message);
+    std::process::exit(1);
 }
 
 fn time<R, F: FnOnce() -> R>(name: &str, block: F) -> R {
@@ -0,0 +1,201 @@
+//! Records the pipeline that produced an image inside of the image
+//! itself, so that it can be rendered again later.
+//!
+//! The pipeline (including its seed) is stored as JSON in an iTXt
+//! chunk, alongside tEXt chunks with the seed and the version of
+//! bkgn, which image viewers tend to show.  SVG documents record the
+//! same JSON in their `<metadata>`.
+
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::position::Position;
+use crate::svg::Document;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+const PIPELINE_KEY: &str = "bkgn:pipeline";
+const SEED_KEY: &str = "bkgn:seed";
+const TARGET_KEY: &str = "bkgn:target";
+const SOFTWARE_KEY: &str = "Software";
+
+/// The pipeline recorded in an image.
+#[derive(Debug)]
+pub struct Recorded {
+    pub config: Config,
+    /// The software that wrote the image, e.g. `bkgn 0.1.0`.
+    pub software: Option<String>,
+    /// The monitor in the pipeline's layout that the image is for,
+    /// if it's only a slice of the whole image.
+    pub target: Option<String>,
+}
+
+impl Recorded {
+    /// Whether the image was written by a different version of bkgn,
+    /// in which case rendering the pipeline again might not give the
+    /// exact same image.
+    pub fn is_foreign(&self) -> bool {
+        self.software.as_deref() != Some(&software()[..])
+    }
+}
+
+pub fn software() -> String {
+    format!("bkgn {}", env!("CARGO_PKG_VERSION"))
+}
+
+/// Writes the image as a PNG, with the pipeline recorded in it.  If
+/// the image is one monitor's slice of a layout, `target` is the name
+/// of the monitor.
+pub fn save_png(
+    image: &image::RgbImage,
+    config: &Config,
+    target: Option<&str>,
+    path: &Path,
+) -> Result<()> {
+    let file = File::create(path)?;
+    let size = image.dimensions().into();
+    let mut writer = writer(BufWriter::new(file), size, config, target)?;
+    // The trailer is written when the writer is dropped.
+    Ok(writer.write_image_data(image.as_raw())?)
+}
+
+/// Writes an SVG document, with the pipeline recorded in it.
+pub fn save_svg(mut document: Document, config: &Config, path: &Path) -> Result<()> {
+    document.set_metadata(serde_json::to_string(config)?);
+    document.save(path)
+}
+
+/// A PNG writer for an RGB image of the given size, with the header
+/// written and the pipeline already recorded after it.
+pub(crate) fn writer<W: Write>(
+    writer: W,
+    size: Position,
+    config: &Config,
+    target: Option<&str>,
+) -> Result<png::Writer<W>> {
+    let pipeline = serde_json::to_string(config)?;
+    let mut encoder = png::Encoder::new(writer, size.x(), size.y());
+    encoder.set_color(png::ColorType::RGB);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    write_text(&mut writer, SOFTWARE_KEY, &software())?;
+    if let Some(seed) = config.seed {
+        write_text(&mut writer, SEED_KEY, &seed.to_string())?;
+    }
+    if let Some(target) = target {
+        write_itxt(&mut writer, TARGET_KEY, target)?;
+    }
+    write_itxt(&mut writer, PIPELINE_KEY, &pipeline)?;
+    Ok(writer)
+}
+
+/// Writes a tEXt chunk.  Its text is Latin-1, so it should only be
+/// given ASCII.
+fn write_text<W: Write>(writer: &mut png::Writer<W>, keyword: &str, text: &str) -> Result<()> {
+    let data = [keyword.as_bytes(), &[0], text.as_bytes()].concat();
+    Ok(writer.write_chunk(*b"tEXt", &data)?)
+}
+
+/// Writes an uncompressed iTXt chunk, whose text is UTF-8, without a
+/// language or a translated keyword.
+fn write_itxt<W: Write>(writer: &mut png::Writer<W>, keyword: &str, text: &str) -> Result<()> {
+    let data = [keyword.as_bytes(), &[0, 0, 0, 0, 0], text.as_bytes()].concat();
+    Ok(writer.write_chunk(*b"iTXt", &data)?)
+}
+
+/// Reads the pipeline recorded in a PNG written by [`save_png`].
+pub fn read(path: &Path) -> Result<Recorded> {
+    // Decoding the header checks every chunk up to the image data,
+    // which is where the text chunks are, so they can be picked out
+    // of the file as is.
+    png::Decoder::new(BufReader::new(File::open(path)?)).read_info()?;
+    let mut text = text_chunks(&std::fs::read(path)?)?;
+
+    let pipeline = text.remove(PIPELINE_KEY).ok_or(Error::MissingPipeline)?;
+    Ok(Recorded {
+        config: Config::from_json(&pipeline)?,
+        software: text.remove(SOFTWARE_KEY),
+        target: text.remove(TARGET_KEY),
+    })
+}
+
+/// The text in the tEXt and uncompressed iTXt chunks of a PNG before
+/// its image data, by keyword.
+fn text_chunks(png: &[u8]) -> Result<HashMap<String, String>> {
+    let invalid = |reason: &'static str| png::DecodingError::Format(reason.into());
+    let mut text = HashMap::new();
+    // Past the signature.
+    let mut rest = png.get(8..).unwrap_or_default();
+    while rest.len() >= 8 {
+        let length = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+        let kind = &rest[4..8];
+        let data = rest
+            .get(8..8 + length)
+            .ok_or_else(|| invalid("chunk runs past the end of the file"))?;
+        if kind == b"IDAT" {
+            break;
+        }
+
+        let mut fields = data.splitn(2, |&b| b == 0);
+        let keyword = String::from_utf8_lossy(fields.next().unwrap_or_default());
+        let value = fields.next().unwrap_or_default();
+        match kind {
+            // Latin-1, which maps straight onto the first 256
+            // characters.
+            b"tEXt" => {
+                text.insert(keyword.into(), value.iter().map(|&b| b as char).collect());
+            }
+            b"iTXt" => {
+                // The compression flag and method, then the language
+                // and translated keyword.
+                if value.first() != Some(&0) {
+                    return Err(invalid("compressed iTXt chunks are not supported").into());
+                }
+                let value = value.get(2..).unwrap_or_default();
+                let value = value.splitn(3, |&b| b == 0).nth(2).unwrap_or_default();
+                let value = String::from_utf8(value.to_vec())
+                    .map_err(|_| invalid("iTXt chunk is not UTF-8"))?;
+                text.insert(keyword.into(), value);
+            }
+            _ => {}
+        }
+        // The length, the kind, the data and the CRC.
+        rest = rest
+            .get(12 + length..)
+            .ok_or_else(|| invalid("chunk runs past the end of the file"))?;
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut config = Config::from_toml(
+            r##"
+            [generator]
+            type = "random"
+            block_size = [2, 2]
+            image_size = [8, 8]
+            block_color = "#ffffff"
+            background_color = "#000000"
+            "##,
+        )
+        .unwrap();
+        config.seed = Some(1234);
+
+        let path = std::env::temp_dir().join(format!("bkgn-metadata-{}.png", std::process::id()));
+        save_png(&image::RgbImage::new(8, 8), &config, Some("left"), &path).unwrap();
+        let recorded = read(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let recorded = recorded.unwrap();
+        assert_eq!(recorded.config.seed, Some(1234));
+        assert_eq!(recorded.config.generator.name(), "random");
+        assert_eq!(recorded.target.as_deref(), Some("left"));
+        assert!(!recorded.is_foreign());
+    }
+}
@@ -0,0 +1,304 @@
+//! Loading palettes from files, and pulling them out of images.
+//!
+//! Palettes can be read from GIMP's `.gpl` files, or from plain
+//! lists of hex colors, one or more to a line, like the ones that
+//! palette sites hand out.  They can also be extracted from an
+//! image, by median cut or by k-means.
+
+use crate::color::{self, Space};
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// How to pull a palette out of an image.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Extraction {
+    /// Splits the colors of the image in half along their widest
+    /// channel, over and over, and averages each part.  Quick, and
+    /// good at keeping small patches of bright color.
+    MedianCut,
+    /// Starts from the median cut, and then moves each color to the
+    /// middle of the pixels closest to it until they settle, in
+    /// OKLab.  Slower, but closer to the image overall.
+    Kmeans,
+}
+
+/// The most pixels of an image that are looked at when extracting a
+/// palette; bigger images are sampled evenly.
+const SAMPLES: usize = 1 << 16;
+const KMEANS_ROUNDS: usize = 16;
+
+/// Loads a palette from a file.  Files ending in `.gpl` are read as
+/// GIMP palettes, and anything else as a list of hex colors.
+pub fn load(path: &Path) -> Result<Vec<image::Rgb<u8>>> {
+    let source = std::fs::read_to_string(path).map_err(|e| {
+        Error::parameter(
+            "palette",
+            "file",
+            format!("could not load {}: {}", path.display(), e),
+        )
+    })?;
+    let colors = match path.extension().and_then(|e| e.to_str()) {
+        Some("gpl") => parse_gpl(&source),
+        _ => parse_hex(&source),
+    };
+    colors.map_err(|e| Error::parameter("palette", "file", format!("{}: {}", path.display(), e)))
+}
+
+/// Parses a GIMP palette.
+pub fn parse_gpl(source: &str) -> Result<Vec<image::Rgb<u8>>, String> {
+    let mut lines = source.lines().enumerate();
+    match lines.next() {
+        Some((_, header)) if header.trim() == "GIMP Palette" => {}
+        _ => return Err("not a GIMP palette".to_string()),
+    }
+
+    let mut colors = vec![];
+    for (i, line) in lines {
+        let line = line.trim();
+        if line.is_empty()
+            || line.starts_with('#')
+            || line.starts_with("Name:")
+            || line.starts_with("Columns:")
+        {
+            continue;
+        }
+        // The channels may be followed by the name of the color.
+        let channels = line
+            .split_whitespace()
+            .take(3)
+            .map(|v| v.parse::<u8>().ok())
+            .collect::<Option<Vec<_>>>()
+            .filter(|channels| channels.len() == 3)
+            .ok_or_else(|| format!("line {}: expected a color, got {:?}", i + 1, line))?;
+        colors.push(image::Rgb([channels[0], channels[1], channels[2]]));
+    }
+    Ok(colors)
+}
+
+/// Parses a list of hex colors, separated by whitespace or commas.
+/// Lines starting with `;` are comments.
+pub fn parse_hex(source: &str) -> Result<Vec<image::Rgb<u8>>, String> {
+    let mut colors = vec![];
+    for (i, line) in source.lines().enumerate() {
+        if line.trim_start().starts_with(';') {
+            continue;
+        }
+        for word in line.split(|c: char| c.is_whitespace() || c == ',') {
+            if word.is_empty() {
+                continue;
+            }
+            let color = word
+                .parse::<crate::config::Color>()
+                .map_err(|e| format!("line {}: {}", i + 1, e))?;
+            colors.push(color.into());
+        }
+    }
+    Ok(colors)
+}
+
+/// Pulls a palette of at most `count` colors out of an image, sorted
+/// from darkest to lightest.  There are fewer colors if the image
+/// doesn't have enough of them.
+pub fn extract(image: &image::RgbImage, count: usize, method: Extraction) -> Vec<image::Rgb<u8>> {
+    extract_weighted(image, count, method)
+        .into_iter()
+        .map(|(color, _)| color)
+        .collect()
+}
+
+/// Like [`extract`], along with how much of the image each color
+/// covers, from `0.0` to `1.0`.
+pub fn extract_weighted(
+    image: &image::RgbImage,
+    count: usize,
+    method: Extraction,
+) -> Vec<(image::Rgb<u8>, f32)> {
+    let step = (image.width() as usize * image.height() as usize / SAMPLES).max(1);
+    let samples = image
+        .pixels()
+        .step_by(step)
+        .map(|pixel| pixel.0)
+        .collect::<Vec<_>>();
+
+    let mut clusters = median_cut(samples.clone(), count);
+    if method == Extraction::Kmeans {
+        let start = clusters.into_iter().map(|(color, _)| color).collect();
+        clusters = kmeans(&samples, start);
+    }
+
+    let lightness = |c: &image::Rgb<u8>| Space::Oklab.coordinates(color::to_array(*c))[0];
+    clusters.sort_by(|(a, _), (b, _)| lightness(a).total_cmp(&lightness(b)));
+    clusters.dedup_by(|(color, n), (kept, total)| {
+        let same = color == kept;
+        if same {
+            *total += *n;
+        }
+        same
+    });
+    let total = samples.len().max(1) as f32;
+    clusters
+        .into_iter()
+        .map(|(color, n)| (color, n as f32 / total))
+        .collect()
+}
+
+// Both of these give each color along with the number of samples
+// that went into it.
+fn median_cut(samples: Vec<[u8; 3]>, count: usize) -> Vec<(image::Rgb<u8>, usize)> {
+    // The channel each box is widest along, and how wide it is.
+    fn widest(samples: &[[u8; 3]]) -> (usize, u8) {
+        (0..3)
+            .map(|c| {
+                let max = samples.iter().map(|s| s[c]).max().unwrap_or(0);
+                let min = samples.iter().map(|s| s[c]).min().unwrap_or(0);
+                (c, max - min)
+            })
+            .max_by_key(|(_, range)| *range)
+            .expect("there are three channels")
+    }
+
+    let mut boxes = vec![samples];
+    while boxes.len() < count {
+        let next = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, samples)| samples.len() > 1)
+            .map(|(i, samples)| (i, widest(samples)))
+            .filter(|(_, (_, range))| *range > 0)
+            .max_by_key(|(_, (_, range))| *range);
+        let (i, (channel, _)) = match next {
+            Some(next) => next,
+            None => break,
+        };
+
+        let mut samples = boxes.swap_remove(i);
+        samples.sort_unstable_by_key(|s| s[channel]);
+        // Splits where the channel changes closest to the middle, so
+        // that pixels of the same color stay together.
+        let middle = samples.len() / 2;
+        let split = (1..samples.len())
+            .filter(|i| samples[i - 1][channel] != samples[*i][channel])
+            .min_by_key(|i| (*i as i64 - middle as i64).abs())
+            .expect("the box is wider than a single value");
+        let upper = samples.split_off(split);
+        boxes.push(samples);
+        boxes.push(upper);
+    }
+
+    boxes
+        .iter()
+        .filter(|samples| !samples.is_empty())
+        .map(|samples| {
+            let mut sum = [0u64; 3];
+            for sample in samples.iter() {
+                for c in 0..3 {
+                    sum[c] += sample[c] as u64;
+                }
+            }
+            let n = samples.len() as u64;
+            let color = image::Rgb([
+                ((sum[0] + n / 2) / n) as u8,
+                ((sum[1] + n / 2) / n) as u8,
+                ((sum[2] + n / 2) / n) as u8,
+            ]);
+            (color, samples.len())
+        })
+        .collect()
+}
+
+fn kmeans(samples: &[[u8; 3]], start: Vec<image::Rgb<u8>>) -> Vec<(image::Rgb<u8>, usize)> {
+    let to_lab = |rgb: [f32; 3]| Space::Oklab.coordinates(rgb);
+    let points = samples
+        .iter()
+        .map(|s| to_lab([s[0] as f32, s[1] as f32, s[2] as f32]))
+        .collect::<Vec<_>>();
+    let mut centers = start
+        .into_iter()
+        .map(|c| to_lab(color::to_array(c)))
+        .collect::<Vec<_>>();
+    let mut assignments = vec![usize::MAX; points.len()];
+
+    for _ in 0..KMEANS_ROUNDS {
+        let mut changed = false;
+        for (point, assignment) in points.iter().zip(assignments.iter_mut()) {
+            let closest = (0..centers.len())
+                .min_by(|a, b| {
+                    let a = color::distance(*point, centers[*a]);
+                    a.total_cmp(&color::distance(*point, centers[*b]))
+                })
+                .expect("there is at least one center");
+            changed |= *assignment != closest;
+            *assignment = closest;
+        }
+        if !changed {
+            break;
+        }
+
+        let mut sums = vec![([0.0f64; 3], 0usize); centers.len()];
+        for (point, assignment) in points.iter().zip(assignments.iter()) {
+            let (sum, n) = &mut sums[*assignment];
+            for c in 0..3 {
+                sum[c] += point[c] as f64;
+            }
+            *n += 1;
+        }
+        for (center, (sum, n)) in centers.iter_mut().zip(sums) {
+            // A center that lost all of its pixels stays put.
+            if n > 0 {
+                let n = n as f64;
+                *center = [
+                    (sum[0] / n) as f32,
+                    (sum[1] / n) as f32,
+                    (sum[2] / n) as f32,
+                ];
+            }
+        }
+    }
+
+    let mut counts = vec![0; centers.len()];
+    for assignment in assignments {
+        counts[assignment] += 1;
+    }
+    centers
+        .into_iter()
+        .map(|center| color::from_linear(color::linear_from_oklab(center)))
+        .zip(counts)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_gpl() {
+        let colors = parse_gpl(
+            "GIMP Palette\nName: Test\nColumns: 2\n#\n  0  0  0\tBlack\n255 128 7 Orange\n",
+        )
+        .unwrap();
+        assert_eq!(colors, vec![crate::hex(0x000000), crate::hex(0xff8007)]);
+        assert!(parse_gpl("0 0 0\n").is_err());
+        assert_eq!(
+            parse_hex("; comment\n#214f72, 7addaa\n").unwrap(),
+            vec![crate::hex(0x214f72), crate::hex(0x7addaa)]
+        );
+    }
+
+    #[test]
+    fn test_extract_finds_each_color() {
+        let image = image::RgbImage::from_fn(40, 10, |x, _| match x / 10 {
+            0 => crate::hex(0x000000),
+            1 => crate::hex(0xff0000),
+            2 => crate::hex(0x00ff00),
+            _ => crate::hex(0xffffff),
+        });
+        for method in &[Extraction::MedianCut, Extraction::Kmeans] {
+            let colors = extract(&image, 4, *method);
+            assert_eq!(colors.len(), 4);
+            assert_eq!(colors[0], crate::hex(0x000000));
+            assert_eq!(colors[3], crate::hex(0xffffff));
+        }
+    }
+}
@@ -0,0 +1,240 @@
+use crate::config::Config;
+use crate::context::Context;
+use crate::error::{Error, Result};
+use crate::filter::{CropFilter, Filter};
+use crate::generator::Generator;
+use crate::position::Position;
+use crate::svg::Document;
+use std::time::{Duration, Instant};
+
+/// A generator followed by any number of filters, which are applied
+/// in the order they were added.
+///
+/// Every stage is given its own context from [`Context::stage`]: the
+/// generator is stage `0`, and the filters follow from `1`.  This is
+/// the same for pipelines built by hand and from a [`Config`], so the
+/// two render the same image for the same seed.
+pub struct Pipeline {
+    generator: Stage<dyn Generator<image::Rgb<u8>>>,
+    filters: Vec<Stage<dyn Filter>>,
+}
+
+struct Stage<T: ?Sized> {
+    name: String,
+    inner: Box<T>,
+}
+
+impl Pipeline {
+    pub fn new<G: Generator<image::Rgb<u8>> + 'static>(generator: G) -> Self {
+        Pipeline {
+            generator: Stage {
+                name: "generate".to_string(),
+                inner: Box::new(generator),
+            },
+            filters: vec![],
+        }
+    }
+
+    /// Builds the generator and filters described by a config.
+    /// This fails if any of the filters are given invalid
+    /// parameters.
+    ///
+    /// If the config has a bleed, the generator is grown by it on
+    /// every side, and a crop back down to the original size is added
+    /// after the last filter.  If it has a supersample, the generator
+    /// draws a larger image that's scaled back down before the first
+    /// filter.
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let size = config.generator.image_size()?;
+        let bleed = config.bleed.whole(size, "pipeline", "bleed")?;
+
+        let mut generator = config.generator.clone();
+        if bleed > 0 {
+            let name = generator.name();
+            let image_size = generator.image_size_mut().ok_or_else(|| {
+                Error::parameter(
+                    "pipeline",
+                    "bleed",
+                    format!("the {} generator has no size to grow", name),
+                )
+            })?;
+            *image_size = size + Position::new(bleed, bleed) * 2;
+        }
+
+        let mut filters = config
+            .filters
+            .iter()
+            .map(|filter| {
+                Ok(Stage {
+                    name: format!("filter.{}", filter.name()),
+                    inner: filter.build(size)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if bleed > 0 {
+            filters.push(Stage {
+                name: "pipeline.bleed".to_string(),
+                inner: Box::new(CropFilter::new(bleed, bleed, bleed, bleed)),
+            });
+        }
+
+        Ok(Pipeline {
+            generator: Stage {
+                name: format!("generate.{}", generator.name()),
+                inner: generator.build(size, config.supersample.as_ref())?,
+            },
+            filters,
+        })
+    }
+
+    /// Adds a filter to the end of the pipeline.
+    pub fn with_filter<F: Filter + 'static>(mut self, filter: F) -> Self {
+        self.filters.push(Stage {
+            name: "filter".to_string(),
+            inner: Box::new(filter),
+        });
+        self
+    }
+
+    pub fn render(&self, context: &Context) -> Result<image::RgbImage> {
+        self.render_with(context, |_, _| {})
+    }
+
+    /// Renders the image, calling `report` with the name of each
+    /// stage and how long it took once it finishes.
+    pub fn render_with<R: FnMut(&str, Duration)>(
+        &self,
+        context: &Context,
+        mut report: R,
+    ) -> Result<image::RgbImage> {
+        let start = Instant::now();
+        let mut image = self.generator.inner.generate(&context.stage(0))?;
+        report(&self.generator.name, start.elapsed());
+
+        for (i, filter) in self.filters.iter().enumerate() {
+            let stage = context.stage(i as u64 + 1);
+            let start = Instant::now();
+            filter.inner.filter(&mut image, &stage)?;
+            report(&filter.name, start.elapsed());
+        }
+
+        Ok(image)
+    }
+}
+
+// A pipeline can be built once and then moved to, or shared between,
+// other threads to render; this stops compiling if that breaks.
+fn assert_send_sync<T: Send + Sync>() {}
+const _: fn() = assert_send_sync::<Pipeline>;
+
+/// Draws the generator of a config as an SVG document, with the same
+/// context that [`Pipeline::render`] would give it, so the shapes match
+/// the image for the same seed.  Filters only work on images, and so
+/// are left out, along with the bleed that they would be cropped from.
+pub fn render_svg(config: &Config, context: &Context) -> Result<Document> {
+    let size = config.generator.image_size()?;
+    config
+        .generator
+        .build_vector(size)?
+        .generate_svg(&context.stage(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::{BlurFilter, NoiseFilter};
+    use crate::generator::RandomGenerator;
+
+    #[test]
+    fn matches_config() {
+        let config = Config::from_toml(
+            r##"
+            [generator]
+            type = "random"
+            image_size = [32, 24]
+            block_size = [4, 4]
+            padding = [2, 2]
+            block_color = "#ffffff"
+            background_color = "#000000"
+
+            [[filters]]
+            type = "noise"
+            rate = 0.5
+            low = 0
+            high = 255
+
+            [[filters]]
+            type = "blur"
+            sigma = 1.0
+            "##,
+        )
+        .unwrap();
+
+        let pipeline = Pipeline::new(RandomGenerator {
+            block_size: Position::new(4, 4),
+            image_size: Position::new(32, 24),
+            padding: Position::new(2, 2),
+            block_color: crate::hex(0xffffff),
+            background_color: crate::hex(0x000000),
+            distribution: 0.5,
+        })
+        .with_filter(NoiseFilter::new(0.5, 0, 255).unwrap())
+        .with_filter(BlurFilter::new(1.0));
+
+        let context = Context::new(7);
+        let expected = Pipeline::from_config(&config).unwrap().render(&context);
+        assert_eq!(pipeline.render(&context).unwrap(), expected.unwrap());
+    }
+
+    #[test]
+    fn test_lengths_across_targets() {
+        let config = Config::from_toml(
+            r##"
+            targets = [
+                { name = "small", size = [100, 50] },
+                { name = "large", size = [200, 100] },
+            ]
+
+            [generator]
+            type = "bars"
+            image_size = [100, 50]
+            initial_offset = "10%"
+            background_color = "#000000"
+            bars = [{ gap = "4%", size = "20%", color = "#ffffff" }]
+            "##,
+        )
+        .unwrap();
+        let mut stripes = config.clone();
+        stripes.generator = toml::from_str(
+            r##"
+            type = "stripe"
+            image_size = [100, 50]
+            top_offset = "14%"
+            background_color = "#000000"
+            stripes = [{ width = "20%", color = "#ffffff", padding_bottom = "80%" }]
+            "##,
+        )
+        .unwrap();
+        // The design is drawn at twice the size in the larger target.
+        // Bars start a pixel past their offset, and so come out a pixel
+        // narrower than the stripes.
+        let expected = [(8..17, 15..34), (7..17, 14..34)];
+        for (config, (small, large)) in [config, stripes].iter().zip(expected.iter()) {
+            let rows = config
+                .targets
+                .iter()
+                .map(|target| {
+                    let image = Pipeline::from_config(&config.for_target(target).unwrap())
+                        .unwrap()
+                        .render(&Context::new(1))
+                        .unwrap();
+                    (0..image.height())
+                        .filter(|&y| image.get_pixel(0, y)[0] == 0xff)
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(rows[0], small.clone().collect::<Vec<_>>());
+            assert_eq!(rows[1], large.clone().collect::<Vec<_>>());
+        }
+    }
+}
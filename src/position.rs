@@ -1,4 +1,6 @@
-#[derive(Debug, Copy, Clone)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Position(u32, u32);
 
 impl Position {
@@ -11,6 +13,26 @@ impl Position {
     pub fn y(&self) -> u32 {
         self.1
     }
+
+    /// Subtracts the other position from this one, or returns `None`
+    /// if either component would go below zero.
+    pub fn checked_sub(self, other: Position) -> Option<Position> {
+        Some(Position(
+            self.0.checked_sub(other.0)?,
+            self.1.checked_sub(other.1)?,
+        ))
+    }
+
+    /// Whether either of the components is zero.
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0 || self.1 == 0
+    }
+}
+
+impl std::fmt::Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}, {}]", self.0, self.1)
+    }
 }
 
 impl From<(u32, u32)> for Position {
@@ -0,0 +1,122 @@
+//! Descriptions of every generator and filter that can be named in
+//! a pipeline.  These are what `bkgn list` and `bkgn describe` print;
+//! the entries themselves live next to the generators and filters
+//! in `generator/mod.rs` and `filter/mod.rs`.
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Kind {
+    Generator,
+    Filter,
+}
+
+impl std::fmt::Display for Kind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Kind::Generator => f.write_str("generator"),
+            Kind::Filter => f.write_str("filter"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Descriptor {
+    /// The `type` used for this in a pipeline.
+    pub name: &'static str,
+    pub kind: Kind,
+    pub summary: &'static str,
+    pub parameters: &'static [Parameter],
+}
+
+#[derive(Debug)]
+pub struct Parameter {
+    pub name: &'static str,
+    /// A short, human-readable type, e.g. `color` or `[u32, u32]`.
+    pub kind: &'static str,
+    /// The value used when the parameter isn't given.  If this is
+    /// `None`, the parameter is required.
+    pub default: Option<&'static str>,
+    pub summary: &'static str,
+}
+
+impl Parameter {
+    pub const fn required(name: &'static str, kind: &'static str, summary: &'static str) -> Self {
+        Parameter {
+            name,
+            kind,
+            default: None,
+            summary,
+        }
+    }
+
+    pub const fn optional(
+        name: &'static str,
+        kind: &'static str,
+        default: &'static str,
+        summary: &'static str,
+    ) -> Self {
+        Parameter {
+            name,
+            kind,
+            default: Some(default),
+            summary,
+        }
+    }
+}
+
+pub fn all() -> impl Iterator<Item = &'static Descriptor> {
+    crate::generator::GENERATORS
+        .iter()
+        .chain(crate::filter::FILTERS.iter())
+}
+
+/// Finds every descriptor with the given name.  A name can match
+/// both a generator and a filter (e.g. `noise`); to pick just one,
+/// the name can be qualified, as in `filter.noise`.
+pub fn find(name: &str) -> Vec<&'static Descriptor> {
+    let (kind, name) = match name.split_once('.') {
+        Some(("generator", name)) => (Some(Kind::Generator), name),
+        Some(("filter", name)) => (Some(Kind::Filter), name),
+        _ => (None, name),
+    };
+
+    all()
+        .filter(|d| d.name == name && kind.map(|k| k == d.kind).unwrap_or(true))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{FilterConfig, GeneratorConfig};
+
+    fn is_known<T: serde::de::DeserializeOwned>(name: &str) -> bool {
+        let source = format!("{{ \"type\": {:?} }}", name);
+        match serde_json::from_str::<T>(&source) {
+            Ok(_) => true,
+            Err(e) => !e.to_string().contains("unknown variant"),
+        }
+    }
+
+    #[test]
+    fn test_descriptors_match_config() {
+        for descriptor in all() {
+            let known = match descriptor.kind {
+                Kind::Generator => is_known::<GeneratorConfig>(descriptor.name),
+                Kind::Filter => is_known::<FilterConfig>(descriptor.name),
+            };
+            assert!(
+                known,
+                "{} {} is not in the config",
+                descriptor.kind, descriptor.name
+            );
+        }
+    }
+
+    #[test]
+    fn test_find_qualified() {
+        assert_eq!(find("noise").len(), 2);
+        assert_eq!(find("filter.noise").len(), 1);
+        assert_eq!(find("filter.noise")[0].kind, Kind::Filter);
+        assert!(find("generator.blur").is_empty());
+    }
+}
@@ -0,0 +1,101 @@
+//! Row-at-a-time access to images, which is spread over every core
+//! when the `parallel` feature is enabled (as it is by default).
+//!
+//! Every row is handed the same inputs however the rows end up being
+//! scheduled, so an image comes out the same with or without the
+//! feature.  Anything random in a row should come from
+//! [`Context::row_rng`](crate::context::Context::row_rng) for the
+//! same reason.
+
+use image::{ImageBuffer, Pixel};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// A single row of an image.
+pub struct Row<'a, P: Pixel> {
+    y: u32,
+    data: &'a mut [P::Subpixel],
+}
+
+impl<'a, P: Pixel> Row<'a, P> {
+    pub fn y(&self) -> u32 {
+        self.y
+    }
+
+    pub fn width(&self) -> u32 {
+        (self.data.len() / channels::<P>()) as u32
+    }
+
+    pub fn get(&self, x: u32) -> P {
+        *P::from_slice(&self.data[range::<P>(x)])
+    }
+
+    pub fn get_mut(&mut self, x: u32) -> &mut P {
+        P::from_slice_mut(&mut self.data[range::<P>(x)])
+    }
+
+    /// The pixels of the row, from left to right.
+    pub fn pixels_mut(&mut self) -> impl Iterator<Item = &mut P> {
+        self.data
+            .chunks_exact_mut(channels::<P>())
+            .map(P::from_slice_mut)
+    }
+}
+
+fn channels<P: Pixel>() -> usize {
+    P::CHANNEL_COUNT as usize
+}
+
+fn range<P: Pixel>(x: u32) -> std::ops::Range<usize> {
+    let start = x as usize * channels::<P>();
+    start..(start + channels::<P>())
+}
+
+/// Calls `f` with every row of the image.
+pub fn for_each_row<P, F>(image: &mut ImageBuffer<P, Vec<P::Subpixel>>, f: F)
+where
+    P: Pixel + 'static,
+    P::Subpixel: Send + Sync,
+    F: Fn(Row<P>) + Send + Sync,
+{
+    let stride = image.width() as usize * channels::<P>();
+    if stride == 0 {
+        return;
+    }
+
+    let data: &mut [P::Subpixel] = image;
+    let row = |(y, data)| f(Row { y: y as u32, data });
+
+    #[cfg(feature = "parallel")]
+    data.par_chunks_mut(stride).enumerate().for_each(row);
+    #[cfg(not(feature = "parallel"))]
+    data.chunks_mut(stride).enumerate().for_each(row);
+}
+
+/// Like [`ImageBuffer::from_fn`], but a row at a time.
+pub fn from_fn<P, F>(width: u32, height: u32, f: F) -> ImageBuffer<P, Vec<P::Subpixel>>
+where
+    P: Pixel + 'static,
+    P::Subpixel: Send + Sync,
+    F: Fn(u32, u32) -> P + Send + Sync,
+{
+    let mut image = ImageBuffer::new(width, height);
+    for_each_row(&mut image, |mut row| {
+        let y = row.y();
+        for (x, pixel) in row.pixels_mut().enumerate() {
+            *pixel = f(x as u32, y);
+        }
+    });
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_from_fn() {
+        let f = |x: u32, y: u32| image::Rgb([x as u8, y as u8, (x * y) as u8]);
+        assert_eq!(from_fn(37, 23, f), ImageBuffer::from_fn(37, 23, f));
+    }
+}
@@ -0,0 +1,435 @@
+//! Terminal color schemes taken from a wallpaper.
+//!
+//! A scheme has the 16 ANSI colors, plus a background, a foreground
+//! and an accent.  The background is the color that covers most of
+//! the image, pushed dark or light enough to read text on, and every
+//! ANSI color takes its hue from the closest color in the image,
+//! while staying close enough to its usual hue that red still reads
+//! as red.  Everything meant to be read is then made bright or dark
+//! enough to keep a minimum contrast against the background.
+
+use crate::color::{self, Space};
+use crate::error::{Error, Result};
+use crate::palette::{self, Extraction};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Whether a scheme has a dark or a light background.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mode {
+    /// Dark if the image is mostly dark, and light otherwise.
+    #[default]
+    Auto,
+    Dark,
+    Light,
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Mode, String> {
+        match value {
+            "auto" => Ok(Mode::Auto),
+            "dark" => Ok(Mode::Dark),
+            "light" => Ok(Mode::Light),
+            _ => Err(format!(
+                "unknown mode {:?}; expected auto, dark or light",
+                value
+            )),
+        }
+    }
+}
+
+/// The formats a scheme can be written in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Format {
+    /// An object with `background`, `foreground`, `accent` and a
+    /// `colors` array, all as hex strings.
+    Json,
+    /// X resources, for `xrdb`.
+    Xresources,
+    /// Shell variables, for `source`-ing.
+    Shell,
+}
+
+impl Format {
+    /// Picks a format from the extension of a path: `.json`,
+    /// `.Xresources` (or `.Xdefaults`), or `.sh`.
+    pub fn from_path(path: &Path) -> Option<Format> {
+        let name = path.file_name()?.to_str()?;
+        let extension = name.rsplit('.').next()?;
+        if extension == name && !name.starts_with('.') {
+            return None;
+        }
+        extension.parse().ok()
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Format, String> {
+        match &value.to_lowercase()[..] {
+            "json" => Ok(Format::Json),
+            "xresources" | "xdefaults" => Ok(Format::Xresources),
+            "sh" | "shell" => Ok(Format::Shell),
+            _ => Err(format!(
+                "unknown scheme format {:?}; expected json, xresources or sh",
+                value
+            )),
+        }
+    }
+}
+
+/// Writes a color scheme taken from the final image of a pipeline.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Export {
+    /// The files to write the scheme to.  Each is written in the
+    /// format given by its extension; see [`Format::from_path`].
+    pub files: Vec<PathBuf>,
+    #[serde(default)]
+    pub mode: Mode,
+    /// The least contrast ratio text may have against the
+    /// background, from `1.0` to `21.0`.
+    #[serde(default = "default_contrast")]
+    pub contrast: f32,
+}
+
+/// The contrast WCAG asks for between text and its background.
+pub fn default_contrast() -> f32 {
+    4.5
+}
+
+impl Export {
+    /// Checks that every file has a format, and returns them along
+    /// with it.
+    pub fn formats(&self) -> Result<Vec<(&Path, Format)>> {
+        self.files
+            .iter()
+            .map(|path| match Format::from_path(path) {
+                Some(format) => Ok((path.as_path(), format)),
+                None => Err(Error::parameter(
+                    "scheme",
+                    "files",
+                    format!(
+                        "could not tell the format of {} from its extension",
+                        path.display()
+                    ),
+                )),
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scheme {
+    pub background: image::Rgb<u8>,
+    pub foreground: image::Rgb<u8>,
+    pub accent: image::Rgb<u8>,
+    /// The ANSI colors, from black (0) to bright white (15).
+    pub colors: [image::Rgb<u8>; 16],
+}
+
+/// The OKLab hues of the six ANSI colors that have one, in order
+/// from red (1) to cyan (6), in degrees.  Yellow leans towards amber,
+/// like it does in most schemes, since a pure yellow turns olive once
+/// it's dark enough to read on a light background.
+const HUES: [f32; 6] = [29.2, 142.5, 85.0, 264.1, 328.4, 194.8];
+/// How far the hue of an ANSI color may be pulled towards a color in
+/// the image, in degrees.  It only goes halfway there, so that
+/// neighboring colors pulled towards the same one stay apart.
+const HUE_PULL: f32 = 15.0;
+/// Colors with less chroma than this count as gray, and don't lend
+/// their hue to anything.
+const GRAY: f32 = 0.04;
+/// Most of the colors a scheme can be built from.
+const CLUSTERS: usize = 16;
+
+impl Scheme {
+    /// Takes a scheme from an image.  Text colors keep at least
+    /// `contrast` against the background, or get as close to it as
+    /// black or white can.
+    pub fn from_image(image: &image::RgbImage, mode: Mode, contrast: f32) -> Result<Scheme> {
+        if !(1.0..=21.0).contains(&contrast) {
+            return Err(Error::parameter(
+                "scheme",
+                "contrast",
+                format!("must be from 1 to 21, not {}", contrast),
+            ));
+        }
+        let extracted = palette::extract_weighted(image, CLUSTERS, Extraction::Kmeans);
+        if extracted.is_empty() {
+            return Err(Error::parameter("scheme", "image", "the image is empty"));
+        }
+        let dark = match mode {
+            Mode::Dark => true,
+            Mode::Light => false,
+            Mode::Auto => {
+                let brightness = extracted
+                    .iter()
+                    .map(|(color, share)| color::brightness(color) * share)
+                    .sum::<f32>();
+                brightness < 0.5
+            }
+        };
+        let clusters = extracted
+            .into_iter()
+            .map(|(color, share)| (Lch::from(color), share))
+            .collect::<Vec<_>>();
+        // Which way text moves to get away from the background.
+        let away = if dark { 1.0 } else { -1.0 };
+
+        // The background comes from the color that covers the most
+        // of the image, favoring the darker colors on a dark scheme and
+        // the lighter ones on a light one.
+        let weight = |(lch, share): &(Lch, f32)| match dark {
+            true => share * (1.0 - lch.l),
+            false => share * lch.l,
+        };
+        let (index, (dominant, _)) = clusters
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| weight(a).total_cmp(&weight(b)))
+            .expect("there is at least one color");
+        let background = Lch {
+            l: if dark {
+                dominant.l.min(0.3)
+            } else {
+                dominant.l.max(0.9)
+            },
+            c: dominant.c.min(0.05),
+            h: dominant.h,
+        }
+        .to_rgb();
+        let readable = |lch: Lch, contrast: f32| lch.readable(background, contrast, away);
+
+        let foreground = readable(
+            Lch {
+                l: if dark { 0.93 } else { 0.25 },
+                c: dominant.c.min(0.02),
+                h: dominant.h,
+            },
+            contrast,
+        );
+
+        let chromatic = clusters
+            .iter()
+            .filter(|(lch, _)| lch.c >= GRAY)
+            .collect::<Vec<_>>();
+        let chroma = match chromatic.len() {
+            0 => 0.12,
+            n => chromatic.iter().map(|(lch, _)| lch.c).sum::<f32>() / n as f32,
+        };
+
+        let mut colors = [background; 16];
+        // The grays run from next to the background out to the
+        // foreground.  Black and bright black are the ones closest to
+        // the background on a dark scheme, and white and bright white
+        // on a light one.  The second of them is for dimmed text, so
+        // it only needs the contrast WCAG asks of large text.
+        let grays = if dark { [0, 8, 7, 15] } else { [15, 7, 8, 0] };
+        let (from, to) = (Lch::from(background).l, Lch::from(foreground).l);
+        let gray = |t: f32| Lch {
+            l: from + (to - from) * t,
+            c: dominant.c.min(0.02),
+            h: dominant.h,
+        };
+        colors[grays[0]] = gray(0.1).to_rgb();
+        colors[grays[1]] = readable(gray(0.45), contrast.min(3.0));
+        colors[grays[2]] = readable(gray(0.8), contrast);
+        colors[grays[3]] = foreground;
+
+        for (i, target) in HUES.iter().enumerate() {
+            let closest = chromatic
+                .iter()
+                .map(|(lch, _)| (lch, hue_difference(lch.h, *target)))
+                .min_by(|(_, a), (_, b)| a.abs().total_cmp(&b.abs()));
+            let (h, c) = match closest {
+                Some((lch, difference)) if difference.abs() < 4.0 * HUE_PULL => {
+                    let pull = (difference / 2.0).clamp(-HUE_PULL, HUE_PULL);
+                    (target + pull, lch.c)
+                }
+                _ => (*target, chroma),
+            };
+            let c = c.clamp(0.08, 0.2);
+            // Bright colors are more colorful as well as lighter, so
+            // they still stand out on a light scheme, where keeping
+            // the contrast can pull them back to the same lightness.
+            let (normal, bright) = if dark { (0.7, 0.8) } else { (0.5, 0.56) };
+            colors[i + 1] = readable(Lch { l: normal, c, h }, contrast);
+            colors[i + 9] = readable(
+                Lch {
+                    l: bright,
+                    c: c * 1.25,
+                    h,
+                },
+                contrast,
+            );
+        }
+
+        // The most colorful color that covers a fair part of the
+        // image, besides the background, or blue if it's all gray.
+        let accent = clusters
+            .iter()
+            .enumerate()
+            .filter(|(i, (lch, _))| *i != index && lch.c >= GRAY)
+            .map(|(_, cluster)| cluster)
+            .max_by(|(a, a_share), (b, b_share)| {
+                (a.c * a_share.sqrt()).total_cmp(&(b.c * b_share.sqrt()))
+            })
+            .map(|(lch, _)| readable(*lch, contrast))
+            .unwrap_or(colors[4]);
+
+        Ok(Scheme {
+            background,
+            foreground,
+            accent,
+            colors,
+        })
+    }
+
+    /// The scheme, written in the given format.
+    pub fn export(&self, format: Format) -> String {
+        let hex = color::to_hex;
+        if format == Format::Json {
+            let json = serde_json::json!({
+                "background": hex(self.background),
+                "foreground": hex(self.foreground),
+                "accent": hex(self.accent),
+                "colors": self.colors.iter().map(|c| hex(*c)).collect::<Vec<_>>(),
+            });
+            return serde_json::to_string_pretty(&json).expect("hex strings are valid JSON") + "\n";
+        }
+
+        // X has no name for an accent, so it colors the cursor.
+        let accent = match format {
+            Format::Xresources => "cursorColor",
+            _ => "accent",
+        };
+        let mut entries = vec![
+            ("background".to_string(), self.background),
+            ("foreground".to_string(), self.foreground),
+            (accent.to_string(), self.accent),
+        ];
+        for (i, color) in self.colors.iter().enumerate() {
+            entries.push((format!("color{}", i), *color));
+        }
+
+        let (comment, line): (_, fn(&str, String) -> String) = match format {
+            Format::Xresources => ("!", |name, hex| format!("*{}: {}\n", name, hex)),
+            _ => ("#", |name, hex| format!("{}='{}'\n", name, hex)),
+        };
+        let mut out = format!("{} Made by {}\n", comment, crate::metadata::software());
+        for (name, color) in entries {
+            out.push_str(&line(&name, hex(color)));
+        }
+        out
+    }
+
+    /// Writes the scheme to a file, in the given format.
+    pub fn save(&self, path: &Path, format: Format) -> Result<()> {
+        Ok(std::fs::write(path, self.export(format))?)
+    }
+}
+
+/// A color in OKLab's polar form: lightness, chroma and hue, in
+/// degrees.
+#[derive(Debug, Copy, Clone)]
+struct Lch {
+    l: f32,
+    c: f32,
+    h: f32,
+}
+
+impl From<image::Rgb<u8>> for Lch {
+    fn from(color: image::Rgb<u8>) -> Self {
+        let [l, a, b] = Space::Oklab.coordinates(color::to_array(color));
+        Lch {
+            l,
+            c: a.hypot(b),
+            h: b.atan2(a).to_degrees().rem_euclid(360.0),
+        }
+    }
+}
+
+impl Lch {
+    /// The closest color sRGB can show, keeping the lightness and hue
+    /// and giving up chroma.
+    fn to_rgb(self) -> image::Rgb<u8> {
+        let l = self.l.clamp(0.0, 1.0);
+        let (sin, cos) = self.h.to_radians().sin_cos();
+        let mut c = self.c;
+        loop {
+            let linear = color::linear_from_oklab([l, c * cos, c * sin]);
+            if c < 1e-3 || linear.iter().all(|v| (-1e-4..=1.0 + 1e-4).contains(v)) {
+                return color::from_linear(linear);
+            }
+            c *= 0.9;
+        }
+    }
+
+    /// The color, made lighter or darker, in the direction of `away`,
+    /// until it has at least `contrast` against the background.
+    fn readable(mut self, background: image::Rgb<u8>, contrast: f32, away: f32) -> image::Rgb<u8> {
+        loop {
+            let rgb = self.to_rgb();
+            if color::contrast(rgb, background) >= contrast || !(0.0..=1.0).contains(&self.l) {
+                return rgb;
+            }
+            self.l += away * 0.01;
+        }
+    }
+}
+
+/// How far hue `a` is from `b`, the short way around, from `-180.0`
+/// to `180.0`.
+fn hue_difference(a: f32, b: f32) -> f32 {
+    (a - b + 180.0).rem_euclid(360.0) - 180.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scheme_is_readable() {
+        // A dark blue wallpaper with a streak of orange.
+        let image = image::RgbImage::from_fn(64, 64, |x, _| {
+            if x < 8 {
+                crate::hex(0xe07020)
+            } else {
+                crate::hex(0x102848)
+            }
+        });
+        let scheme = Scheme::from_image(&image, Mode::Auto, 4.5).unwrap();
+        assert!(color::brightness(&scheme.background) < 0.3);
+        for (i, color) in scheme.colors.iter().enumerate() {
+            if ![0, 8].contains(&i) {
+                assert!(
+                    color::contrast(*color, scheme.background) >= 4.5,
+                    "color{}",
+                    i
+                );
+            }
+        }
+        let [r, g, b] = scheme.colors[1].0;
+        assert!(r > g && r > b);
+        // The accent is the orange, more or less.
+        let [r, g, b] = scheme.accent.0;
+        assert!(r > g && g > b);
+
+        assert_eq!(
+            Format::from_path(Path::new("theme.json")),
+            Some(Format::Json)
+        );
+        assert_eq!(
+            Format::from_path(Path::new(".Xresources")),
+            Some(Format::Xresources)
+        );
+        let shell = scheme.export(Format::Shell);
+        assert!(shell.contains(&format!("color1='{}'", color::to_hex(scheme.colors[1]))));
+    }
+}
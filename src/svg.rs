@@ -0,0 +1,239 @@
+//! SVG documents, for the generators that draw shapes and can be
+//! scaled to any size.
+//!
+//! Shapes are placed in pixels, the same as in the image that the
+//! generator would render, and the document is the same size as that
+//! image.  Pixel `(x, y)` covers the square from `(x, y)` to
+//! `(x + 1, y + 1)`.
+
+use crate::error::Result;
+use std::fmt::{self, Write as _};
+use std::path::Path;
+
+/// A point in the document, in pixels.
+pub type Point = [f32; 2];
+
+pub struct Document {
+    width: u32,
+    height: u32,
+    metadata: Option<String>,
+    body: String,
+    // How many gradients there are, for giving each its own id.
+    gradients: u32,
+}
+
+impl Document {
+    /// An empty document, filled with `background`.
+    pub fn new(width: u32, height: u32, background: image::Rgb<u8>) -> Self {
+        let mut document = Document {
+            width,
+            height,
+            metadata: None,
+            body: String::new(),
+            gradients: 0,
+        };
+        document.rect([0.0, 0.0], [width as f32, height as f32], background);
+        document
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Records some text in the document that isn't drawn, like the
+    /// pipeline that made it.
+    pub fn set_metadata<S: Into<String>>(&mut self, text: S) {
+        self.metadata = Some(text.into());
+    }
+
+    /// Fills a rectangle from its top left corner.  Rectangles aren't
+    /// antialiased, since ones that meet edge to edge would show thin
+    /// seams between them if they were.
+    pub fn rect(&mut self, [x, y]: Point, [width, height]: Point, color: image::Rgb<u8>) {
+        if width <= 0.0 || height <= 0.0 {
+            return;
+        }
+        let _ = writeln!(
+            self.body,
+            r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}" shape-rendering="crispEdges"/>"#,
+            Number(x),
+            Number(y),
+            Number(width),
+            Number(height),
+            Color(color)
+        );
+    }
+
+    /// Fills a polygon.
+    pub fn polygon(&mut self, points: &[Point], color: image::Rgb<u8>) {
+        let _ = writeln!(
+            self.body,
+            r#"<polygon points="{}" fill="{}"/>"#,
+            Points(points),
+            Color(color)
+        );
+    }
+
+    /// Fills a polygon with a gradient that runs straight from `from`
+    /// to `to`, through `stops` at positions from `0.0` to `1.0`.
+    /// Beyond its ends, the gradient keeps the colors of the first and
+    /// last stops.
+    pub fn polygon_gradient(
+        &mut self,
+        points: &[Point],
+        from: Point,
+        to: Point,
+        stops: &[(f32, image::Rgb<u8>)],
+    ) {
+        let id = self.gradients;
+        self.gradients += 1;
+        let _ = writeln!(
+            self.body,
+            r#"<linearGradient id="gradient{}" gradientUnits="userSpaceOnUse" x1="{}" y1="{}" x2="{}" y2="{}">"#,
+            id,
+            Number(from[0]),
+            Number(from[1]),
+            Number(to[0]),
+            Number(to[1])
+        );
+        for &(position, color) in stops {
+            let _ = writeln!(
+                self.body,
+                r#"<stop offset="{}" stop-color="{}"/>"#,
+                Number(position),
+                Color(color)
+            );
+        }
+        self.body.push_str("</linearGradient>\n");
+        let _ = writeln!(
+            self.body,
+            r#"<polygon points="{}" fill="url(#gradient{})"/>"#,
+            Points(points),
+            id
+        );
+    }
+
+    /// Draws a line through `points`, `width` pixels wide, with round
+    /// ends and joins.
+    pub fn polyline(&mut self, points: &[Point], width: f32, color: image::Rgb<u8>) {
+        if points.len() < 2 || width <= 0.0 {
+            return;
+        }
+        let _ = writeln!(
+            self.body,
+            r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="{}" stroke-linecap="round" stroke-linejoin="round"/>"#,
+            Points(points),
+            Color(color),
+            Number(width)
+        );
+    }
+
+    /// Draws everything that `draw` adds to the document moved by
+    /// `offset`.
+    pub fn group<F: FnOnce(&mut Self)>(&mut self, offset: Point, draw: F) {
+        let _ = writeln!(
+            self.body,
+            r#"<g transform="translate({} {})">"#,
+            Number(offset[0]),
+            Number(offset[1])
+        );
+        draw(self);
+        self.body.push_str("</g>\n");
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        Ok(std::fs::write(path, self.to_string())?)
+    }
+}
+
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+            w = self.width,
+            h = self.height
+        )?;
+        if let Some(metadata) = self.metadata.as_ref() {
+            writeln!(f, "<metadata>{}</metadata>", Escaped(metadata))?;
+        }
+        f.write_str(&self.body)?;
+        f.write_str("</svg>\n")
+    }
+}
+
+/// A number with no more than two decimal places, and without any
+/// trailing zeros.
+struct Number(f32);
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = format!("{:.2}", self.0);
+        let text = text.trim_end_matches('0').trim_end_matches('.');
+        match text {
+            "-0" => f.write_str("0"),
+            _ => f.write_str(text),
+        }
+    }
+}
+
+struct Points<'a>(&'a [Point]);
+
+impl fmt::Display for Points<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, [x, y]) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{},{}", Number(*x), Number(*y))?;
+        }
+        Ok(())
+    }
+}
+
+struct Color(image::Rgb<u8>);
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.0[0], self.0[1], self.0[2])
+    }
+}
+
+/// Text with the characters that mean something in XML escaped.
+struct Escaped<'a>(&'a str);
+
+impl fmt::Display for Escaped<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '&' => f.write_str("&amp;")?,
+                '<' => f.write_str("&lt;")?,
+                '>' => f.write_str("&gt;")?,
+                _ => f.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document() {
+        let mut document = Document::new(4, 3, crate::hex(0x102030));
+        document.rect([1.0, 0.5], [2.0, 1.0 / 3.0], crate::hex(0xffffff));
+        document.set_metadata("a < b");
+        let text = document.to_string();
+        assert!(text.starts_with("<svg "));
+        assert!(text.contains(r#"viewBox="0 0 4 3""#));
+        assert!(text.contains(r##"<rect x="0" y="0" width="4" height="3" fill="#102030""##));
+        assert!(text.contains(r##"<rect x="1" y="0.5" width="2" height="0.33" fill="#ffffff""##));
+        assert!(text.contains("<metadata>a &lt; b</metadata>"));
+    }
+}